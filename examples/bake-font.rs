@@ -0,0 +1,22 @@
+/*!
+ * This example turns a TrueType font into an image font that simple can load without SDL2_ttf.
+ *
+ *     cargo run --example bake-font --features ttf -- font.ttf 16 out.png
+ */
+
+extern crate simple;
+use simple::font;
+
+const CHARSET: &str =
+    " abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,!?-+/():;%&`'*#=[]\"";
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("usage: bake-font <font.ttf> <point size> <out.png>");
+        std::process::exit(1);
+    }
+    let size: u16 = args[2].parse().expect("the point size must be a number");
+    font::bake_image_font(&args[1], size, CHARSET, &args[3]).unwrap();
+    println!("wrote {} with the charset:\n{}", args[3], CHARSET);
+}
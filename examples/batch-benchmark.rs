@@ -0,0 +1,66 @@
+/*!
+ * This example measures how long it takes to draw a map of 19,200 small tiles with batching on
+ * and with it off. Neighbouring tiles mostly share a color, so with batching on most of them are
+ * drawn together in a handful of calls to SDL.
+ */
+
+use std::time::{Duration, Instant};
+
+extern crate simple;
+use simple::*;
+
+const ITERATIONS: u32 = 20;
+const TILE: u32 = 4;
+const COLUMNS: i32 = 160;
+const ROWS: i32 = 120;
+
+/// Draw the map: bands of grass, sand and water, each a run of tiles in one color.
+fn draw_map(app: &mut Window) {
+    for row in 0..ROWS {
+        for column in 0..COLUMNS {
+            match (row + column / 40) % 12 {
+                0..=5 => app.set_color(40, 160, 60, 255),
+                6..=8 => app.set_color(220, 200, 120, 255),
+                _ => app.set_color(40, 90, 200, 255),
+            }
+            let (x, y) = (column * TILE as i32, row * TILE as i32);
+            app.fill_rect(Rect::new(x, y, TILE, TILE));
+        }
+    }
+}
+
+/// Return how long drawing the map takes on average. Reading a pixel back waits for the GPU to
+/// finish, so the time includes the drawing itself and not only the calls that ask for it.
+fn time_map(app: &mut Window) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        draw_map(app);
+        app.read_pixels(Some(Rect::new(0, 0, 1, 1))).unwrap();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let mut app = Window::new("Batch Benchmark", 640, 480);
+
+    app.set_batching(false);
+    let unbatched = time_map(&mut app);
+    app.set_batching(true);
+    let batched = time_map(&mut app);
+    let message = format!(
+        "{} tiles: {:?} per map unbatched, {:?} batched",
+        COLUMNS * ROWS,
+        unbatched,
+        batched
+    );
+    println!("{}", message);
+
+    while app.next_frame() {
+        app.clear();
+        draw_map(&mut app);
+        app.set_color(0, 0, 0, 255);
+        app.fill_rect(Rect::new(0, 0, 640, 24));
+        app.set_color(255, 255, 255, 255);
+        app.print(&message, 4, 4);
+    }
+}
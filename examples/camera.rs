@@ -0,0 +1,74 @@
+/*!
+ * This example scrolls around a level that is much bigger than the Window. Move the square with
+ * the arrow keys; the camera follows it smoothly and stops at the edges of the level.
+ */
+
+extern crate simple;
+use simple::camera::Follow;
+use simple::{Key, Rect, Window};
+
+const LEVEL_WIDTH: u32 = 2400;
+const LEVEL_HEIGHT: u32 = 1600;
+const TILE: u32 = 80;
+const SPEED: f32 = 400.0; // pixels per second
+
+fn main() {
+    let mut app = Window::new("Camera", 640, 480);
+    let (w, h) = app.drawable_size();
+
+    let mut camera = Follow::new(w, h);
+    camera.set_stiffness(6.0);
+    camera.set_dead_zone(Some(Rect::new(w as i32 / 3, h as i32 / 3, w / 3, h / 3)));
+    camera.set_bounds(Some(Rect::new(0, 0, LEVEL_WIDTH, LEVEL_HEIGHT)));
+
+    let mut player = (LEVEL_WIDTH as f32 / 2.0, LEVEL_HEIGHT as f32 / 2.0);
+    camera.snap_to(player);
+
+    while app.next_frame() {
+        while app.has_event() {
+            app.next_event();
+        }
+
+        let dt = app.frame_time() as f32 / 1000.0;
+        let mut step = |key, dx: f32, dy: f32| {
+            if app.is_key_down(key) {
+                player.0 = (player.0 + dx * SPEED * dt).clamp(0.0, LEVEL_WIDTH as f32);
+                player.1 = (player.1 + dy * SPEED * dt).clamp(0.0, LEVEL_HEIGHT as f32);
+            }
+        };
+        step(Key::Left, -1.0, 0.0);
+        step(Key::Right, 1.0, 0.0);
+        step(Key::Up, 0.0, -1.0);
+        step(Key::Down, 0.0, 1.0);
+        camera.update(player, dt);
+
+        app.clear();
+
+        // the world, in world coordinates
+        camera.apply(&mut app);
+        for ty in 0..LEVEL_HEIGHT / TILE {
+            for tx in 0..LEVEL_WIDTH / TILE {
+                let shade = if (tx + ty) % 2 == 0 { 60 } else { 90 };
+                app.set_color(shade, shade, shade + 40, 255);
+                app.fill_rect(Rect::new(
+                    (tx * TILE) as i32,
+                    (ty * TILE) as i32,
+                    TILE,
+                    TILE,
+                ));
+            }
+        }
+        app.set_color(255, 200, 0, 255);
+        app.fill_rect(Rect::new(
+            player.0 as i32 - 16,
+            player.1 as i32 - 16,
+            32,
+            32,
+        ));
+
+        // the HUD, in screen coordinates
+        app.set_draw_offset(0, 0);
+        app.set_color(255, 255, 255, 255);
+        app.print("arrow keys to move", 10, 10);
+    }
+}
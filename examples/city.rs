@@ -6,7 +6,7 @@ extern crate simple;
 
 fn main() {
     let mut window = simple::Window::new_fullscreen("Los Angeles");
-    let mut pic = window.load_image(include_bytes!("city.jpg")).unwrap();
+    let pic = window.load_image(include_bytes!("city.jpg")).unwrap();
 
     let mut frame_number: u64 = 0;
 
@@ -17,7 +17,7 @@ fn main() {
         let color = (sine * 255f32) as u8;
 
         window.set_color(100 + color / 3, color, 255 - color, 255);
-        window.draw_image(&mut pic, 0, 0);
+        window.draw_image(&pic, 0, 0);
 
         frame_number += 1;
     }
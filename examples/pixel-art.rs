@@ -0,0 +1,55 @@
+/*!
+ * This example draws a small sprite moving slowly across a 160x120 pixel-art scene. Resize the
+ * Window: the scene is always scaled by a whole number, so every pixel stays square and the sprite
+ * never shimmers.
+ */
+
+extern crate simple;
+use simple::{Rect, Window};
+
+const SPRITE: [&str; 16] = [
+    "......####......",
+    "....########....",
+    "...##########...",
+    "..###..##..###..",
+    "..###..##..###..",
+    ".##############.",
+    ".##############.",
+    ".###.######.###.",
+    ".####......####.",
+    "..###########...",
+    "...#########....",
+    "....#######.....",
+    ".....#...#......",
+    "....##...##.....",
+    "...###...###....",
+    "................",
+];
+
+fn main() {
+    let mut app = Window::new("Pixel Art", 640, 480);
+    app.enable_pixel_perfect(160, 120).unwrap();
+
+    let mut x = 0.0;
+    while app.next_frame() {
+        while app.has_event() {
+            app.next_event();
+        }
+        // a quarter of a design pixel per frame, drawn at whole design pixels
+        x = (x + 0.25) % 160.0;
+
+        app.clear_to_color(40, 40, 60);
+        app.set_color(90, 160, 90, 255);
+        app.fill_rect(Rect::new(0, 100, 160, 20));
+
+        app.set_color(255, 220, 120, 255);
+        for (row, line) in SPRITE.iter().enumerate() {
+            for (column, pixel) in line.chars().enumerate() {
+                if pixel == '#' {
+                    let px = x as i32 + column as i32;
+                    app.fill_rect(Rect::new(px, 84 + row as i32, 1, 1));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/*!
+ * This example measures how long it takes to print a 10,000 character line that is almost
+ * entirely off-screen. Glyphs outside the window are culled, so this should take about as long as
+ * printing just the visible part.
+ */
+
+use std::time::Instant;
+
+extern crate simple;
+use simple::*;
+
+const ITERATIONS: u32 = 100;
+
+fn main() {
+    let mut app = Window::new("Print Benchmark", 640, 480);
+    let text = "The quick brown fox jumps over the lazy dog. ".repeat(223);
+    let text = &text[..10_000];
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        app.print(text, -5000, 32);
+    }
+    let per_print = start.elapsed() / ITERATIONS;
+    let message = format!("{} chars per print, {:?} per print", text.len(), per_print);
+    println!("{}", message);
+
+    while app.next_frame() {
+        app.clear();
+        app.print(text, -5000, 32);
+        app.print(&message, 32, 64);
+    }
+}
@@ -0,0 +1,47 @@
+/*!
+ * This example shows how precisely key presses are timed. Tap the space bar to a steady beat:
+ * the time between the last few taps is shown, to the millisecond, along with how much it varies.
+ */
+
+extern crate simple;
+use simple::*;
+
+const HISTORY: usize = 8;
+
+fn main() {
+    let mut app = Window::new("Rhythm", 480, 240);
+    let mut taps: Vec<f64> = vec![];
+
+    while app.next_frame() {
+        while app.has_event() {
+            app.next_event();
+        }
+        for time in app.key_press_times(Key::Space) {
+            taps.push(time);
+            if taps.len() > HISTORY + 1 {
+                taps.remove(0);
+            }
+        }
+
+        app.clear_to_color(20, 20, 30);
+        app.set_color(255, 255, 255, 255);
+        app.print("Tap the space bar to a steady beat", 15, 15);
+
+        let intervals: Vec<f64> = taps.windows(2).map(|t| (t[1] - t[0]) * 1000.0).collect();
+        for (i, interval) in intervals.iter().enumerate() {
+            app.print(&format!("{:.0} ms", interval), 15, 50 + 18 * i as i32);
+        }
+        if intervals.len() > 1 {
+            let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+            let spread = intervals
+                .iter()
+                .map(|i| (i - mean).abs())
+                .fold(0.0, f64::max);
+            app.print(
+                &format!("average {:.0} ms, furthest off {:.0} ms", mean, spread),
+                200,
+                50,
+            );
+        }
+    }
+}
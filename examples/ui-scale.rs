@@ -0,0 +1,44 @@
+/*!
+ * This example sizes its whole user interface by `Window::suggested_ui_scale`, so that the text
+ * and buttons are comfortable to read on a high-DPI laptop as well as on an ordinary monitor.
+ */
+
+extern crate simple;
+use simple::{Rect, Window};
+
+fn main() {
+    let mut app = Window::new("UI Scale", 800, 600);
+    let scale = app.suggested_ui_scale();
+    let px = |n: i32| (n as f32 * scale).round() as i32;
+
+    let dpi = match app.display_dpi() {
+        Some((_, hdpi, _)) => format!("display: {:.0} dpi", hdpi),
+        None => "display: dpi unknown".to_string(),
+    };
+    let labels = ["New game", "Load game", "Options", "Quit"];
+
+    while app.next_frame() {
+        while app.has_event() {
+            app.next_event();
+        }
+
+        app.clear();
+        app.set_color(255, 255, 255, 255);
+        app.print_scaled(&format!("UI scale {}", scale), px(16), px(16), scale);
+        app.print_scaled(&dpi, px(16), px(40), scale);
+
+        for (i, label) in labels.iter().enumerate() {
+            let button = Rect::new(
+                px(16),
+                px(80 + 40 * i as i32),
+                px(160) as u32,
+                px(32) as u32,
+            );
+            app.set_color(60, 60, 120, 255);
+            app.fill_rect(button);
+            app.set_color(255, 255, 255, 255);
+            app.draw_rect(button);
+            app.print_scaled(label, button.x() + px(8), button.y() + px(8), scale);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+/*!
+ * Texture atlases pack many small images into a few large textures, so that drawing them doesn't
+ * mean switching textures for every sprite.
+ */
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+extern crate sdl2;
+use crate::error::{self, SimpleError};
+use crate::pack::ShelfPacker;
+use crate::shape;
+use crate::texture;
+use crate::util;
+use crate::window::Window;
+use sdl2::image::ImageRWops;
+use sdl2::image::LoadSurface;
+use sdl2::rwops;
+use sdl2::surface;
+
+/// The default largest page an AtlasBuilder will create, in pixels along each side. Nearly every
+/// GPU supports textures at least this large.
+const DEFAULT_MAX_TEXTURE_SIZE: u32 = 2048;
+
+/// Where the pixels of an image in the atlas come from.
+enum Source<'a> {
+    Bytes(&'a [u8]),
+    File(PathBuf),
+}
+
+/// Decode the image into an RGBA Surface that owns its pixels, ready to be copied onto a page.
+fn decode(source: &Source) -> Result<surface::Surface<'static>, String> {
+    let rwops;
+    let mut decoded: surface::Surface = match *source {
+        Source::Bytes(data) => {
+            rwops = rwops::RWops::from_bytes(data)?;
+            rwops.load()?
+        }
+        Source::File(ref filename) => LoadSurface::from_file(filename)?,
+    };
+    util::to_rgba_surface(&mut decoded)
+}
+
+/**
+ * AtlasBuilder collects named images and packs them into an Atlas.
+ *
+ * Images are packed onto shelves, tallest first. When a page fills up (see
+ * `set_max_texture_size`), packing continues on a new page instead of failing. Only an image that
+ * is larger than a whole page is an error.
+ */
+pub struct AtlasBuilder<'a> {
+    sources: Vec<(String, Source<'a>)>,
+    max_texture_size: u32,
+}
+
+impl<'a> Default for AtlasBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> AtlasBuilder<'a> {
+    pub fn new() -> Self {
+        AtlasBuilder {
+            sources: vec![],
+            max_texture_size: DEFAULT_MAX_TEXTURE_SIZE,
+        }
+    }
+
+    /// Set the largest width and height a single page of the Atlas may have.
+    pub fn set_max_texture_size(&mut self, size: u32) {
+        self.max_texture_size = size;
+    }
+
+    /// Add an image from a slice of bytes, like `Window::load_image`.
+    pub fn add_image(&mut self, name: &str, data: &'a [u8]) {
+        self.sources.push((name.to_string(), Source::Bytes(data)));
+    }
+
+    /// Add the image at the path you specify, like `Window::load_image_from_file`.
+    pub fn add_image_from_file(&mut self, name: &str, filename: impl AsRef<Path>) {
+        self.sources.push((
+            name.to_string(),
+            Source::File(filename.as_ref().to_path_buf()),
+        ));
+    }
+
+    /// Decode every image, pack them and upload the pages to the GPU.
+    pub fn build(&self, window: &Window) -> Result<Atlas, SimpleError> {
+        let mut surfaces = Vec::with_capacity(self.sources.len());
+        for (name, source) in &self.sources {
+            if surfaces.iter().any(|(n, _): &(&String, _)| *n == name) {
+                return Err(SimpleError::InvalidArgument(format!(
+                    "atlas image name {:?} is used twice",
+                    name
+                )));
+            }
+            let surf = match *source {
+                Source::File(ref path) => error::check_readable(path)
+                    .and_then(|()| decode(source).map_err(SimpleError::ImageLoad))
+                    .map_err(|e| e.in_file(path))?,
+                Source::Bytes(_) => decode(source).map_err(SimpleError::ImageLoad)?,
+            };
+            surfaces.push((name, surf));
+        }
+
+        // tallest first makes for much fuller shelves
+        let mut order: Vec<usize> = (0..surfaces.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(surfaces[i].1.height()));
+
+        let mut packers: Vec<ShelfPacker> = vec![];
+        let mut placements: Vec<(usize, shape::Rect)> =
+            vec![(0, shape::Rect::new(0, 0, 1, 1)); surfaces.len()];
+        for i in order {
+            let (w, h) = surfaces[i].1.size();
+            if w > self.max_texture_size || h > self.max_texture_size {
+                return Err(SimpleError::InvalidArgument(format!(
+                    "atlas image {:?} ({}x{}) is larger than the maximum texture size {}",
+                    surfaces[i].0, w, h, self.max_texture_size
+                )));
+            }
+            let placed = packers
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, packer)| packer.insert(w, h).map(|rect| (page, rect)));
+            placements[i] = match placed {
+                Some(p) => p,
+                None => {
+                    let mut packer = ShelfPacker::new(self.max_texture_size, self.max_texture_size);
+                    let rect = packer.insert(w, h).unwrap();
+                    packers.push(packer);
+                    (packers.len() - 1, rect)
+                }
+            };
+        }
+
+        let mut pages = Vec::with_capacity(packers.len());
+        for (page, packer) in packers.iter().enumerate() {
+            let (used_w, used_h) = packer.used_size();
+            let mut page_surf = util::new_rgba_surface(used_w, used_h)?;
+            for (i, &(p, rect)) in placements.iter().enumerate() {
+                if p == page {
+                    surfaces[i].1.blit(None, &mut page_surf, rect)?;
+                }
+            }
+            pages.push(window.texture_from_surface(&page_surf)?);
+        }
+
+        let regions = surfaces
+            .iter()
+            .zip(placements)
+            .map(|((name, _), (page, rect))| ((*name).clone(), AtlasRegion { page, rect }))
+            .collect();
+        Ok(Atlas { pages, regions })
+    }
+}
+
+/**
+ * Atlas is a set of textures with named regions, built by AtlasBuilder. Draw a region with
+ * `Window::draw_region`.
+ */
+pub struct Atlas {
+    pub(crate) pages: Vec<texture::Texture>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl Atlas {
+    /// Return the region of the image that was added under `name`.
+    pub fn region(&self, name: &str) -> Option<AtlasRegion> {
+        self.regions.get(name).copied()
+    }
+
+    /// Return the number of textures the images were packed into.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// AtlasRegion is the location of one image inside an Atlas.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AtlasRegion {
+    pub(crate) page: usize,
+    pub(crate) rect: shape::Rect,
+}
+
+impl AtlasRegion {
+    pub fn get_width(&self) -> u32 {
+        self.rect.width()
+    }
+    pub fn get_height(&self) -> u32 {
+        self.rect.height()
+    }
+}
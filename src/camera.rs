@@ -0,0 +1,189 @@
+/*!
+ * Cameras for worlds that are bigger than the Window.
+ *
+ * A camera is just a position in the world. Applying it sets the Window's draw offset (see
+ * `Window::set_draw_offset`), so the rest of the program keeps drawing in world coordinates.
+ */
+
+use crate::shape;
+use crate::window::Window;
+
+/// How quickly a new Follow catches up with its target, per second. See `Follow::set_stiffness`.
+const DEFAULT_STIFFNESS: f32 = 8.0;
+
+/**
+ * Follow is a camera that smoothly follows a target, such as the player.
+ *
+ * Smoothing is exponential and takes the length of the frame into account, so the camera moves
+ * the same way at 30 frames per second as it does at 144. Call `update` once per frame with the
+ * position of the target and the time the frame took, then `apply` before drawing the world.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Follow {
+    // top-left corner of the view, in world coordinates
+    x: f32,
+    y: f32,
+    view_width: u32,
+    view_height: u32,
+    stiffness: f32,
+    dead_zone: Option<shape::Rect>,
+    bounds: Option<shape::Rect>,
+}
+
+impl Follow {
+    /// Create a camera showing `view_width` by `view_height` pixels of the world, usually the size
+    /// of the Window (or of a viewport), with its top-left corner at the world origin.
+    pub fn new(view_width: u32, view_height: u32) -> Self {
+        Follow {
+            x: 0.0,
+            y: 0.0,
+            view_width,
+            view_height,
+            stiffness: DEFAULT_STIFFNESS,
+            dead_zone: None,
+            bounds: None,
+        }
+    }
+
+    /// Set how quickly the camera catches up with the target. Each second, the distance left to go
+    /// shrinks by a factor of `e^stiffness`, so higher is snappier. Zero or less makes the camera
+    /// jump straight to the target.
+    pub fn set_stiffness(&mut self, per_second: f32) {
+        self.stiffness = per_second;
+    }
+
+    /// Let the target move around inside `zone` without the camera reacting. `zone` is measured
+    /// from the top-left corner of the view. When the target leaves it, the camera moves just far
+    /// enough to bring the target back to its edge. Without a dead zone, the camera keeps the
+    /// target in the center of the view.
+    pub fn set_dead_zone(&mut self, zone: Option<shape::Rect>) {
+        self.dead_zone = zone;
+    }
+
+    /// Keep the view inside `bounds`, usually the whole level, so nothing outside it is ever shown.
+    /// If the level is smaller than the view along an axis, the level is centered along that axis.
+    pub fn set_bounds(&mut self, bounds: Option<shape::Rect>) {
+        self.bounds = bounds;
+        let (x, y) = self.clamp(self.x, self.y);
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Return the top-left corner of the view, in world coordinates.
+    pub fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    /// Move the camera to where it wants to be for `target` immediately, without smoothing. Use
+    /// this when a level starts or the player teleports.
+    pub fn snap_to(&mut self, target: (f32, f32)) {
+        let (x, y) = self.desired(target);
+        let (x, y) = self.clamp(x, y);
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Move the camera towards `target`, a position in the world, for a frame that took `dt`
+    /// seconds.
+    pub fn update(&mut self, target: (f32, f32), dt: f32) {
+        let (want_x, want_y) = self.desired(target);
+        let t = if self.stiffness > 0.0 {
+            1.0 - (-self.stiffness * dt.max(0.0)).exp()
+        } else {
+            1.0
+        };
+        let (x, y) = self.clamp(
+            self.x + (want_x - self.x) * t,
+            self.y + (want_y - self.y) * t,
+        );
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Set the Window's draw offset so that drawing in world coordinates shows this camera's view.
+    pub fn apply(&self, window: &mut Window) {
+        window.set_draw_offset(-self.x.round() as i32, -self.y.round() as i32);
+    }
+
+    /// Where the top-left corner of the view should be to show `target` properly.
+    fn desired(&self, target: (f32, f32)) -> (f32, f32) {
+        let zone = match self.dead_zone {
+            Some(zone) => zone,
+            None => {
+                return (
+                    target.0 - self.view_width as f32 / 2.0,
+                    target.1 - self.view_height as f32 / 2.0,
+                );
+            }
+        };
+        let axis = |camera: f32, target: f32, start: i32, length: u32| {
+            let (low, high) = (camera + start as f32, camera + start as f32 + length as f32);
+            if target < low {
+                camera - (low - target)
+            } else if target > high {
+                camera + (target - high)
+            } else {
+                camera
+            }
+        };
+        (
+            axis(self.x, target.0, zone.x(), zone.width()),
+            axis(self.y, target.1, zone.y(), zone.height()),
+        )
+    }
+
+    /// Keep the view inside the bounds, if there are any.
+    fn clamp(&self, x: f32, y: f32) -> (f32, f32) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return (x, y),
+        };
+        let axis = |camera: f32, start: i32, length: u32, view: u32| {
+            let (start, length, view) = (start as f32, length as f32, view as f32);
+            if length <= view {
+                start + (length - view) / 2.0
+            } else {
+                camera.max(start).min(start + length - view)
+            }
+        };
+        (
+            axis(x, bounds.x(), bounds.width(), self.view_width),
+            axis(y, bounds.y(), bounds.height(), self.view_height),
+        )
+    }
+}
+
+#[test]
+fn test_follow_is_frame_rate_independent() {
+    let run = |fps: u32| {
+        let mut camera = Follow::new(100, 100);
+        for _ in 0..fps {
+            camera.update((1000.0, 500.0), 1.0 / fps as f32);
+        }
+        camera.position()
+    };
+    let (slow, fast) = (run(30), run(144));
+    assert!((slow.0 - fast.0).abs() < 0.01 && (slow.1 - fast.1).abs() < 0.01);
+
+    // after a second at stiffness 8, almost all of the way there
+    assert!((slow.0 - 950.0).abs() < 1.0);
+}
+
+#[test]
+fn test_follow_bounds_and_dead_zone() {
+    let mut camera = Follow::new(100, 50);
+    camera.set_bounds(Some(shape::Rect::new(0, 0, 400, 40)));
+    camera.snap_to((390.0, 0.0));
+    // can't show past the right edge; the level is shorter than the view, so it is centered
+    assert!(camera.position() == (300.0, -5.0));
+    camera.snap_to((-50.0, 0.0));
+    assert!(camera.position().0 == 0.0);
+
+    let mut camera = Follow::new(100, 100);
+    camera.set_stiffness(0.0);
+    camera.set_dead_zone(Some(shape::Rect::new(25, 25, 50, 50)));
+    camera.update((50.0, 50.0), 0.1);
+    assert!(camera.position() == (0.0, 0.0));
+    camera.update((80.0, 10.0), 0.1);
+    assert!(camera.position() == (5.0, -15.0));
+}
@@ -0,0 +1,331 @@
+/*!
+ * A drop-down debug console. Press the key left of 1 (backquote on a US keyboard) to open or close
+ * it. It shows recent log lines and runs commands that the program registers.
+ *
+ * The console, this module with it, is only compiled into debug builds. Turn on the `console`
+ * feature to keep it in release builds as well. Without the feature, calls to `Window::console`
+ * don't compile in release builds, and `simple::log!` does nothing there.
+ */
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+#[cfg(test)]
+use crate::event::key_press;
+use crate::event::{Event, Key};
+use crate::hook::HookResult;
+
+/// How many lines a new Console keeps. See `Console::set_scrollback`.
+const DEFAULT_SCROLLBACK: usize = 200;
+
+type Command = Box<dyn FnMut(&[&str]) -> String>;
+
+/**
+ * Console holds the debug console's log, its commands and what is being typed into it. Get the
+ * Window's console with `Window::console`.
+ *
+ * While the console is open it takes every keyboard and text-input event, so they never reach
+ * the event hooks or the event queue. `Window::is_key_down` still reports the keys as they are.
+ * The console is drawn over each frame as it is shown, so a program that only draws in response
+ * to `Window::wait_event` shows what was typed into it when it next draws.
+ */
+pub struct Console {
+    open: bool,
+    lines: VecDeque<String>,
+    scrollback: usize,
+    input: String,
+    history: Vec<String>,
+    // index into history while browsing it with the arrow keys
+    history_position: Option<usize>,
+    commands: Vec<(String, Command)>,
+    // the text and the release of the key press that last toggled the console are still to come
+    toggle_text_due: bool,
+    toggle_up_due: bool,
+}
+
+impl Console {
+    pub(crate) fn new() -> Self {
+        Console {
+            open: false,
+            lines: VecDeque::new(),
+            scrollback: DEFAULT_SCROLLBACK,
+            input: String::new(),
+            history: vec![],
+            history_position: None,
+            commands: vec![],
+            toggle_text_due: false,
+            toggle_up_due: false,
+        }
+    }
+
+    /// Add `text` to the end of the log, one line per line of `text`. The oldest lines are
+    /// forgotten once there are more than the scrollback allows.
+    pub fn log(&mut self, text: &str) {
+        for line in text.lines() {
+            self.lines.push_back(line.to_string());
+        }
+        self.trim();
+    }
+
+    /// Keep at most `lines` lines of log. The default is 200.
+    pub fn set_scrollback(&mut self, lines: usize) {
+        self.scrollback = lines;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.lines.len() > self.scrollback {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Return the lines of the log, oldest first.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(|line| line.as_str())
+    }
+
+    /// Make `handler` run when a line starting with the word `name` is entered. It is given the
+    /// rest of the words, and what it returns is logged. Registering a name again replaces its
+    /// handler.
+    pub fn register_command(
+        &mut self,
+        name: &str,
+        handler: impl FnMut(&[&str]) -> String + 'static,
+    ) {
+        match self.commands.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = Box::new(handler),
+            None => self.commands.push((name.to_string(), Box::new(handler))),
+        }
+    }
+
+    /// Run `line` as if it had been typed into the console: log it, then log what its command
+    /// returns. Blank lines do nothing.
+    pub fn run(&mut self, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            return;
+        };
+        self.log(&format!("> {}", line));
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+
+        let output = match self.commands.iter_mut().find(|(n, _)| n == name) {
+            Some((_, handler)) => handler(args),
+            None => {
+                let names: Vec<&str> = self.commands.iter().map(|(n, _)| n.as_str()).collect();
+                format!("unknown command {:?}; commands: {}", name, names.join(", "))
+            }
+        };
+        self.log(&output);
+    }
+
+    /// Return true if the console is showing.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Show or hide the console, as the backquote key does.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Return what has been typed on the command line so far.
+    pub(crate) fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Take an event if the console wants it: the key that toggles it, with the text and the
+    /// release that follow that press, and all keyboard and text input while it is open.
+    pub(crate) fn handle_event(&mut self, e: &Event) -> HookResult {
+        if self.is_toggle_echo(e) {
+            return HookResult::Consume;
+        }
+        match *e {
+            Event::Keyboard {
+                is_down: true,
+                key: Key::Grave,
+                ..
+            } => {
+                self.open = !self.open;
+                (self.toggle_text_due, self.toggle_up_due) = (true, true);
+                HookResult::Consume
+            }
+            _ if !self.open => HookResult::Pass,
+            Event::Keyboard {
+                is_down: true, key, ..
+            } => {
+                self.press(key);
+                HookResult::Consume
+            }
+            Event::Keyboard { .. } => HookResult::Consume,
+            Event::TextInput { ref text } => {
+                self.input.push_str(text);
+                HookResult::Consume
+            }
+            Event::TextEditing { .. } => HookResult::Consume,
+            _ => HookResult::Pass,
+        }
+    }
+
+    /// Return true if `e` is the text or the release of the key press that last toggled the
+    /// console. They arrive after it, when the console may already be closed, and are the
+    /// console's as well.
+    fn is_toggle_echo(&mut self, e: &Event) -> bool {
+        match *e {
+            Event::TextInput { ref text } => {
+                std::mem::take(&mut self.toggle_text_due) && text == "`"
+            }
+            Event::Keyboard {
+                is_down: false,
+                key: Key::Grave,
+                ..
+            } => std::mem::take(&mut self.toggle_up_due),
+            _ => false,
+        }
+    }
+
+    fn press(&mut self, key: Key) {
+        match key {
+            Key::Return | Key::KpEnter => {
+                let line = std::mem::take(&mut self.input);
+                self.history_position = None;
+                self.run(&line);
+            }
+            Key::Backspace => {
+                self.input.pop();
+            }
+            Key::Escape => self.open = false,
+            Key::Up if !self.history.is_empty() => {
+                let position = match self.history_position {
+                    Some(p) => p.saturating_sub(1),
+                    None => self.history.len() - 1,
+                };
+                self.history_position = Some(position);
+                self.input = self.history[position].clone();
+            }
+            Key::Down => match self.history_position {
+                Some(p) if p + 1 < self.history.len() => {
+                    self.history_position = Some(p + 1);
+                    self.input = self.history[p + 1].clone();
+                }
+                _ => {
+                    self.history_position = None;
+                    self.input.clear();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Move the lines logged with `log!` since the last call into this console.
+    pub(crate) fn collect_pending(&mut self) {
+        PENDING.with(|pending| {
+            for line in pending.borrow_mut().drain(..) {
+                self.log(&line);
+            }
+        });
+    }
+}
+
+thread_local! {
+    // lines logged with log!, waiting for the Window to collect them
+    static PENDING: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+}
+
+/// Queue a line logged with `log!` for the Window to collect.
+pub(crate) fn log_fmt(args: std::fmt::Arguments) {
+    PENDING.with(|pending| pending.borrow_mut().push(args.to_string()));
+}
+
+#[test]
+fn test_console_commands_and_history() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut console = Console::new();
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    console.register_command("add", move |args| {
+        counter.set(counter.get() + 1);
+        let sum: i32 = args.iter().filter_map(|a| a.parse::<i32>().ok()).sum();
+        sum.to_string()
+    });
+
+    // closed: keys go to the application
+    assert!(console.handle_event(&key_press(Key::A)) == HookResult::Pass);
+    assert!(console.handle_event(&key_press(Key::Grave)) == HookResult::Consume);
+    assert!(console.is_open());
+
+    let text = |text: &str| Event::TextInput {
+        text: text.to_string(),
+    };
+    assert!(console.handle_event(&text("`")) == HookResult::Consume);
+    assert!(console.handle_event(&text("add 2 3")) == HookResult::Consume);
+    assert!(console.input() == "add 2 3");
+    assert!(console.handle_event(&key_press(Key::Return)) == HookResult::Consume);
+    assert!(calls.get() == 1);
+    assert!(console.lines().collect::<Vec<_>>() == ["> add 2 3", "5"]);
+
+    console.run("nope");
+    assert!(console.lines().last() == Some("unknown command \"nope\"; commands: add"));
+
+    // up goes back through history, down comes forward to an empty line
+    console.handle_event(&key_press(Key::Up));
+    assert!(console.input() == "nope");
+    console.handle_event(&key_press(Key::Up));
+    console.handle_event(&key_press(Key::Up));
+    assert!(console.input() == "add 2 3");
+    console.handle_event(&key_press(Key::Down));
+    assert!(console.input() == "nope");
+    console.handle_event(&key_press(Key::Down));
+    assert!(console.input().is_empty());
+
+    assert!(console.handle_event(&Event::Quit) == HookResult::Pass);
+    console.handle_event(&key_press(Key::Escape));
+    assert!(!console.is_open());
+}
+
+#[test]
+fn test_console_toggle_key_stays_in_the_console() {
+    let mut console = Console::new();
+    let text = |text: &str| Event::TextInput {
+        text: text.to_string(),
+    };
+    let release = |key| Event::Keyboard {
+        is_down: false,
+        key,
+        logical: Some(key),
+    };
+    let toggle = [key_press(Key::Grave), text("`"), release(Key::Grave)];
+    let typing = [key_press(Key::A), text("a"), release(Key::A), text("b`c")];
+
+    // what SDL sends to open the console, type into it and close it again
+    let events = toggle.iter().chain(&typing).chain(&toggle);
+    let reached_app: Vec<&Event> = events
+        .filter(|e| console.handle_event(e) == HookResult::Pass)
+        .collect();
+    assert!(reached_app.is_empty());
+    assert!(!console.is_open());
+    assert!(console.input() == "ab`c");
+
+    // once closed, the same keys go to the application
+    assert!(
+        typing
+            .iter()
+            .all(|e| console.handle_event(e) == HookResult::Pass)
+    );
+}
+
+#[test]
+fn test_console_scrollback_and_log_macro() {
+    let mut console = Console::new();
+    console.set_scrollback(3);
+    console.log("one\ntwo");
+    console.log("three");
+    crate::log!("{}", "four");
+    console.collect_pending();
+    assert!(console.lines().collect::<Vec<_>>() == ["two", "three", "four"]);
+    console.collect_pending();
+    assert!(console.lines().count() == 3);
+}
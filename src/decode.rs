@@ -0,0 +1,95 @@
+/*!
+ * Decoding images and image fonts without a Window.
+ *
+ * Loading an asset has two halves: decoding the file into pixels, which is slow, and uploading the
+ * pixels to the GPU, which is fast. Only the upload needs the Window, and so the main thread. The
+ * functions here do the decoding, touch no Window state, and return plain data that can be sent
+ * between threads, so a loading screen can decode on every core at once and then hand the results
+ * to `Window::image_from_decoded` and `Window::font_from_decoded`.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+
+extern crate sdl2;
+use crate::error::SimpleError;
+use crate::font;
+use crate::shape;
+use crate::util;
+use sdl2::image::ImageRWops;
+use sdl2::image::LoadSurface;
+use sdl2::rwops;
+use sdl2::surface;
+
+/// DecodedImage is an image decoded into RGBA pixels, four bytes per pixel with no padding
+/// between rows, and not yet uploaded to the GPU. See `decode_image`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl DecodedImage {
+    fn from_surface(surf: &surface::SurfaceRef) -> Self {
+        DecodedImage {
+            width: surf.width(),
+            height: surf.height(),
+            pixels: util::rgba_surface_bytes(surf),
+        }
+    }
+}
+
+/// DecodedFont is an image font whose image has been decoded and whose glyphs have been found,
+/// but which is not yet uploaded to the GPU. See `decode_font`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DecodedFont {
+    pub(crate) image: DecodedImage,
+    pub(crate) glyphs: HashMap<char, shape::Rect>,
+}
+
+/// Decode PNG (or any other supported image) bytes into RGBA pixels. This is safe to call from any
+/// thread.
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage, SimpleError> {
+    let decode = || -> Result<DecodedImage, String> {
+        let rwops = rwops::RWops::from_bytes(bytes)?;
+        let mut surf: surface::Surface = rwops.load()?;
+        let rgba = util::to_rgba_surface(&mut surf)?;
+        Ok(DecodedImage::from_surface(&rgba))
+    };
+    decode().map_err(SimpleError::ImageLoad)
+}
+
+/// Decode an image font and find its glyphs, using the same rules as `Window::load_font`. This is
+/// safe to call from any thread.
+pub fn decode_font(bytes: &[u8], charset: &str) -> Result<DecodedFont, SimpleError> {
+    let surf = font::decode_font_image(bytes).map_err(SimpleError::FontParse)?;
+    let glyphs = font::parse_glyphs(&surf, charset).map_err(SimpleError::FontParse)?;
+    Ok(DecodedFont {
+        image: DecodedImage::from_surface(&surf),
+        glyphs,
+    })
+}
+
+/// Decode the image file at `path`, like `decode_image`.
+pub(crate) fn decode_image_file(path: &Path) -> Result<DecodedImage, String> {
+    let mut surf: surface::Surface = LoadSurface::from_file(path)?;
+    let rgba = util::to_rgba_surface(&mut surf)?;
+    Ok(DecodedImage::from_surface(&rgba))
+}
+
+#[test]
+fn test_decode_on_another_thread() {
+    let decoded = std::thread::spawn(|| {
+        let image = decode_image(font::DEFAULT_FONT_BYTES).unwrap();
+        let font = decode_font(font::DEFAULT_FONT_BYTES, font::DEFAULT_FONT_STR).unwrap();
+        (image, font)
+    })
+    .join()
+    .unwrap();
+    let (image, font) = decoded;
+    assert!(image.height == 17);
+    assert!(image.pixels.len() == (image.width * image.height * 4) as usize);
+    assert!(font.glyphs.len() == font::DEFAULT_FONT_STR.chars().count());
+    assert!(decode_image(b"not an image").is_err());
+}
@@ -0,0 +1,129 @@
+/*!
+ * The error type returned by everything in simple that can fail.
+ */
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// SimpleError says why an operation failed. Its `Display` is the same message the operation
+/// used to return as a `String`, so it can still be shown to the user as it is.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SimpleError {
+    /// SDL reported an error.
+    Sdl(String),
+    /// An image couldn't be read or decoded.
+    ImageLoad(String),
+    /// An image font couldn't be read or decoded, or doesn't match its charset.
+    FontParse(String),
+    /// Reading from a reader failed, or a file couldn't be opened.
+    Io(String),
+    /// Writing to a file failed.
+    Write(String),
+    /// The requested bytes lie outside the bundle. See `Window::load_image_at`.
+    OutOfRange {
+        offset: usize,
+        len: usize,
+        bundle_len: usize,
+    },
+    /// An argument was out of bounds, or named something that doesn't exist.
+    InvalidArgument(String),
+    /// The platform, or the version of SDL, can't do what was asked.
+    Unsupported(String),
+    /// Loading or saving the file at `path` failed because of `error`. The `Display` output
+    /// starts with the path, so batch loads say which file was at fault.
+    File {
+        path: PathBuf,
+        error: Box<SimpleError>,
+    },
+}
+
+impl SimpleError {
+    /// Wrap the error in a `File` error for `path`.
+    pub(crate) fn in_file(self, path: &Path) -> Self {
+        SimpleError::File {
+            path: path.to_path_buf(),
+            error: Box::new(self),
+        }
+    }
+}
+
+impl fmt::Display for SimpleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SimpleError::Sdl(ref e)
+            | SimpleError::ImageLoad(ref e)
+            | SimpleError::FontParse(ref e)
+            | SimpleError::InvalidArgument(ref e)
+            | SimpleError::Unsupported(ref e) => write!(f, "{}", e),
+            SimpleError::Io(ref e) => write!(f, "read failed: {}", e),
+            SimpleError::Write(ref e) => write!(f, "write failed: {}", e),
+            SimpleError::OutOfRange {
+                offset,
+                len,
+                bundle_len,
+            } => write!(
+                f,
+                "bytes {}..{} are outside of the {} byte bundle",
+                offset,
+                offset.saturating_add(len),
+                bundle_len
+            ),
+            SimpleError::File {
+                ref path,
+                ref error,
+            } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for SimpleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            SimpleError::File { ref error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Return an `Io` error if the file at `path` can't be opened for reading, so that a file that is
+/// missing or unreadable isn't reported as one that failed to decode.
+pub(crate) fn check_readable(path: &Path) -> Result<(), SimpleError> {
+    std::fs::File::open(path)
+        .map(drop)
+        .map_err(|e| SimpleError::Io(e.to_string()))
+}
+
+/// SDL reports its errors as Strings, so an unclassified String error is an SDL error.
+impl From<String> for SimpleError {
+    fn from(e: String) -> Self {
+        SimpleError::Sdl(e)
+    }
+}
+
+#[test]
+fn test_display_keeps_messages() {
+    let e = SimpleError::ImageLoad("Unsupported image format".to_string());
+    assert!(e.to_string() == "Unsupported image format");
+    let e = SimpleError::OutOfRange {
+        offset: 4,
+        len: 10,
+        bundle_len: 8,
+    };
+    assert!(e.to_string() == "bytes 4..14 are outside of the 8 byte bundle");
+    assert!(SimpleError::from("oops".to_string()) == SimpleError::Sdl("oops".to_string()));
+
+    let e = SimpleError::ImageLoad("Unsupported image format".to_string())
+        .in_file(Path::new("art/ship.png"));
+    assert!(e.to_string() == "art/ship.png: Unsupported image format");
+    assert!(std::error::Error::source(&e).unwrap().to_string() == "Unsupported image format");
+}
+
+#[test]
+fn test_check_readable() {
+    let path = std::env::temp_dir().join("simple-test-check-readable");
+    std::fs::write(&path, b"").unwrap();
+    assert!(check_readable(&path).is_ok());
+    std::fs::remove_file(&path).unwrap();
+    let e = check_readable(&path).err().unwrap();
+    assert!(matches!(e, SimpleError::Io(_)) && e.to_string().starts_with("read failed: "));
+}
@@ -1,17 +1,30 @@
 extern crate sdl2;
 use sdl2::event::Event as SDL_Event;
+use sdl2::event::WindowEvent;
 pub use sdl2::keyboard::Scancode as Key;
 pub use sdl2::mouse::MouseButton;
 
 /**
- * Event is an enumeration of the effects that a user can have on a running Window.
+ * Event is an enumeration of the effects that a user can have on a running Window, along with a
+ * few notifications from the Window itself.
  *
  * TODO: Add support for more events like touch events and window resizes.
  */
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub enum Event {
     /// Keyboard is either a keypress or a keyrelease. The `is_down` bool tells you which :)
-    Keyboard { is_down: bool, key: Key },
+    ///
+    /// `key` is the physical key, named after what it types on a US keyboard, whatever the layout:
+    /// use it for movement, so WASD stays in the same place on every keyboard. `logical` is the
+    /// key that types the same thing on a US keyboard as this one does with the user's layout, so
+    /// pressing the key labelled Z on a French keyboard gives `key: Key::W, logical: Some(Key::Z)`:
+    /// use it for mnemonic shortcuts like "Z to undo". It is `None` for keys that type something a
+    /// US keyboard has no key for.
+    Keyboard {
+        is_down: bool,
+        key: Key,
+        logical: Option<Key>,
+    },
 
     /// Mouse can be either a click or a click release. Refer to `is_down`. Note that the position
     /// of the mouse at the time of the click is listed. The mouse may have moved in the meantime,
@@ -23,6 +36,69 @@ pub enum Event {
         mouse_y: i32,
     },
 
+    /// MouseMotion is sent whenever the mouse moves inside the Window. `mouse_x` and `mouse_y` are
+    /// the new position, and `dx` and `dy` are the distance moved since the previous motion event.
+    MouseMotion {
+        mouse_x: i32,
+        mouse_y: i32,
+        dx: i32,
+        dy: i32,
+    },
+
+    /// The mouse pointer moved onto the Window. See also `Window::mouse_in_window`.
+    MouseEnter,
+
+    /// The mouse pointer left the Window, or another window took mouse focus.
+    MouseLeave,
+
+    /// Text was typed. Unlike `Keyboard`, this follows the keyboard layout, Shift and dead keys,
+    /// so it is what a text field should append. One key press can produce several characters,
+    /// or none.
+    TextInput { text: String },
+
+    /// An input method, such as one for Japanese or Chinese, is composing text that has not been
+    /// typed yet. `text` is the whole composition so far, to be shown at the caret until a
+    /// `TextInput` event replaces it; an empty `text` means the composition was cancelled.
+    /// `cursor` is where the input method's cursor is within `text`, and `selection_len` how many
+    /// characters after it are selected, both counted in characters.
+    TextEditing {
+        text: String,
+        cursor: usize,
+        selection_len: usize,
+    },
+
+    /// A game controller was plugged in, or was already connected when the Window was created.
+    /// `id` identifies it in `Window::controllers` and `Window::rumble`.
+    ControllerAdded { id: u32 },
+
+    /// A game controller was unplugged.
+    ControllerRemoved { id: u32 },
+
+    /// An image requested with `Window::load_image_async` has finished loading. Collect it with
+    /// `Window::take_image`, using the same `key`.
+    ImageLoaded { key: String },
+
+    /// An image requested with `Window::load_image_async` could not be loaded.
+    ImageLoadFailed { key: String, error: String },
+
+    /// The Window has been shown on screen, for example after being minimized.
+    Shown,
+
+    /// The Window has been hidden, for example by being minimized.
+    Hidden,
+
+    /// Part of the Window that was covered, by another window or by being off-screen, can be seen
+    /// again and has to be redrawn. Programs that only draw in response to `Window::wait_event`
+    /// should draw the whole frame again when this arrives.
+    Exposed,
+
+    /// The renderer has lost what was drawn on the render target Images made with
+    /// `Window::create_render_target`, for instance because the Window went fullscreen. SDL
+    /// reports a reset of the graphics device, which loses every texture on some platforms, the
+    /// same way. Until each one is drawn again with `Window::draw_to_image`, it draws as a
+    /// checkerboard.
+    RenderTargetsLost,
+
     /// The user has signaled to the OS that the application should be killed. This could happen
     /// through clicking the X in the corner of the window or using CMD-Q or Alt-F4 (depending on
     /// the platform).
@@ -38,17 +114,39 @@ impl Event {
             // Quit
             SDL_Event::Quit { .. } => Some(Event::Quit),
 
+            // sdl2 has no variants for the renderer's events
+            ref e if is_render_reset(e) => Some(Event::RenderTargetsLost),
+
             // Keyboard
             SDL_Event::KeyDown {
                 scancode: Some(key),
+                keycode,
                 ..
-            } => Some(Event::Keyboard { is_down: true, key }),
+            } => Some(Event::Keyboard {
+                is_down: true,
+                key,
+                logical: keycode.and_then(|k| key_for_keycode(k as i32)),
+            }),
             SDL_Event::KeyUp {
                 scancode: Some(key),
+                keycode,
                 ..
             } => Some(Event::Keyboard {
                 is_down: false,
                 key,
+                logical: keycode.and_then(|k| key_for_keycode(k as i32)),
+            }),
+
+            SDL_Event::TextInput { text, .. } => Some(Event::TextInput { text }),
+            SDL_Event::TextEditing {
+                text,
+                start,
+                length,
+                ..
+            } => Some(Event::TextEditing {
+                text,
+                cursor: start.max(0) as usize,
+                selection_len: length.max(0) as usize,
             }),
 
             // Mouse
@@ -74,12 +172,319 @@ impl Event {
                 mouse_x: x,
                 mouse_y: y,
             }),
+            SDL_Event::MouseMotion {
+                x, y, xrel, yrel, ..
+            } => Some(Event::MouseMotion {
+                mouse_x: x,
+                mouse_y: y,
+                dx: xrel,
+                dy: yrel,
+            }),
+
+            // Window
+            SDL_Event::Window { win_event, .. } => match win_event {
+                WindowEvent::Shown => Some(Event::Shown),
+                WindowEvent::Hidden => Some(Event::Hidden),
+                WindowEvent::Exposed => Some(Event::Exposed),
+                WindowEvent::Enter => Some(Event::MouseEnter),
+                WindowEvent::Leave => Some(Event::MouseLeave),
+                _ => None,
+            },
 
             _ => None,
         }
     }
 }
 
+/// Return true if `e` says the renderer has lost its render targets, or the device they were on.
+pub(crate) fn is_render_reset(e: &SDL_Event) -> bool {
+    use sdl2::sys::SDL_EventType::{SDL_RENDER_DEVICE_RESET, SDL_RENDER_TARGETS_RESET};
+    match *e {
+        SDL_Event::Unknown { type_, .. } => {
+            type_ == SDL_RENDER_TARGETS_RESET as u32 || type_ == SDL_RENDER_DEVICE_RESET as u32
+        }
+        _ => false,
+    }
+}
+
+/// Human-readable names for the keys that commonly appear in keybindings. The names are stable, so
+/// they are safe to store in configuration files.
+const KEY_NAMES: &[(Key, &str)] = &[
+    (Key::A, "A"),
+    (Key::B, "B"),
+    (Key::C, "C"),
+    (Key::D, "D"),
+    (Key::E, "E"),
+    (Key::F, "F"),
+    (Key::G, "G"),
+    (Key::H, "H"),
+    (Key::I, "I"),
+    (Key::J, "J"),
+    (Key::K, "K"),
+    (Key::L, "L"),
+    (Key::M, "M"),
+    (Key::N, "N"),
+    (Key::O, "O"),
+    (Key::P, "P"),
+    (Key::Q, "Q"),
+    (Key::R, "R"),
+    (Key::S, "S"),
+    (Key::T, "T"),
+    (Key::U, "U"),
+    (Key::V, "V"),
+    (Key::W, "W"),
+    (Key::X, "X"),
+    (Key::Y, "Y"),
+    (Key::Z, "Z"),
+    (Key::Num0, "0"),
+    (Key::Num1, "1"),
+    (Key::Num2, "2"),
+    (Key::Num3, "3"),
+    (Key::Num4, "4"),
+    (Key::Num5, "5"),
+    (Key::Num6, "6"),
+    (Key::Num7, "7"),
+    (Key::Num8, "8"),
+    (Key::Num9, "9"),
+    (Key::Space, "Space"),
+    (Key::Return, "Return"),
+    (Key::Escape, "Escape"),
+    (Key::Backspace, "Backspace"),
+    (Key::Tab, "Tab"),
+    (Key::Minus, "Minus"),
+    (Key::Equals, "Equals"),
+    (Key::LeftBracket, "LeftBracket"),
+    (Key::RightBracket, "RightBracket"),
+    (Key::Backslash, "Backslash"),
+    (Key::Semicolon, "Semicolon"),
+    (Key::Apostrophe, "Apostrophe"),
+    (Key::Grave, "Grave"),
+    (Key::Comma, "Comma"),
+    (Key::Period, "Period"),
+    (Key::Slash, "Slash"),
+    (Key::CapsLock, "CapsLock"),
+    (Key::Insert, "Insert"),
+    (Key::Delete, "Delete"),
+    (Key::Home, "Home"),
+    (Key::End, "End"),
+    (Key::PageUp, "PageUp"),
+    (Key::PageDown, "PageDown"),
+    (Key::Left, "LeftArrow"),
+    (Key::Right, "RightArrow"),
+    (Key::Up, "UpArrow"),
+    (Key::Down, "DownArrow"),
+    (Key::LShift, "LeftShift"),
+    (Key::RShift, "RightShift"),
+    (Key::LCtrl, "LeftCtrl"),
+    (Key::RCtrl, "RightCtrl"),
+    (Key::LAlt, "LeftAlt"),
+    (Key::RAlt, "RightAlt"),
+    (Key::LGui, "LeftGui"),
+    (Key::RGui, "RightGui"),
+    (Key::F1, "F1"),
+    (Key::F2, "F2"),
+    (Key::F3, "F3"),
+    (Key::F4, "F4"),
+    (Key::F5, "F5"),
+    (Key::F6, "F6"),
+    (Key::F7, "F7"),
+    (Key::F8, "F8"),
+    (Key::F9, "F9"),
+    (Key::F10, "F10"),
+    (Key::F11, "F11"),
+    (Key::F12, "F12"),
+    (Key::F13, "F13"),
+    (Key::F14, "F14"),
+    (Key::F15, "F15"),
+    (Key::F16, "F16"),
+    (Key::F17, "F17"),
+    (Key::F18, "F18"),
+    (Key::F19, "F19"),
+    (Key::F20, "F20"),
+    (Key::F21, "F21"),
+    (Key::F22, "F22"),
+    (Key::F23, "F23"),
+    (Key::F24, "F24"),
+];
+
+/// Return a human-readable name for `key`, such as `"Space"` or `"LeftArrow"`. Printable keys,
+/// arrows, modifiers and function keys use simple's own stable names. Any other key falls back to
+/// the name SDL gives it.
+///
+/// Note that `Key` is SDL's `Scancode`, so `Key::name()` also exists, but it uses SDL's spelling
+/// (`"Left"`, `"Left Alt"`). Use this function and `key_from_name` when round-tripping names.
+pub fn key_name(key: Key) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, name)| *name)
+        .unwrap_or_else(|| key.name())
+}
+
+/// Parse a name produced by `key_name` back into a `Key`. Matching is case-insensitive. Names that
+/// simple doesn't know are handed to SDL, so SDL's own key names are accepted too.
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|(k, _)| *k)
+        .or_else(|| Key::from_name(name))
+}
+
+/// SDL keycodes of keys that don't type a character are their scancode with this bit set.
+const SCANCODE_MASK: i32 = 1 << 30;
+
+/// The keys, other than letters and digits, whose keycode is a character.
+const CHARACTER_KEYS: &[(Key, char)] = &[
+    (Key::Return, '\r'),
+    (Key::Escape, '\u{1b}'),
+    (Key::Backspace, '\u{8}'),
+    (Key::Tab, '\t'),
+    (Key::Space, ' '),
+    (Key::Delete, '\u{7f}'),
+    (Key::Minus, '-'),
+    (Key::Equals, '='),
+    (Key::LeftBracket, '['),
+    (Key::RightBracket, ']'),
+    (Key::Backslash, '\\'),
+    (Key::Semicolon, ';'),
+    (Key::Apostrophe, '\''),
+    (Key::Grave, '`'),
+    (Key::Comma, ','),
+    (Key::Period, '.'),
+    (Key::Slash, '/'),
+];
+
+/// Return the character an SDL keycode stands for, if it stands for one. The keycodes of keys that
+/// type something are the character itself; every other key has the scancode bit set.
+pub(crate) fn keycode_char(keycode: i32) -> Option<char> {
+    if keycode & SCANCODE_MASK != 0 {
+        return None;
+    }
+    char::from_u32(keycode as u32).filter(|c| !c.is_control())
+}
+
+/// Return the Key that produces `keycode` on a US keyboard, or `None` if none does.
+pub(crate) fn key_for_keycode(keycode: i32) -> Option<Key> {
+    if keycode & SCANCODE_MASK != 0 {
+        return Key::from_i32(keycode & !SCANCODE_MASK);
+    }
+    let ch = char::from_u32(keycode as u32)?;
+    match ch {
+        'a'..='z' => Key::from_i32(Key::A as i32 + (ch as i32 - 'a' as i32)),
+        '0' => Some(Key::Num0),
+        '1'..='9' => Key::from_i32(Key::Num1 as i32 + (ch as i32 - '1' as i32)),
+        _ => CHARACTER_KEYS
+            .iter()
+            .find(|(_, c)| *c == ch)
+            .map(|(k, _)| *k),
+    }
+}
+
+/// Return the keycode that `key` produces on a US keyboard. This is the inverse of
+/// `key_for_keycode`.
+pub(crate) fn keycode_for_key(key: Key) -> i32 {
+    let (n, a, one) = (key as i32, Key::A as i32, Key::Num1 as i32);
+    if (a..=Key::Z as i32).contains(&n) {
+        return 'a' as i32 + (n - a);
+    }
+    if (one..=Key::Num9 as i32).contains(&n) {
+        return '1' as i32 + (n - one);
+    }
+    if key == Key::Num0 {
+        return '0' as i32;
+    }
+    match CHARACTER_KEYS.iter().find(|(k, _)| *k == key) {
+        Some((_, c)) => *c as i32,
+        None => n | SCANCODE_MASK,
+    }
+}
+
+/// Merge every run of consecutive `MouseMotion` events in `queue[start..]` into a single event
+/// carrying the final position and the summed deltas. Any other event acts as a barrier, so the
+/// ordering of motion relative to clicks and keys is preserved. Return how many events were merged
+/// away.
+pub(crate) fn coalesce_motion(queue: &mut Vec<Event>, start: usize) -> usize {
+    let tail: Vec<Event> = queue.drain(start..).collect();
+    let before = tail.len();
+    for e in tail {
+        if let Event::MouseMotion {
+            mouse_x,
+            mouse_y,
+            dx,
+            dy,
+        } = e
+            && queue.len() > start
+            && let Some(Event::MouseMotion {
+                mouse_x: last_x,
+                mouse_y: last_y,
+                dx: last_dx,
+                dy: last_dy,
+            }) = queue.last_mut()
+        {
+            *last_x = mouse_x;
+            *last_y = mouse_y;
+            *last_dx += dx;
+            *last_dy += dy;
+            continue;
+        }
+        queue.push(e);
+    }
+    before - (queue.len() - start)
+}
+
+/// Work out the mouse velocity in pixels per second from `(timestamp, x, y)` samples, oldest
+/// first, using the ones no more than `window` milliseconds older than `now`. With fewer than two
+/// such samples the mouse is taken to be still.
+pub(crate) fn mouse_velocity<I: Iterator<Item = (u32, i32, i32)>>(
+    samples: I,
+    now: u32,
+    window: u32,
+) -> (f32, f32) {
+    let mut recent = samples.filter(|&(t, _, _)| now.saturating_sub(t) <= window);
+    let (first, last) = match (recent.next(), recent.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return (0.0, 0.0),
+    };
+    // several samples in the same millisecond are still movement
+    let seconds = last.0.saturating_sub(first.0).max(1) as f32 / 1000.0;
+    (
+        (last.1 - first.1) as f32 / seconds,
+        (last.2 - first.2) as f32 / seconds,
+    )
+}
+
+/// Return false for events that must reach the application even when the queue overflows: `Quit`,
+/// and the completion of a request the application made.
+fn is_droppable(e: &Event) -> bool {
+    !matches!(
+        e,
+        Event::Quit | Event::ImageLoaded { .. } | Event::ImageLoadFailed { .. }
+    )
+}
+
+/// Shrink `queue` to at most `limit` events. Runs of `MouseMotion` are coalesced first, since they
+/// lose the least information; if the queue is still too long, the oldest events are dropped.
+/// `Quit` and load completions are never dropped. Return how many events were removed.
+pub(crate) fn enforce_queue_limit(queue: &mut Vec<Event>, limit: usize) -> usize {
+    if queue.len() <= limit {
+        return 0;
+    }
+
+    let mut dropped = coalesce_motion(queue, 0);
+    let mut excess = queue.len().saturating_sub(limit);
+    queue.retain(|e| {
+        if excess > 0 && is_droppable(e) {
+            excess -= 1;
+            dropped += 1;
+            false
+        } else {
+            true
+        }
+    });
+    dropped
+}
+
 #[test]
 fn test_from_sdl2_event() {
     fn test(input: SDL_Event, expected: Event) {
@@ -87,6 +492,250 @@ fn test_from_sdl2_event() {
     }
 
     test(SDL_Event::Quit { timestamp: 0 }, Event::Quit);
+    test(
+        SDL_Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Exposed,
+        },
+        Event::Exposed,
+    );
+    test(
+        SDL_Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Hidden,
+        },
+        Event::Hidden,
+    );
+    test(
+        SDL_Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Enter,
+        },
+        Event::MouseEnter,
+    );
+    test(
+        SDL_Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Leave,
+        },
+        Event::MouseLeave,
+    );
+    assert!(
+        Event::from_sdl2_event(SDL_Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Moved(3, 4),
+        })
+        .is_none()
+    );
+    test(
+        SDL_Event::TextInput {
+            timestamp: 0,
+            window_id: 1,
+            text: "日本".to_string(),
+        },
+        Event::TextInput {
+            text: "日本".to_string(),
+        },
+    );
+    test(
+        SDL_Event::TextEditing {
+            timestamp: 0,
+            window_id: 1,
+            text: "にほん".to_string(),
+            start: 2,
+            length: 1,
+        },
+        Event::TextEditing {
+            text: "にほん".to_string(),
+            cursor: 2,
+            selection_len: 1,
+        },
+    );
+    for type_ in [0x2000, 0x2001] {
+        test(
+            SDL_Event::Unknown {
+                timestamp: 0,
+                type_,
+            },
+            Event::RenderTargetsLost,
+        );
+    }
+    assert!(
+        Event::from_sdl2_event(SDL_Event::Unknown {
+            timestamp: 0,
+            type_: 0x2002,
+        })
+        .is_none()
+    );
 
     // TODO: Test more comprehensively.
 }
+
+#[cfg(test)]
+fn motion(x: i32, y: i32) -> Event {
+    Event::MouseMotion {
+        mouse_x: x,
+        mouse_y: y,
+        dx: 1,
+        dy: 2,
+    }
+}
+
+#[cfg(test)]
+fn click(is_down: bool) -> Event {
+    Event::Mouse {
+        is_down,
+        button: MouseButton::Left,
+        mouse_x: 0,
+        mouse_y: 0,
+    }
+}
+
+/// The event of `key` being pressed, for the tests of whatever handles key presses.
+#[cfg(test)]
+pub(crate) fn key_press(key: Key) -> Event {
+    Event::Keyboard {
+        is_down: true,
+        key,
+        logical: Some(key),
+    }
+}
+
+#[test]
+fn test_coalesce_motion() {
+    let mut queue = vec![
+        motion(1, 1),
+        motion(2, 2),
+        click(true),
+        motion(3, 3),
+        motion(4, 4),
+    ];
+    assert!(coalesce_motion(&mut queue, 0) == 2);
+    assert!(queue.len() == 3);
+    assert!(
+        queue[0]
+            == Event::MouseMotion {
+                mouse_x: 2,
+                mouse_y: 2,
+                dx: 2,
+                dy: 4,
+            }
+    );
+    assert!(queue[1] == click(true));
+    assert!(
+        queue[2]
+            == Event::MouseMotion {
+                mouse_x: 4,
+                mouse_y: 4,
+                dx: 2,
+                dy: 4,
+            }
+    );
+
+    // events before `start` are never merged, even if they are motion events
+    let mut queue = vec![motion(1, 1), motion(2, 2), motion(3, 3)];
+    assert!(coalesce_motion(&mut queue, 1) == 1);
+    assert!(queue.len() == 2);
+    assert!(queue[0] == motion(1, 1));
+}
+
+#[test]
+fn test_enforce_queue_limit() {
+    // under the limit: untouched
+    let mut queue = vec![motion(1, 1), motion(2, 2)];
+    assert!(enforce_queue_limit(&mut queue, 2) == 0);
+    assert!(queue.len() == 2);
+
+    // coalescing alone is enough, so no click is lost
+    let mut queue = vec![
+        click(true),
+        motion(1, 1),
+        motion(2, 2),
+        motion(3, 3),
+        click(false),
+    ];
+    assert!(enforce_queue_limit(&mut queue, 3) == 2);
+    assert!(queue.len() == 3);
+    assert!(queue[0] == click(true));
+    assert!(queue[2] == click(false));
+
+    // oldest events are dropped after coalescing, but Quit survives
+    let mut queue = vec![Event::Quit, click(true), click(false), click(true)];
+    assert!(enforce_queue_limit(&mut queue, 2) == 2);
+    assert!(queue.len() == 2);
+    assert!(queue[0] == Event::Quit);
+    assert!(queue[1] == click(true));
+}
+
+#[test]
+fn test_key_names() {
+    for &(key, name) in KEY_NAMES {
+        assert!(key_name(key) == name);
+        assert!(key_from_name(name) == Some(key));
+    }
+
+    assert!(key_name(Key::Left) == "LeftArrow");
+    assert!(key_from_name("space") == Some(Key::Space));
+    assert!(key_from_name("f12") == Some(Key::F12));
+    assert!(key_from_name("7") == Some(Key::Num7));
+}
+
+#[test]
+fn test_keycode_char() {
+    assert!(keycode_char('z' as i32) == Some('z'));
+    assert!(keycode_char('\u{e9}' as i32) == Some('\u{e9}'));
+    assert!(keycode_char(' ' as i32) == Some(' '));
+    assert!(keycode_char(sdl2::keyboard::Keycode::Escape as i32).is_none());
+    assert!(keycode_char(sdl2::keyboard::Keycode::Backspace as i32).is_none());
+    assert!(keycode_char(sdl2::keyboard::Keycode::Left as i32).is_none());
+    assert!(keycode_char(sdl2::keyboard::Keycode::F1 as i32).is_none());
+    assert!(keycode_char(0).is_none());
+}
+
+#[test]
+fn test_keycode_translation() {
+    use sdl2::keyboard::Keycode;
+    assert!(key_for_keycode(Keycode::Z as i32) == Some(Key::Z));
+    assert!(key_for_keycode(Keycode::Num0 as i32) == Some(Key::Num0));
+    assert!(key_for_keycode(Keycode::Num7 as i32) == Some(Key::Num7));
+    assert!(key_for_keycode(Keycode::Semicolon as i32) == Some(Key::Semicolon));
+    assert!(key_for_keycode(Keycode::Left as i32) == Some(Key::Left));
+    assert!(key_for_keycode(Keycode::KpEnter as i32) == Some(Key::KpEnter));
+    assert!(key_for_keycode('\u{e9}' as i32).is_none());
+
+    // every key survives the round trip, and agrees with SDL's own keycodes
+    let keys = KEY_NAMES.iter().map(|(k, _)| k);
+    for &key in keys.chain(CHARACTER_KEYS.iter().map(|(k, _)| k)) {
+        assert!(key_for_keycode(keycode_for_key(key)) == Some(key));
+    }
+    assert!(keycode_for_key(Key::A) == Keycode::A as i32);
+    assert!(keycode_for_key(Key::Return) == Keycode::Return as i32);
+    assert!(keycode_for_key(Key::F1) == Keycode::F1 as i32);
+}
+
+#[test]
+fn test_enforce_queue_limit_keeps_load_completions() {
+    let loaded = Event::ImageLoaded {
+        key: "a".to_string(),
+    };
+    let mut queue = vec![loaded.clone(), click(true), click(false)];
+    assert!(enforce_queue_limit(&mut queue, 1) == 2);
+    assert!(queue == vec![loaded]);
+}
+
+#[test]
+fn test_mouse_velocity() {
+    let samples = [(100, 0, 0), (110, 10, 0), (120, 20, -5)];
+    let v = mouse_velocity(samples.iter().copied(), 120, 50);
+    assert!(v == (1000.0, -250.0));
+
+    // the old samples have fallen out of the window
+    assert!(mouse_velocity(samples.iter().copied(), 165, 50) == (0.0, 0.0));
+    assert!(mouse_velocity(samples.iter().copied(), 1000, 50) == (0.0, 0.0));
+    assert!(mouse_velocity(std::iter::empty(), 0, 50) == (0.0, 0.0));
+}
@@ -0,0 +1,802 @@
+/*!
+ * Image fonts, and the parser that turns a specially formatted image into glyphs.
+ *
+ * The parser only needs pixels, not a Window, so `inspect_image_font` can check font assets in
+ * tests and build scripts using exactly the same rules as `Window::load_font`.
+ */
+
+use std::cell::Ref;
+use std::collections::HashMap;
+#[cfg(feature = "ttf")]
+use std::path::Path;
+use std::rc::Rc;
+
+extern crate sdl2;
+use crate::error::SimpleError;
+use crate::shape;
+use crate::texture::{self, Texture};
+use crate::util;
+use sdl2::image::ImageRWops;
+#[cfg(feature = "ttf")]
+use sdl2::image::SaveSurface;
+use sdl2::pixels;
+#[cfg(feature = "ttf")]
+use sdl2::render;
+use sdl2::rwops;
+use sdl2::surface;
+
+/// This is the default font.
+pub(crate) const DEFAULT_FONT_BYTES: &[u8] = include_bytes!("default_font.png");
+pub(crate) const DEFAULT_FONT_STR: &str =
+    " abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,!?-+/():;%&`'*#=[]\"";
+
+/**
+ * Font is a way to render text, loaded from a specially formatted image.
+ *
+ * Note that Font is not loaded from a TrueType file, but instead, from a specially formatted
+ * image. Loading from an image is a little faster and a little simpler and a little more portable,
+ * but has a couple disadvantages. For one, the font size is fixed by the file. To have two
+ * different font sizes, you have to create two different Fonts from two different files. Another
+ * disadvantage is that these special images are less widely available.
+ *
+ * This link describes how ImageFonts work: https://love2d.org/wiki/Tutorial:Fonts_and_Text
+ *
+ * Like an Image, a Font can be kept anywhere and dropped at any time, and can be given to a new
+ * Window with `Window::adopt_font`.
+ */
+pub struct Font {
+    pub(crate) texture: Texture,
+    // the same glyphs, with only their edges left, made the first time print_hollow needs them
+    pub(crate) hollow_texture: Option<Texture>,
+    pub(crate) chars: HashMap<char, shape::Rect>,
+    pub(crate) height: u32,
+    pub(crate) kerning: HashMap<(char, char), i32>,
+    // the RGBA pixels of the texture, and their width and height, once crisp text or print_hollow
+    // has needed them or the Window that made the texture has gone
+    pub(crate) pixels: Rc<texture::Pixels>,
+    // (texture, hollow texture) enlarged by each whole-number factor crisp text has needed so far
+    pub(crate) upscaled: HashMap<u32, (Texture, Option<Texture>)>,
+}
+
+impl Font {
+    /// Move `b` by `adjust` pixels whenever it follows `a`: a negative adjustment tucks a pair
+    /// like "AV" closer together. The adjustment is scaled along with the glyphs by
+    /// `print_scaled`, and every way of printing and measuring text takes it into account. An
+    /// adjustment of 0 removes the pair.
+    pub fn set_kerning(&mut self, a: char, b: char, adjust: i32) {
+        if adjust == 0 {
+            self.kerning.remove(&(a, b));
+        } else {
+            self.kerning.insert((a, b), adjust);
+        }
+    }
+
+    /// Return the kerning adjustment set for `b` following `a`, or 0 if there is none.
+    pub fn kerning(&self, a: char, b: char) -> i32 {
+        self.kerning.get(&(a, b)).copied().unwrap_or(0)
+    }
+
+    /// Determine whether "ch" exists in this Font.
+    pub fn is_printable(&self, ch: char) -> bool {
+        self.chars.contains_key(&ch)
+    }
+
+    /// Return the number of printable characters that the Font contains.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Returns `true` if the `chars` contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Return the height of the Font. This is constant for every possible character, while the
+    /// individual character widths vary. Note that certain characters (such a single quote `'`)
+    /// might not actually take up all of `height`. However, no character may exceed this limit.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return the RGBA pixels of the Font's texture, and their width and height, reading them back
+    /// from the texture the first time. They are kept from then on.
+    pub(crate) fn pixels(&self) -> Result<Ref<'_, (Vec<u8>, u32, u32)>, String> {
+        if self.pixels.borrow().is_none() {
+            let pixels = self.texture.read_back()?;
+            *self.pixels.borrow_mut() = Some(pixels);
+        }
+        Ok(Ref::map(self.pixels.borrow(), |pixels| {
+            pixels.as_ref().unwrap()
+        }))
+    }
+
+    /// Return the portion of the Font's texture that is used to draw the `char` you provide. If
+    /// the character can't be drawn by this Font, return None.
+    pub(crate) fn get_rect(&self, ch: char) -> Option<&shape::Rect> {
+        self.chars.get(&ch)
+    }
+
+    /// Return the average width of the glyphs in this Font, rounded, or 5 pixels if it has none.
+    pub(crate) fn average_width(&self) -> u32 {
+        if self.chars.is_empty() {
+            return 5;
+        }
+        let total: u32 = self.chars.values().map(|r| r.width()).sum();
+        (total as f32 / self.chars.len() as f32).round() as u32
+    }
+
+    /// Return the distance between tab stops when none has been set: four spaces.
+    fn default_tab_width(&self) -> u32 {
+        4 * self
+            .get_rect(' ')
+            .map_or(self.average_width(), |r| r.width())
+    }
+
+    /// Walk through `text` the way `print` lays it out, every glyph scaled by `scale` and followed
+    /// by `extra_px` more pixels of space (fewer if negative), except for the last one. Missing
+    /// glyphs and tabs are handled according to `rules`. `f` is given the texture region and
+    /// where the glyph goes, relative to the top-left corner of the string; the region is `None`
+    /// for a box standing in for a missing glyph. Return the total width of the string, which is
+    /// never negative.
+    pub(crate) fn layout_styled<F: FnMut(Option<&shape::Rect>, shape::Rect)>(
+        &self,
+        text: &str,
+        scale: f32,
+        extra_px: i32,
+        rules: LayoutRules,
+        mut f: F,
+    ) -> i32 {
+        self.layout_indexed(text, scale, extra_px, rules, |_, src, dst| f(src, dst))
+    }
+
+    /// Like `layout_styled`, but `f` is also given the index of the character each glyph is for.
+    pub(crate) fn layout_indexed<F: FnMut(usize, Option<&shape::Rect>, shape::Rect)>(
+        &self,
+        text: &str,
+        scale: f32,
+        extra_px: i32,
+        rules: LayoutRules,
+        mut f: F,
+    ) -> i32 {
+        let mut index = 0;
+        self.walk(text, scale, extra_px, rules, |glyph, _| {
+            if let Some((src, dst)) = glyph {
+                f(index, src, dst)
+            }
+            index += 1;
+        })
+    }
+
+    /// Return where the caret goes before each character of `text`, and after the last one, as an
+    /// offset from the start of the string printed with `print`. The result has one more entry
+    /// than `text` has characters.
+    pub(crate) fn caret_offsets(&self, text: &str, rules: LayoutRules) -> Vec<i32> {
+        let mut offsets = vec![0];
+        let width = self.walk(text, 1.0, 0, rules, |_, next| offsets.push(next));
+        if let Some(last) = offsets.last_mut() {
+            *last = width;
+        }
+        offsets
+    }
+
+    /// The loop behind `layout_styled` and `caret_offsets`. After every character, `f` is given
+    /// what was drawn for it, if anything, and the offset at which the next character starts.
+    /// Offsets saturate at the limits of an i32 instead of overflowing, however long the text,
+    /// large the scale or wide the spacing.
+    fn walk<F: FnMut(Option<(Option<&shape::Rect>, shape::Rect)>, i32)>(
+        &self,
+        text: &str,
+        scale: f32,
+        extra_px: i32,
+        rules: LayoutRules,
+        mut f: F,
+    ) -> i32 {
+        let scaled = |length: u32| (length as f32 * scale).round().max(0.0) as u32;
+        let height = scaled(self.height);
+        let tab_width = scaled(rules.tab_width.unwrap_or_else(|| self.default_tab_width()));
+        let tab_width = tab_width.max(1) as i32;
+        let mut offset: i32 = 0;
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let glyph = self.get_rect(ch).or(match rules.missing {
+                MissingGlyphMode::ReplacementChar(c) => self.get_rect(c),
+                _ => None,
+            });
+            let drawn = match (glyph, rules.missing) {
+                // a tab moves on to the next tab stop, measured from the start of the string
+                _ if ch == '\t' => {
+                    offset = offset
+                        .div_euclid(tab_width)
+                        .saturating_add(1)
+                        .saturating_mul(tab_width);
+                    None
+                }
+                (Some(r), _) => {
+                    let dst = shape::Rect::new(offset, 0, scaled(r.width()), height);
+                    offset = offset.saturating_add(dst.width() as i32);
+                    Some((Some(r), dst))
+                }
+                (None, MissingGlyphMode::Box) => {
+                    let dst = shape::Rect::new(offset, 0, scaled(self.average_width()), height);
+                    offset = offset.saturating_add(dst.width() as i32);
+                    Some((None, dst))
+                }
+                // Our Font cannot represent the current character. Leave a little space.
+                (None, _) => {
+                    offset = offset.saturating_add(scaled(5) as i32);
+                    None
+                }
+            };
+            if let Some(&next) = chars.peek() {
+                offset = offset.saturating_add(extra_px);
+                if !self.kerning.is_empty() {
+                    let kerning = (self.kerning(ch, next) as f32 * scale).round() as i32;
+                    offset = offset.saturating_add(kerning);
+                }
+            }
+            f(drawn, offset);
+        }
+        offset.max(0)
+    }
+}
+
+/// Return the index of the caret position in `offsets` (see `Font::caret_offsets`) nearest to `x`.
+/// A point in the left half of a character puts the caret before it, and one in the right half
+/// after it.
+pub(crate) fn nearest_caret(offsets: &[i32], x: i32) -> usize {
+    for (i, pair) in offsets.windows(2).enumerate() {
+        // in i64, so that offsets near the limits of an i32 don't overflow
+        if 2 * (x as i64) < pair[0] as i64 + pair[1] as i64 {
+            return i;
+        }
+    }
+    offsets.len().saturating_sub(1)
+}
+
+/// The settings, besides the font itself, that decide where the glyphs of a line go. The Window
+/// keeps these, so they apply to whichever Font is set on it.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub(crate) struct LayoutRules {
+    pub(crate) missing: MissingGlyphMode,
+    /// The distance between tab stops, or `None` for the width of four spaces.
+    pub(crate) tab_width: Option<u32>,
+}
+
+/// What `print` does with characters that the Font has no glyph for. See
+/// `Window::set_missing_glyph_mode`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum MissingGlyphMode {
+    /// Leave a 5 pixel gap. This is the default.
+    #[default]
+    Gap,
+    /// Draw a hollow rectangle, as wide as an average glyph, in the current color, so missing
+    /// characters are easy to spot.
+    Box,
+    /// Draw this character instead, if the Font has it. Otherwise, leave a gap.
+    ReplacementChar(char),
+}
+
+/// Where a line of text goes across the rectangle it is printed in. See `TextOptions`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Where a block of text goes down the rectangle it is printed in. See `TextOptions`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum VAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// How `Window::print_rect_with` lays text out inside its rectangle. The default is what
+/// `print_rect` does: left and top aligned, ordinary line spacing, clipped at the bottom.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextOptions {
+    pub align: Align,
+    /// A block of text that is taller than the rectangle always starts at the top, whatever this
+    /// says, so that nothing is drawn above the rectangle.
+    pub valign: VAlign,
+    /// The distance from one line to the next, as a multiple of the font height.
+    pub line_spacing: f32,
+    /// If true, lines that would run past the bottom of the rectangle are not drawn.
+    pub clip: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions {
+            align: Align::Left,
+            valign: VAlign::Top,
+            line_spacing: 1.0,
+            clip: true,
+        }
+    }
+}
+
+/// Return how far from the start of a space `available` pixels long something `size` pixels long
+/// should go to be aligned as `align` says. Something too big for the space starts at 0, so it
+/// never sticks out before the start.
+pub(crate) fn align_offset(size: u32, available: u32, align: VAlign) -> i32 {
+    let spare = available.saturating_sub(size) as i32;
+    match align {
+        VAlign::Top => 0,
+        VAlign::Middle => spare / 2,
+        VAlign::Bottom => spare,
+    }
+}
+
+/// Break `text` into lines no wider than `max_width`, as measured by `width_of`. Lines break at
+/// spaces where possible; a word that is too wide for a line on its own is broken between
+/// characters instead. Newlines in `text` always start a new line. Every line has at least one
+/// character (unless its paragraph is empty), so this terminates even when `max_width` is tiny.
+pub(crate) fn wrap_text<F: Fn(&str) -> u32>(
+    text: &str,
+    max_width: u32,
+    width_of: F,
+) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if width_of(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            // the word doesn't fit on a line of its own: break it up
+            let mut rest = word;
+            while width_of(rest) > max_width && rest.chars().nth(1).is_some() {
+                let mut end = rest.char_indices().nth(1).unwrap().0;
+                for (i, ch) in rest.char_indices().skip(1) {
+                    if width_of(&rest[..i + ch.len_utf8()]) > max_width {
+                        break;
+                    }
+                    end = i + ch.len_utf8();
+                }
+                lines.push(rest[..end].to_string());
+                rest = &rest[end..];
+            }
+            line = rest.to_string();
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// A substitution for `Window::print_template`: the name of a token, the value to replace it
+/// with, and the color to draw the value in, as `(red, green, blue, alpha)`, if not the Window's.
+pub type Substitution<'a> = (&'a str, &'a str, Option<(u8, u8, u8, u8)>);
+
+/// A template with its `{name}` tokens replaced, by `expand_template`.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ExpandedTemplate {
+    pub(crate) text: String,
+    // (first character, character after the last, color) of each substitution with a color
+    pub(crate) spans: Vec<(usize, usize, pixels::Color)>,
+    // the names of tokens that had no substitution, and were left in the text as they were
+    pub(crate) unknown: Vec<String>,
+    char_count: usize,
+}
+
+impl ExpandedTemplate {
+    fn push(&mut self, s: &str) {
+        self.text.push_str(s);
+        self.char_count += s.chars().count();
+    }
+}
+
+/// Replace every `{name}` in `template` with the value of the substitution called `name`. `{{`
+/// and `}}` stand for `{` and `}`. A token without a substitution, and a `{` or `}` that doesn't
+/// belong to a token, are kept as they are.
+pub(crate) fn expand_template(template: &str, substitutions: &[Substitution]) -> ExpandedTemplate {
+    let mut out = ExpandedTemplate {
+        text: String::new(),
+        spans: vec![],
+        unknown: vec![],
+        char_count: 0,
+    };
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        // a token ends at the first brace after it, which must close it
+        let end = tail[1..].find(['{', '}']).map(|j| j + 1);
+        match end.filter(|&j| tail.starts_with('{') && tail[j..].starts_with('}')) {
+            Some(j) => {
+                let name = &tail[1..j];
+                match substitutions.iter().find(|s| s.0 == name) {
+                    Some(&(_, value, color)) => {
+                        let start = out.char_count;
+                        out.push(value);
+                        if let Some((r, g, b, a)) = color {
+                            let color = pixels::Color::RGBA(r, g, b, a);
+                            out.spans.push((start, out.char_count, color));
+                        }
+                    }
+                    None => {
+                        out.unknown.push(name.to_string());
+                        out.push(&tail[..=j]);
+                    }
+                }
+                rest = &tail[j + 1..];
+            }
+            None => {
+                out.push(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push(rest);
+    out
+}
+
+/// FontInfo describes an image font without loading it onto the GPU. See `inspect_image_font`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FontInfo {
+    /// How many glyphs were found in the image. If this is smaller than the number of characters
+    /// in the charset, the image is missing glyphs for the last characters.
+    pub glyph_count: usize,
+
+    /// The width of each glyph that was found, in charset order.
+    pub widths: Vec<(char, u32)>,
+
+    /// The height shared by every glyph.
+    pub height: u32,
+}
+
+/// Decode an image font from PNG (or any other supported image) bytes and report what the glyph
+/// parser finds in it, using the same rules as `Window::load_font`. No Window is needed, so asset
+/// pipelines can check that a font image matches its charset before shipping it.
+pub fn inspect_image_font(png_bytes: &[u8], charset: &str) -> Result<FontInfo, SimpleError> {
+    let surf = decode_font_image(png_bytes).map_err(SimpleError::FontParse)?;
+    let chars = parse_glyphs(&surf, charset).map_err(SimpleError::FontParse)?;
+
+    let widths: Vec<(char, u32)> = charset
+        .chars()
+        .filter_map(|c| chars.get(&c).map(|r| (c, r.width())))
+        .collect();
+    Ok(FontInfo {
+        glyph_count: widths.len(),
+        widths,
+        height: surf.height(),
+    })
+}
+
+/// Rasterize every character of `charset` from the TrueType font at `ttf_path`, at `point_size`,
+/// and write them to `out_png` as an image font: the glyphs side by side in white, with a one
+/// pixel column of border color before, between and after them. Loading the PNG with
+/// `Window::load_font_from_file` and the same charset gives a Font that can print every character
+/// of the charset.
+///
+/// This is meant for a tools binary or a build step, so that the program itself only ships the
+/// image and does not depend on SDL2_ttf. It needs the `ttf` feature.
+#[cfg(feature = "ttf")]
+pub fn bake_image_font(
+    ttf_path: impl AsRef<Path>,
+    point_size: u16,
+    charset: &str,
+    out_png: impl AsRef<Path>,
+) -> Result<(), SimpleError> {
+    let (ttf_path, out_png) = (ttf_path.as_ref(), out_png.as_ref());
+    if util::string_has_duplicate_chars(charset.to_string()) {
+        return Err(SimpleError::InvalidArgument(
+            "image font string has duplicate characters".to_string(),
+        ));
+    }
+    let ttf = sdl2::ttf::init().map_err(|e| SimpleError::Sdl(e.to_string()))?;
+    let font = ttf
+        .load_font(ttf_path, point_size)
+        .map_err(|e| SimpleError::Sdl(e).in_file(ttf_path))?;
+
+    let mut glyphs = Vec::new();
+    for c in charset.chars() {
+        let glyph = font
+            .render_char(c)
+            .blended(pixels::Color::RGBA(255, 255, 255, 255))
+            .map_err(|e| format!("can't render {:?}: {}", c, e))?;
+        glyphs.push(glyph);
+    }
+
+    let widths: Vec<u32> = glyphs.iter().map(|g| g.width().max(1)).collect();
+    let height = glyphs
+        .iter()
+        .map(|g| g.height())
+        .fold(font.height().max(1) as u32, u32::max);
+    let (starts, width) = glyph_strip(&widths);
+
+    let mut strip = util::new_rgba_surface(width, height)?;
+    let border = pixels::Color::RGBA(255, 0, 255, 255);
+    strip.fill_rect(shape::Rect::new(0, 0, 1, height), border)?;
+    for ((glyph, &x), &w) in glyphs.iter_mut().zip(&starts).zip(&widths) {
+        glyph.set_blend_mode(render::BlendMode::None)?;
+        glyph.blit(None, &mut strip, shape::Rect::new(x, 0, w, height))?;
+        strip.fill_rect(shape::Rect::new(x + w as i32, 0, 1, height), border)?;
+    }
+    strip
+        .save(out_png)
+        .map_err(|e| SimpleError::Sdl(e).in_file(out_png))
+}
+
+/// Lay out glyphs of the given widths in a row for an image font, with a one pixel border column
+/// before each glyph and after the last one. Return where each glyph starts, and the width of
+/// the whole row.
+#[cfg_attr(not(feature = "ttf"), allow(dead_code))]
+fn glyph_strip(widths: &[u32]) -> (Vec<i32>, u32) {
+    let mut starts = Vec::with_capacity(widths.len());
+    let mut x = 1;
+    for &w in widths {
+        starts.push(x as i32);
+        x += w + 1;
+    }
+    (starts, x)
+}
+
+/// Return a copy of the RGBA `pixels` of a `width` by `height` font image with only the edges of
+/// the glyphs left: the pixels at least half opaque with a neighbour above, below, left or right
+/// that is less than half opaque or outside the glyph. Everything else, the separators between
+/// glyphs included, is made transparent.
+pub(crate) fn hollow_pixels<'a>(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    glyphs: impl Iterator<Item = &'a shape::Rect>,
+) -> Vec<u8> {
+    let (width, height) = (width as i32, height as i32);
+    let mut hollow = vec![0; pixels.len()];
+    // in usize, so that an image too big for an i32 count of bytes doesn't overflow
+    let index = |x: i32, y: i32| (y as usize * width as usize + x as usize) * 4;
+    let solid = |x: i32, y: i32| pixels[index(x, y) + 3] >= 128;
+    for glyph in glyphs {
+        // glyphs come from parsing this image, but stay inside it regardless
+        let (left, right) = (glyph.left().max(0), glyph.right().min(width));
+        let (top, bottom) = (glyph.top().max(0), glyph.bottom().min(height));
+        let inside = |x: i32, y: i32| {
+            (left..right).contains(&x) && (top..bottom).contains(&y) && solid(x, y)
+        };
+        for y in top..bottom {
+            for x in left..right {
+                let edge = !inside(x - 1, y)
+                    || !inside(x + 1, y)
+                    || !inside(x, y - 1)
+                    || !inside(x, y + 1);
+                if solid(x, y) && edge {
+                    let i = index(x, y);
+                    hollow[i..i + 4].copy_from_slice(&pixels[i..i + 4]);
+                }
+            }
+        }
+    }
+    hollow
+}
+
+/// Decode image bytes into the RGBA Surface the glyph parser works on.
+pub(crate) fn decode_font_image(data: &[u8]) -> Result<surface::Surface<'static>, String> {
+    let rwops = rwops::RWops::from_bytes(data)?;
+    let mut surf: surface::Surface = rwops.load()?;
+    util::to_rgba_surface(&mut surf)
+}
+
+/// Find the glyphs in an RGBA image font Surface, assigning them to the characters of `charset` in
+/// order.
+pub(crate) fn parse_glyphs(
+    surf: &surface::SurfaceRef,
+    charset: &str,
+) -> Result<HashMap<char, shape::Rect>, String> {
+    let width = surf.width() as usize;
+    let top_row: Vec<u32> = surf.with_lock(|pixels| {
+        pixels[..width * 4]
+            .chunks(4)
+            .map(|p| u32::from_ne_bytes([p[0], p[1], p[2], p[3]]))
+            .collect()
+    });
+    parse_glyph_row(&top_row, surf.height(), charset)
+}
+
+/// Divide an image font into glyph rectangles according to the color of its topmost row of pixels.
+/// The color of the very first pixel is the border color; each run of other colors between border
+/// pixels is one glyph, spanning the full `height` of the image. Glyphs are assigned to the
+/// characters of `charset` in order, and any glyphs beyond the end of `charset` are ignored.
+fn parse_glyph_row(
+    top_row: &[u32],
+    height: u32,
+    charset: &str,
+) -> Result<HashMap<char, shape::Rect>, String> {
+    if util::string_has_duplicate_chars(charset.to_string()) {
+        return Err("image font string has duplicate characters".to_string());
+    }
+
+    let mut chars: HashMap<char, shape::Rect> = HashMap::new();
+    let mut charset = charset.chars();
+    let border_color = match top_row.first() {
+        Some(&c) => c,
+        None => return Ok(chars),
+    };
+
+    let mut glyph_start: Option<usize> = None;
+    for (i, &pixel) in top_row.iter().enumerate() {
+        if pixel == border_color {
+            if let Some(start) = glyph_start {
+                let c = match charset.next() {
+                    Some(c) => c,
+                    // Out of characters to add to the hashmap, so just return with what have
+                    // parsed so far.
+                    None => break,
+                };
+                chars.insert(
+                    c,
+                    shape::Rect::new(start as i32, 0, (i - start) as u32, height),
+                );
+                glyph_start = None;
+            }
+        } else if glyph_start.is_none() {
+            glyph_start = Some(i);
+        }
+    }
+    Ok(chars)
+}
+
+#[test]
+fn test_parse_glyph_row() {
+    // border, 2-wide glyph, border, 3-wide glyph, border, 1-wide glyph with no closing border
+    let row = [9, 1, 2, 9, 9, 3, 3, 3, 9, 4];
+    let chars = parse_glyph_row(&row, 7, "abc").unwrap();
+    assert!(chars.len() == 2);
+    assert!(chars[&'a'] == shape::Rect::new(1, 0, 2, 7));
+    assert!(chars[&'b'] == shape::Rect::new(5, 0, 3, 7));
+
+    // more glyphs than characters: the extras are ignored
+    let chars = parse_glyph_row(&row, 7, "a").unwrap();
+    assert!(chars.len() == 1);
+
+    assert!(parse_glyph_row(&row, 7, "aa").is_err());
+    assert!(parse_glyph_row(&[], 7, "a").unwrap().is_empty());
+}
+
+#[test]
+fn test_hollow_pixels() {
+    // a 5x4 image holding a 3x3 solid square glyph at (1, 0) and a separator column at x = 0
+    let (width, height) = (5, 4);
+    let mut pixels = vec![0u8; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let alpha = match (x, y) {
+                (0, _) => 255,
+                (1..=3, 0..=2) => 255,
+                _ => 0,
+            };
+            pixels[(y * width + x) * 4..][..4].copy_from_slice(&[10, 20, 30, alpha]);
+        }
+    }
+    let glyphs = [shape::Rect::new(1, 0, 4, 4)];
+    let hollow = hollow_pixels(&pixels, width as u32, height as u32, glyphs.iter());
+
+    let alpha_at = |x: usize, y: usize| hollow[(y * width + x) * 4 + 3];
+    // the separator is gone, the square's middle is punched out, and its edges are kept as they
+    // were, even where they touch the glyph's border
+    assert!(alpha_at(0, 1) == 0);
+    assert!(alpha_at(2, 1) == 0);
+    for &(x, y) in &[
+        (1, 0),
+        (2, 0),
+        (3, 0),
+        (1, 1),
+        (3, 1),
+        (1, 2),
+        (2, 2),
+        (3, 2),
+    ] {
+        assert!(hollow[(y * width + x) * 4..][..4] == [10, 20, 30, 255]);
+    }
+    assert!(alpha_at(4, 3) == 0);
+}
+
+#[test]
+fn test_glyph_strip_matches_parser() {
+    let widths = [3, 1, 4];
+    let (starts, width) = glyph_strip(&widths);
+    assert!(starts == [1, 5, 7] && width == 12);
+
+    // paint the top row the way bake_image_font does and parse it back
+    let mut row = vec![0; width as usize];
+    row[0] = 9;
+    for (&x, &w) in starts.iter().zip(&widths) {
+        row[x as usize + w as usize] = 9;
+    }
+    let chars = parse_glyph_row(&row, 5, "abc").unwrap();
+    assert!(chars.len() == 3);
+    assert!(chars[&'c'] == shape::Rect::new(7, 0, 4, 5));
+}
+
+#[test]
+fn test_inspect_default_font() {
+    let info = inspect_image_font(DEFAULT_FONT_BYTES, DEFAULT_FONT_STR).unwrap();
+    assert!(info.glyph_count == DEFAULT_FONT_STR.chars().count());
+    assert!(info.height == 17);
+    assert!(info.widths.iter().all(|&(_, w)| w > 0));
+}
+
+#[test]
+fn test_align_offset() {
+    assert!(align_offset(10, 30, VAlign::Top) == 0);
+    assert!(align_offset(10, 30, VAlign::Middle) == 10);
+    assert!(align_offset(10, 30, VAlign::Bottom) == 20);
+    // too tall: pinned to the top
+    assert!(align_offset(50, 30, VAlign::Middle) == 0);
+    assert!(align_offset(50, 30, VAlign::Bottom) == 0);
+}
+
+#[test]
+fn test_nearest_caret() {
+    // three characters, 4, 2 and 6 pixels wide
+    let offsets = [0, 4, 6, 12];
+    assert!(nearest_caret(&offsets, -10) == 0);
+    assert!(nearest_caret(&offsets, 1) == 0);
+    assert!(nearest_caret(&offsets, 2) == 1);
+    assert!(nearest_caret(&offsets, 5) == 2);
+    assert!(nearest_caret(&offsets, 8) == 2);
+    assert!(nearest_caret(&offsets, 9) == 3);
+    assert!(nearest_caret(&offsets, 100) == 3);
+    assert!(nearest_caret(&[0], 5) == 0);
+
+    // offsets and positions near the limits of an i32 don't overflow
+    let far = [0, i32::MAX - 1, i32::MAX];
+    assert!(nearest_caret(&far, i32::MAX) == 2);
+    assert!(nearest_caret(&far, i32::MIN) == 0);
+}
+
+#[test]
+fn test_wrap_text() {
+    // every character is 10 pixels wide
+    let wrap = |text: &str, max_width: u32| {
+        wrap_text(text, max_width, |s: &str| s.chars().count() as u32 * 10)
+    };
+    assert!(wrap("the quick brown fox", 100) == ["the quick", "brown fox"]);
+    assert!(wrap("the quick brown fox", 1000) == ["the quick brown fox"]);
+    assert!(wrap("one\n\ntwo", 1000) == ["one", "", "two"]);
+
+    // words that are too long are broken between characters
+    assert!(wrap("abcdefgh ij", 30) == ["abc", "def", "gh", "ij"]);
+    assert!(wrap("abc", 0) == ["a", "b", "c"]);
+    assert!(wrap("", 50) == [""]);
+}
+
+#[test]
+fn test_expand_template() {
+    let red = Some((255, 0, 0, 255));
+    let t = expand_template(
+        "Press {key:JUMP} to {verb}",
+        &[("key:JUMP", "Space", red), ("verb", "jump", None)],
+    );
+    assert!(t.text == "Press Space to jump");
+    assert!(t.spans == [(6, 11, pixels::Color::RGBA(255, 0, 0, 255))]);
+    assert!(t.unknown.is_empty());
+
+    // spans count characters, not bytes
+    let t = expand_template("é{a}", &[("a", "ü", red)]);
+    assert!(t.text == "éü" && t.spans[0].0 == 1 && t.spans[0].1 == 2);
+
+    let t = expand_template("{{a}} {a} {b} {} {c d", &[("a", "x", None)]);
+    assert!(t.text == "{a} x {b} {} {c d");
+    assert!(t.unknown == ["b", ""]);
+    let t = expand_template("} {a{b} }}", &[("b", "y", None)]);
+    assert!(t.text == "} {ay }");
+}
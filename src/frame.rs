@@ -0,0 +1,176 @@
+/*!
+ * A guard for drawing one frame, in the style of love2d's `love.graphics`.
+ *
+ * `Window::begin_frame` returns a Frame, which borrows the Window until it is dropped or
+ * `finish`ed. While it is alive the Window can only be drawn on through it, so calling
+ * `end_frame` or `next_frame` in the middle of a frame doesn't compile.
+ */
+
+use crate::error::SimpleError;
+use crate::event::Event;
+use crate::shape;
+use crate::window::{Image, Window};
+
+/**
+ * Frame draws on the Window for the rest of the current frame. See the module documentation.
+ *
+ * Besides the common drawing methods, it can take the events `begin_frame` queued, and it draws
+ * images with `image`, which takes the position, rotation and scale one at a time instead of as a
+ * row of arguments:
+ *
+ * ```no_run
+ * # let mut window = simple::Window::new("frame", 64, 64);
+ * # let sprite = window.load_image_from_file("sprite.png").unwrap();
+ * let mut g = window.begin_frame();
+ * g.image(&sprite).at(10, 10).rotation(45.0).scale(2.0).draw()?;
+ * g.finish();
+ * window.end_frame();
+ * # Ok::<(), simple::SimpleError>(())
+ * ```
+ */
+pub struct Frame<'a> {
+    window: &'a mut Window,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(window: &'a mut Window) -> Self {
+        Frame { window }
+    }
+
+    /// See `Window::has_event`.
+    pub fn has_event(&self) -> bool {
+        self.window.has_event()
+    }
+
+    /// See `Window::next_event`.
+    pub fn next_event(&mut self) -> Event {
+        self.window.next_event()
+    }
+
+    /// See `Window::set_color`.
+    pub fn set_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
+        self.window.set_color(red, green, blue, alpha);
+    }
+
+    /// See `Window::clear`.
+    pub fn clear(&mut self) {
+        self.window.clear();
+    }
+
+    /// See `Window::draw_rect`.
+    pub fn draw_rect(&mut self, rect: shape::Rect) {
+        self.window.draw_rect(rect);
+    }
+
+    /// See `Window::fill_rect`.
+    pub fn fill_rect(&mut self, rect: shape::Rect) {
+        self.window.fill_rect(rect);
+    }
+
+    /// See `Window::draw_point`.
+    pub fn draw_point(&mut self, point: shape::Point) {
+        self.window.draw_point(point);
+    }
+
+    /// See `Window::draw_polygon`.
+    pub fn draw_polygon(&mut self, polygon: shape::Polygon) {
+        self.window.draw_polygon(polygon);
+    }
+
+    /// See `Window::draw_image`.
+    pub fn draw_image(&mut self, image: &Image, x: i32, y: i32) {
+        self.window.draw_image(image, x, y);
+    }
+
+    /// See `Window::print`.
+    pub fn print(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
+        self.window.print(text, x, y)
+    }
+
+    /// Start drawing `image`: at (0, 0), unrotated and at its own size, until the ImageDraw says
+    /// otherwise. Nothing is drawn until `ImageDraw::draw` is called.
+    pub fn image<'f>(&'f mut self, image: &'f Image) -> ImageDraw<'f> {
+        ImageDraw::new(self.window, image)
+    }
+
+    /// Stop drawing through this Frame, so that the Window can be used again to end the frame.
+    /// Dropping the Frame does the same.
+    pub fn finish(self) {}
+}
+
+/// ImageDraw collects how to draw one image. See `Frame::image`.
+#[must_use = "nothing is drawn until `draw` is called"]
+pub struct ImageDraw<'f> {
+    window: &'f mut Window,
+    image: &'f Image,
+    x: i32,
+    y: i32,
+    rotation: f64,
+    scale: f32,
+    flip: (bool, bool),
+}
+
+impl<'f> ImageDraw<'f> {
+    pub(crate) fn new(window: &'f mut Window, image: &'f Image) -> Self {
+        ImageDraw {
+            window,
+            image,
+            x: 0,
+            y: 0,
+            rotation: 0.0,
+            scale: 1.0,
+            flip: (false, false),
+        }
+    }
+
+    /// Put the top-left corner of the scaled image, before it is rotated, at (x, y).
+    pub fn at(mut self, x: i32, y: i32) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    /// Rotate the image clockwise by `degrees` around its center.
+    pub fn rotation(mut self, degrees: f64) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    /// Draw the image `scale` times its size.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Mirror the image left to right if `horizontal`, and top to bottom if `vertical`, before it
+    /// is rotated.
+    pub fn flip(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.flip = (horizontal, vertical);
+        self
+    }
+
+    /// Draw the image, tinted with the Window's color like `Window::draw_image`. Return an error,
+    /// and draw nothing, if the scale isn't a positive number or the rotation isn't finite.
+    pub fn draw(self) -> Result<(), SimpleError> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(SimpleError::InvalidArgument(format!(
+                "image scale must be a positive number, got {}",
+                self.scale
+            )));
+        }
+        if !self.rotation.is_finite() {
+            return Err(SimpleError::InvalidArgument(format!(
+                "image rotation must be finite, got {}",
+                self.rotation
+            )));
+        }
+        self.window.draw_image_transformed(
+            self.image,
+            (self.x, self.y),
+            self.rotation,
+            self.scale,
+            self.flip,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+/*!
+ * Tile grids for games laid out on a regular grid of square cells, and line-of-sight queries
+ * against them.
+ *
+ * Coordinates are measured in cells: cell `(x, y)` covers the area from `(x, y)` up to but not
+ * including `(x + 1, y + 1)`. A point lying exactly on a grid line therefore belongs to the cell on
+ * its right (or below it).
+ */
+
+/**
+ * PathGrid is a rectangle of cells, each of which is either open or blocked.
+ *
+ * Anything outside the grid counts as blocked, so rays and searches never escape the map.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct PathGrid {
+    width: u32,
+    height: u32,
+    blocked: Vec<bool>,
+}
+
+impl PathGrid {
+    /// Create a grid in which every cell is open.
+    pub fn new(width: u32, height: u32) -> Self {
+        PathGrid {
+            width,
+            height,
+            blocked: vec![false; width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return true if `(x, y)` is a cell of this grid.
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
+
+    /// Mark the cell as blocked or open. Cells outside the grid are ignored.
+    pub fn set_blocked(&mut self, x: i32, y: i32, blocked: bool) {
+        if self.in_bounds(x, y) {
+            let i = self.index(x, y);
+            self.blocked[i] = blocked;
+        }
+    }
+
+    /// Return true if the cell is blocked. Cells outside the grid are always blocked.
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        !self.in_bounds(x, y) || self.blocked[self.index(x, y)]
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+/// The outcome of `raycast`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RaycastHit {
+    /// True if the ray was stopped by a blocked cell before reaching its end.
+    pub blocked: bool,
+
+    /// The blocked cell that stopped the ray, or the cell containing the end point if nothing did.
+    pub cell: (i32, i32),
+
+    /// Where the ray entered the blocked cell, or the end point if nothing blocked it.
+    pub point: (f32, f32),
+}
+
+/// Walk the cells crossed by the segment from `from` to `to` (Amanatides & Woo's traversal) and
+/// report the first blocked one.
+///
+/// When the ray passes exactly through a corner shared by four cells, it visits the horizontally
+/// adjacent cell before the diagonal one. That means two diagonal walls touching at a corner do
+/// stop a ray that tries to squeeze between them, as long as the wall is on the horizontal side. A
+/// ray that runs exactly along a grid line travels through the cells on the right of (or below)
+/// that line, following the convention in the module documentation.
+///
+/// If `from` is itself in a blocked cell, the ray is blocked at `from`. If either point is NaN or
+/// infinite there are no cells to walk, and the ray is reported as reaching `to` unblocked.
+pub fn raycast(grid: &PathGrid, from: (f32, f32), to: (f32, f32)) -> RaycastHit {
+    let (x0, y0) = from;
+    let (dx, dy) = (to.0 - x0, to.1 - y0);
+    let mut cell = (x0.floor() as i32, y0.floor() as i32);
+    let end = (to.0.floor() as i32, to.1.floor() as i32);
+    let unblocked = RaycastHit {
+        blocked: false,
+        cell: end,
+        point: to,
+    };
+    if ![x0, y0, to.0, to.1].iter().all(|c| c.is_finite()) {
+        return unblocked;
+    }
+
+    // for each axis: which way we step, how far along the ray (0..1) the next grid line is, and
+    // how far along the ray one whole cell is
+    let axis = |start: f32, delta: f32, cell: i32| -> (i32, f32, f32) {
+        if delta > 0.0 {
+            (1, (cell as f32 + 1.0 - start) / delta, 1.0 / delta)
+        } else if delta < 0.0 {
+            (-1, (start - cell as f32) / -delta, 1.0 / -delta)
+        } else {
+            (0, f32::INFINITY, f32::INFINITY)
+        }
+    };
+    let (step_x, mut t_max_x, t_delta_x) = axis(x0, dx, cell.0);
+    let (step_y, mut t_max_y, t_delta_y) = axis(y0, dy, cell.1);
+
+    let mut t = 0.0;
+    loop {
+        if grid.is_blocked(cell.0, cell.1) {
+            return RaycastHit {
+                blocked: true,
+                cell,
+                point: (x0 + dx * t, y0 + dy * t),
+            };
+        }
+        if cell == end {
+            break;
+        }
+
+        // ties go to x first; see the corner convention above
+        if t_max_x <= t_max_y {
+            if t_max_x > 1.0 {
+                break;
+            }
+            t = t_max_x;
+            t_max_x += t_delta_x;
+            cell.0 += step_x;
+        } else {
+            if t_max_y > 1.0 {
+                break;
+            }
+            t = t_max_y;
+            t_max_y += t_delta_y;
+            cell.1 += step_y;
+        }
+    }
+    unblocked
+}
+
+/// Return every cell within `radius` cells of `origin` that can be seen from the centre of
+/// `origin`. A cell is visible if a ray from the centre of `origin` to its centre is not blocked
+/// before reaching it. Blocked cells are visible too when they are the ones stopping the ray, so
+/// walls show up at the edge of the field of view.
+pub fn visible_cells(grid: &PathGrid, origin: (i32, i32), radius: u32) -> Vec<(i32, i32)> {
+    // only over the cells of the grid, however far the radius reaches past it, and in wide enough
+    // integers: an i64 holds any difference of two i32 coordinates, and an i128 its square
+    let r = radius as i64;
+    let (cx, cy) = (origin.0 as i64, origin.1 as i64);
+    let xs = (cx - r).max(0)..=(cx + r).min(grid.width as i64 - 1);
+    let ys = (cy - r).max(0)..=(cy + r).min(grid.height as i64 - 1);
+    let from = (origin.0 as f32 + 0.5, origin.1 as f32 + 0.5);
+    let mut visible = vec![];
+
+    for y in ys {
+        for x in xs.clone() {
+            let (ox, oy) = ((x - cx) as i128, (y - cy) as i128);
+            if ox * ox + oy * oy > (r as i128).pow(2) {
+                continue;
+            }
+            let (x, y) = (x as i32, y as i32);
+            let hit = raycast(grid, from, (x as f32 + 0.5, y as f32 + 0.5));
+            if !hit.blocked || hit.cell == (x, y) {
+                visible.push((x, y));
+            }
+        }
+    }
+    visible
+}
+
+#[test]
+fn test_raycast_open_and_blocked() {
+    let mut grid = PathGrid::new(10, 10);
+    let hit = raycast(&grid, (0.5, 0.5), (9.5, 0.5));
+    assert!(!hit.blocked);
+    assert!(hit.cell == (9, 0));
+    assert!(hit.point == (9.5, 0.5));
+
+    grid.set_blocked(4, 0, true);
+    let hit = raycast(&grid, (0.5, 0.5), (9.5, 0.5));
+    assert!(hit.blocked);
+    assert!(hit.cell == (4, 0));
+    assert!(hit.point == (4.0, 0.5));
+
+    // going the other way, the ray enters through the right-hand edge of the cell
+    let hit = raycast(&grid, (9.5, 0.5), (0.5, 0.5));
+    assert!(hit.cell == (4, 0));
+    assert!(hit.point == (5.0, 0.5));
+
+    // starting inside a wall
+    let hit = raycast(&grid, (4.5, 0.5), (8.5, 0.5));
+    assert!(hit.blocked);
+    assert!(hit.point == (4.5, 0.5));
+
+    // leaving the grid counts as hitting a wall
+    let hit = raycast(&grid, (5.5, 5.5), (5.5, 12.0));
+    assert!(hit.blocked);
+    assert!(hit.cell == (5, 10));
+}
+
+#[test]
+fn test_raycast_along_grid_lines() {
+    // a ray along the line y = 3 travels through row 3, not row 2
+    let mut grid = PathGrid::new(10, 10);
+    grid.set_blocked(5, 2, true);
+    assert!(!raycast(&grid, (0.5, 3.0), (9.5, 3.0)).blocked);
+    grid.set_blocked(5, 3, true);
+    let hit = raycast(&grid, (0.5, 3.0), (9.5, 3.0));
+    assert!(hit.blocked);
+    assert!(hit.cell == (5, 3));
+
+    // an end point exactly on a line belongs to the cell on its right
+    let grid = PathGrid::new(10, 10);
+    assert!(raycast(&grid, (0.5, 0.5), (3.0, 0.5)).cell == (3, 0));
+}
+
+#[test]
+fn test_raycast_through_corner() {
+    // diagonal from the centre of (0, 0) through the corner at (1, 1) into (1, 1)
+    let mut grid = PathGrid::new(3, 3);
+    assert!(!raycast(&grid, (0.5, 0.5), (1.5, 1.5)).blocked);
+
+    // the vertically adjacent cell is skipped...
+    grid.set_blocked(0, 1, true);
+    assert!(!raycast(&grid, (0.5, 0.5), (1.5, 1.5)).blocked);
+
+    // ...but the horizontally adjacent one is visited
+    grid.set_blocked(1, 0, true);
+    let hit = raycast(&grid, (0.5, 0.5), (1.5, 1.5));
+    assert!(hit.blocked);
+    assert!(hit.cell == (1, 0));
+    assert!(hit.point == (1.0, 1.0));
+}
+
+#[test]
+fn test_raycast_not_finite() {
+    let mut grid = PathGrid::new(3, 3);
+    grid.set_blocked(1, 1, true);
+    for point in [
+        (f32::NAN, 0.5),
+        (0.5, f32::INFINITY),
+        (f32::NEG_INFINITY, f32::NAN),
+    ] {
+        assert!(!raycast(&grid, point, (2.5, 2.5)).blocked);
+        let hit = raycast(&grid, (0.5, 0.5), point);
+        assert!(!hit.blocked && hit.cell == (point.0 as i32, point.1 as i32));
+    }
+}
+
+#[test]
+fn test_visible_cells() {
+    let mut grid = PathGrid::new(7, 7);
+    grid.set_blocked(4, 3, true);
+    let visible = visible_cells(&grid, (3, 3), 3);
+
+    assert!(visible.contains(&(3, 3)));
+    assert!(visible.contains(&(3, 0)));
+    assert!(visible.contains(&(4, 3))); // the wall itself
+    assert!(!visible.contains(&(5, 3))); // behind the wall
+    assert!(!visible.contains(&(6, 3)));
+    assert!(!visible.contains(&(0, 0))); // outside the radius
+}
+
+#[test]
+fn test_visible_cells_far_away() {
+    let grid = PathGrid::new(7, 7);
+    assert!(visible_cells(&grid, (i32::MAX, 0), 100_000).is_empty());
+    assert!(visible_cells(&grid, (i32::MIN, i32::MAX), u32::MAX).is_empty());
+    // a huge radius only visits the cells of the grid
+    assert!(visible_cells(&grid, (3, 3), u32::MAX).len() == 49);
+}
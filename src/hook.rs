@@ -0,0 +1,62 @@
+/*!
+ * Hooks let code that is not the application's main loop, such as a library built on simple,
+ * watch events and draw at the end of every frame. See `Window::add_event_hook` and
+ * `Window::add_frame_hook`.
+ */
+
+use crate::event::Event;
+use crate::window::Window;
+
+/// HookId identifies a hook so that it can be removed with `Window::remove_hook`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct HookId(pub(crate) u64);
+
+/// What an event hook wants done with the event it was shown.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HookResult {
+    /// Pass the event on to the next hook, and then to the event queue.
+    Pass,
+    /// Stop here: later hooks and the application never see the event.
+    Consume,
+}
+
+pub(crate) type EventHook = Box<dyn FnMut(&Event) -> HookResult>;
+pub(crate) type FrameHook = Box<dyn FnMut(&mut Window)>;
+pub(crate) type EventLogger = Box<dyn FnMut(&Event)>;
+pub(crate) type SlowFrameHook = Box<dyn FnMut(u32)>;
+
+/// Show `e` to every hook in order. Return the event if none of them consumed it.
+pub(crate) fn dispatch(hooks: &mut [(HookId, EventHook)], e: Event) -> Option<Event> {
+    for (_, hook) in hooks.iter_mut() {
+        if hook(&e) == HookResult::Consume {
+            return None;
+        }
+    }
+    Some(e)
+}
+
+#[test]
+fn test_dispatch_stops_at_consume() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let seen = Rc::new(Cell::new(0));
+    let counter = |result| -> EventHook {
+        let seen = seen.clone();
+        Box::new(move |_| {
+            seen.set(seen.get() + 1);
+            result
+        })
+    };
+    let mut hooks = vec![
+        (HookId(0), counter(HookResult::Pass)),
+        (HookId(1), counter(HookResult::Consume)),
+        (HookId(2), counter(HookResult::Pass)),
+    ];
+    assert!(dispatch(&mut hooks, Event::Quit).is_none());
+    assert!(seen.get() == 2);
+
+    hooks.retain(|(id, _)| *id != HookId(1));
+    assert!(dispatch(&mut hooks, Event::Quit) == Some(Event::Quit));
+    assert!(seen.get() == 4);
+}
@@ -0,0 +1,401 @@
+/*!
+ * Deferred drawing. Once a layer has been selected with `Window::layer`, draw calls are recorded
+ * here instead of being executed, and `next_frame` replays them in layer order.
+ *
+ * Rectangles and points that are drawn one after another in the same color are batched here too,
+ * and drawn with a single call once something else is drawn or the canvas state is about to
+ * change. See `Window::set_batching`.
+ */
+
+extern crate sdl2;
+use crate::shape;
+use crate::util;
+use sdl2::pixels;
+use sdl2::render;
+use sdl2::video;
+use std::collections::HashMap;
+
+/// TextureId refers to a texture without borrowing it, so that a draw call can be recorded while
+/// the Image (or Font, or Atlas) it came from goes back to its owner.
+///
+/// This is only sound because a texture is never destroyed while a recorded command may refer to
+/// it: dropping a texture only queues it until the frame has been shown (see `texture`), and the
+/// canvas that created it outlives every recorded command.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct TextureId(*mut sdl2::sys::SDL_Texture);
+
+impl TextureId {
+    pub(crate) fn of(texture: &render::Texture) -> Self {
+        TextureId(texture.raw())
+    }
+}
+
+/// One drawing operation, in the coordinates the canvas understands (the draw offset has already
+/// been applied).
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Draw {
+    Rect(shape::Rect),
+    FillRect(shape::Rect),
+    Points(Vec<shape::Point>),
+    // a texture drawn with a blend mode of None follows the canvas's blend mode, and a copy that
+    // is a glyph of printed text is counted in DrawStats::glyphs
+    Copy {
+        texture: TextureId,
+        src: Option<shape::Rect>,
+        dst: shape::Rect,
+        blend: Option<render::BlendMode>,
+        glyph: bool,
+    },
+    CopyRotated {
+        texture: TextureId,
+        src: shape::Rect,
+        dst: shape::Rect,
+        angle: f64,
+        center: shape::Point,
+        // mirrored (horizontally, vertically)
+        flip: (bool, bool),
+        blend: Option<render::BlendMode>,
+        glyph: bool,
+    },
+}
+
+/// DrawStats counts the work one frame asked of the renderer. See `Window::draw_stats`.
+///
+/// The counts are taken where the renderer is actually called, so a draw call that is culled,
+/// or recorded on a layer and not yet replayed, isn't counted.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct DrawStats {
+    /// Rectangles, points and clears, counted once per call: drawing a polygon's points with one
+    /// call counts as one, and so does a batch of rectangles or points drawn together (see
+    /// `Window::set_batching`).
+    pub primitives: u32,
+    /// Copies of a texture, including each glyph of printed text.
+    pub texture_copies: u32,
+    /// Glyphs drawn by `print` and its relatives. These are included in `texture_copies` too.
+    pub glyphs: u32,
+    /// Changes of the draw color, the blend mode, or the color and alpha mods of a texture.
+    /// Setting what is already set isn't counted.
+    pub state_changes: u32,
+}
+
+/// The renderer state set so far this frame, so that setting a draw color or blend mode that is
+/// already in place isn't counted in DrawStats::state_changes. The state is forgotten when the
+/// frame's stats are taken, so the first change of every frame is counted.
+#[derive(Default)]
+struct Applied {
+    draw_color: Option<pixels::Color>,
+    blend_mode: Option<render::BlendMode>,
+    // the color and alpha mods, and the blend mode, of every texture drawn
+    textures: HashMap<TextureId, (Option<pixels::Color>, Option<render::BlendMode>)>,
+}
+
+impl Applied {
+    fn set_draw_color(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        color: pixels::Color,
+        stats: &mut DrawStats,
+    ) {
+        canvas.set_draw_color(color);
+        count_change(&mut self.draw_color, color, stats);
+    }
+
+    fn set_blend_mode(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        mode: render::BlendMode,
+        stats: &mut DrawStats,
+    ) {
+        canvas.set_blend_mode(mode);
+        count_change(&mut self.blend_mode, mode, stats);
+    }
+
+    fn set_texture_state(
+        &mut self,
+        texture: &mut render::Texture,
+        color: pixels::Color,
+        blend: render::BlendMode,
+        stats: &mut DrawStats,
+    ) {
+        util::set_texture_color(&color, texture);
+        texture.set_blend_mode(blend);
+        let (applied_color, applied_blend) =
+            self.textures.entry(TextureId::of(texture)).or_default();
+        count_change(applied_color, color, stats);
+        count_change(applied_blend, blend, stats);
+    }
+}
+
+/// Remember `value` as what is now applied, counting a state change if it is different.
+fn count_change<T: PartialEq>(applied: &mut Option<T>, value: T, stats: &mut DrawStats) {
+    if applied.as_ref() != Some(&value) {
+        *applied = Some(value);
+        stats.state_changes += 1;
+    }
+}
+
+/// A recorded Draw, with the parts of the Window's state that affect how it looks.
+struct Command {
+    draw: Draw,
+    color: pixels::Color,
+    viewport: Option<shape::Rect>,
+    clip_rect: Option<shape::Rect>,
+}
+
+/// The kinds of Draw that can be batched.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum BatchKind {
+    Rects,
+    FillRects,
+    Points,
+}
+
+/// Rectangles or points waiting to be drawn with one call. Only one of `rects` and `points` is in
+/// use at a time, and both keep their allocations from one batch to the next.
+#[derive(Default)]
+struct Batch {
+    // what is waiting, and in which color; None when nothing is
+    pending: Option<(BatchKind, pixels::Color)>,
+    rects: Vec<shape::Rect>,
+    points: Vec<shape::Point>,
+}
+
+/// Layers holds the recorded commands of every layer, sorted by layer index, and the batch of
+/// draws waiting to be executed.
+#[derive(Default)]
+pub(crate) struct Layers {
+    active: Option<usize>,
+    // (index, commands), sorted by index. Emptied every frame, but never removed, so both the
+    // outer and the inner Vecs keep their allocations from one frame to the next.
+    layers: Vec<(i32, Vec<Command>)>,
+    batch: Batch,
+    // execute every draw straight away instead of batching
+    pub(crate) immediate: bool,
+    /// What has been drawn so far this frame.
+    pub(crate) stats: DrawStats,
+    applied: Applied,
+}
+
+impl Layers {
+    /// Record to the layer with this index from now on.
+    pub(crate) fn select(&mut self, index: i32) {
+        let position = match self.layers.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(position) => position,
+            Err(position) => {
+                self.layers.insert(position, (index, vec![]));
+                position
+            }
+        };
+        self.active = Some(position);
+    }
+
+    /// Return what has been drawn this frame and start counting again from zero.
+    pub(crate) fn take_stats(&mut self) -> DrawStats {
+        self.applied.draw_color = None;
+        self.applied.blend_mode = None;
+        self.applied.textures.clear();
+        std::mem::take(&mut self.stats)
+    }
+
+    /// Set the canvas's draw color, counting it in the stats if it changed.
+    pub(crate) fn set_draw_color(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        color: pixels::Color,
+    ) {
+        self.applied.set_draw_color(canvas, color, &mut self.stats);
+    }
+
+    /// Set the canvas's blend mode, counting it in the stats if it changed.
+    pub(crate) fn set_blend_mode(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        mode: render::BlendMode,
+    ) {
+        self.applied.set_blend_mode(canvas, mode, &mut self.stats);
+    }
+
+    /// Set the tint and blend mode of `texture`, counting each in the stats if it changed.
+    pub(crate) fn set_texture_state(
+        &mut self,
+        texture: &mut render::Texture,
+        color: pixels::Color,
+        blend: render::BlendMode,
+    ) {
+        self.applied
+            .set_texture_state(texture, color, blend, &mut self.stats);
+    }
+
+    /// Stop recording and return what was being recorded to, so it can be resumed with `resume`.
+    pub(crate) fn pause(&mut self) -> Option<usize> {
+        self.active.take()
+    }
+
+    pub(crate) fn resume(&mut self, active: Option<usize>) {
+        self.active = active;
+    }
+
+    /// Record `draw` if a layer is selected, or draw it straight away otherwise.
+    pub(crate) fn submit(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        draw: Draw,
+        color: pixels::Color,
+        viewport: Option<shape::Rect>,
+    ) {
+        match self.active {
+            None => self.execute(canvas, draw, color),
+            Some(position) => self.layers[position].1.push(Command {
+                draw,
+                color,
+                viewport,
+                clip_rect: canvas.clip_rect(),
+            }),
+        }
+    }
+
+    /// Replay every recorded command, lowest layer index first, then empty the layers. The canvas
+    /// is left with `viewport` and `clip_rect`.
+    pub(crate) fn flush(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        viewport: Option<shape::Rect>,
+        clip_rect: Option<shape::Rect>,
+    ) {
+        self.flush_batch(canvas);
+        let (mut current_viewport, mut current_clip) = (viewport, clip_rect);
+        let mut layers = std::mem::take(&mut self.layers);
+        for (_, commands) in layers.iter_mut() {
+            for command in commands.drain(..) {
+                if command.viewport != current_viewport || command.clip_rect != current_clip {
+                    self.flush_batch(canvas);
+                }
+                if command.viewport != current_viewport {
+                    current_viewport = command.viewport;
+                    canvas.set_viewport(current_viewport);
+                }
+                if command.clip_rect != current_clip {
+                    current_clip = command.clip_rect;
+                    canvas.set_clip_rect(current_clip);
+                }
+                self.execute(canvas, command.draw, command.color);
+            }
+        }
+        self.layers = layers;
+        self.flush_batch(canvas);
+        if current_viewport != viewport {
+            canvas.set_viewport(viewport);
+        }
+        if current_clip != clip_rect {
+            canvas.set_clip_rect(clip_rect);
+        }
+    }
+
+    /// Carry out `draw` with `color`, or add it to the batch if it can be drawn together with
+    /// what is already waiting there.
+    fn execute(
+        &mut self,
+        canvas: &mut render::Canvas<video::Window>,
+        draw: Draw,
+        color: pixels::Color,
+    ) {
+        let kind = match draw {
+            _ if self.immediate => None,
+            Draw::Rect(_) => Some(BatchKind::Rects),
+            Draw::FillRect(_) => Some(BatchKind::FillRects),
+            Draw::Points(_) => Some(BatchKind::Points),
+            Draw::Copy { .. } | Draw::CopyRotated { .. } => None,
+        };
+        let Some(kind) = kind else {
+            self.flush_batch(canvas);
+            return execute(canvas, &draw, color, &mut self.applied, &mut self.stats);
+        };
+        if self.batch.pending != Some((kind, color)) {
+            self.flush_batch(canvas);
+            self.batch.pending = Some((kind, color));
+        }
+        match draw {
+            Draw::Rect(rect) | Draw::FillRect(rect) => self.batch.rects.push(rect),
+            Draw::Points(points) => self.batch.points.extend(points),
+            Draw::Copy { .. } | Draw::CopyRotated { .. } => (),
+        }
+    }
+
+    /// Draw whatever is waiting in the batch. This must happen before anything else is drawn and
+    /// before the canvas state changes, so that the batch is drawn as it would have been without
+    /// batching.
+    pub(crate) fn flush_batch(&mut self, canvas: &mut render::Canvas<video::Window>) {
+        let Some((kind, color)) = self.batch.pending.take() else {
+            return;
+        };
+        self.stats.primitives += 1;
+        self.applied.set_draw_color(canvas, color, &mut self.stats);
+        let batch = &mut self.batch;
+        match kind {
+            BatchKind::Rects => canvas.draw_rects(&batch.rects).unwrap(),
+            BatchKind::FillRects => canvas.fill_rects(&batch.rects).unwrap(),
+            BatchKind::Points => canvas.draw_points(&batch.points[..]).unwrap(),
+        }
+        batch.rects.clear();
+        batch.points.clear();
+    }
+}
+
+/// Carry out one Draw with `color` as the draw color and texture tint, counting it in `stats`.
+fn execute(
+    canvas: &mut render::Canvas<video::Window>,
+    draw: &Draw,
+    color: pixels::Color,
+    applied: &mut Applied,
+    stats: &mut DrawStats,
+) {
+    match *draw {
+        Draw::Rect(_) | Draw::FillRect(_) | Draw::Points(_) => {
+            stats.primitives += 1;
+            applied.set_draw_color(canvas, color, stats);
+        }
+        Draw::Copy { glyph, .. } | Draw::CopyRotated { glyph, .. } => {
+            stats.texture_copies += 1;
+            stats.glyphs += glyph as u32;
+        }
+    }
+    match *draw {
+        Draw::Rect(rect) => canvas.draw_rect(rect).unwrap(),
+        Draw::FillRect(rect) => canvas.fill_rect(rect).unwrap(),
+        Draw::Points(ref points) => canvas.draw_points(&points[..]).unwrap(),
+        Draw::Copy {
+            texture,
+            src,
+            dst,
+            blend,
+            ..
+        } => {
+            let mut texture = texture_of(canvas, texture);
+            let blend = blend.unwrap_or_else(|| canvas.blend_mode());
+            applied.set_texture_state(&mut texture, color, blend, stats);
+            canvas.copy(&texture, src, dst).unwrap();
+        }
+        Draw::CopyRotated {
+            texture,
+            src,
+            dst,
+            angle,
+            center,
+            flip,
+            blend,
+            ..
+        } => {
+            let mut texture = texture_of(canvas, texture);
+            let blend = blend.unwrap_or_else(|| canvas.blend_mode());
+            applied.set_texture_state(&mut texture, color, blend, stats);
+            canvas
+                .copy_ex(&texture, src, dst, angle, center, flip.0, flip.1)
+                .unwrap();
+        }
+    }
+}
+
+pub(crate) fn texture_of(canvas: &render::Canvas<video::Window>, id: TextureId) -> render::Texture {
+    // SAFETY: see TextureId. The texture still exists, and was created by this canvas.
+    unsafe { canvas.raw_create_texture(id.0) }
+}
@@ -9,9 +9,19 @@ extern crate rand;
 extern crate sdl2;
 
 // Re-export some of the symbols from the other modules.
+pub use atlas::{Atlas, AtlasBuilder, AtlasRegion};
+pub use decode::{DecodedFont, DecodedImage, decode_font, decode_image};
+pub use error::SimpleError;
 pub use event::Event;
-pub use shape::{Point, Polygon, Rect};
-pub use window::{Font, Image, Window};
+pub use event::{key_from_name, key_name};
+pub use font::{Align, Font, MissingGlyphMode, TextOptions, VAlign};
+pub use frame::{Frame, ImageDraw};
+pub use hook::{HookId, HookResult};
+pub use layer::DrawStats;
+pub use shape::{Anchor, Point, Polygon, Rect, RectExt, simplify_polyline, smooth_polyline};
+pub use window::{
+    BackgroundFit, BlendMode, ControllerInfo, Image, ScaleQuality, Window, is_display_available,
+};
 
 // rustdoc has some bugs right now and the below code works around this. Rust issue link:
 // https://github.com/rust-lang/rust/issues/24305
@@ -21,7 +31,46 @@ pub use window::{Font, Image, Window};
 pub use event::Key;
 pub use event::MouseButton;
 
+pub mod camera;
+pub mod collision;
+#[cfg(any(debug_assertions, feature = "console"))]
+pub mod console;
+pub mod font;
+pub mod grid;
+pub mod noise;
+pub mod pack;
+pub mod rng;
+pub mod sprite;
+pub mod text_field;
+
+mod atlas;
+mod decode;
+mod error;
 mod event;
+mod frame;
+mod hook;
+mod layer;
+mod replay;
 mod shape;
+mod texture;
 mod util;
 mod window;
+
+/// Log a formatted line to the debug console of the Window running on this thread, like
+/// `simple::log!("score {}", score)`. The line shows up at the start of the next frame. In release
+/// builds without the `console` feature this does nothing. See the `console` module.
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::log_fmt(format_args!($($arg)*))
+    };
+}
+
+/// What `log!` calls. Use the macro instead.
+#[doc(hidden)]
+pub fn log_fmt(args: std::fmt::Arguments) {
+    #[cfg(any(debug_assertions, feature = "console"))]
+    console::log_fmt(args);
+    #[cfg(not(any(debug_assertions, feature = "console")))]
+    let _ = args;
+}
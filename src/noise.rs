@@ -0,0 +1,119 @@
+/*!
+ * Smooth noise for procedural content such as terrain, clouds and starfields.
+ *
+ * Noise2D is value noise: a random value at every point of the integer grid, smoothly
+ * interpolated in between. Like `rng::Rng` it only uses integer arithmetic and the basic float
+ * operations, which give the same result everywhere, so a seed produces the same noise on every
+ * platform.
+ */
+
+/// Noise2D is seeded two-dimensional noise. See the module documentation.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Noise2D {
+    seed: u64,
+}
+
+impl Noise2D {
+    /// Create noise whose values are determined entirely by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Noise2D { seed }
+    }
+
+    /// Return the noise at (x, y), between -1 and 1. It changes smoothly with x and y, and
+    /// features are about 1 unit apart, so scale the coordinates to zoom in or out.
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        self.interpolate(x, y, |ix, iy| (ix, iy))
+    }
+
+    /// Like `sample`, but the noise repeats every `period` units in both directions, so it can
+    /// be used for a texture that tiles. A period of zero is taken as one.
+    pub fn sample_wrapped(&self, x: f32, y: f32, period: u32) -> f32 {
+        let period = period.max(1) as i64;
+        self.interpolate(x, y, |ix, iy| {
+            (ix.rem_euclid(period), iy.rem_euclid(period))
+        })
+    }
+
+    /// Add `n` layers of noise, each with twice the detail of the one before and `persistence`
+    /// times its strength, for a rougher look. The result is scaled back to between -1 and 1.
+    pub fn octaves(&self, x: f32, y: f32, n: u32, persistence: f32) -> f32 {
+        let (mut total, mut max) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for _ in 0..n {
+            total += self.sample(x * frequency, y * frequency) * amplitude;
+            max += amplitude;
+            frequency *= 2.0;
+            amplitude *= persistence;
+        }
+        if max > 0.0 { total / max } else { 0.0 }
+    }
+
+    /// Blend the values at the four grid points around (x, y). `wrap` maps a grid point to the
+    /// one whose value it takes.
+    fn interpolate<F: Fn(i64, i64) -> (i64, i64)>(&self, x: f32, y: f32, wrap: F) -> f32 {
+        let (fx, fy) = (x.floor(), y.floor());
+        let (ix, iy) = (fx as i64, fy as i64);
+        let (tx, ty) = (fade(x - fx), fade(y - fy));
+        let value = |dx: i64, dy: i64| {
+            let (gx, gy) = wrap(ix + dx, iy + dy);
+            self.lattice(gx, gy)
+        };
+        let top = lerp(value(0, 0), value(1, 0), tx);
+        let bottom = lerp(value(0, 1), value(1, 1), tx);
+        lerp(top, bottom, ty)
+    }
+
+    /// The random value, between -1 and 1, at a point of the integer grid.
+    fn lattice(&self, x: i64, y: i64) -> f32 {
+        // splitmix64's finalizer over the seed and both coordinates
+        let mut h = self.seed
+            ^ (x as u64).wrapping_mul(0x9E3779B97F4A7C15)
+            ^ (y as u64).wrapping_mul(0xC2B2AE3D27D4EB4F);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D049BB133111EB);
+        h ^= h >> 31;
+        (h >> 40) as f32 / (1u32 << 23) as f32 - 1.0
+    }
+}
+
+/// Ease `t`, between 0 and 1, so the noise has no visible creases at the grid lines.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[test]
+fn test_noise_range_and_determinism() {
+    let noise = Noise2D::new(9);
+    let mut lowest: f32 = 1.0;
+    let mut highest: f32 = -1.0;
+    for i in 0..2000 {
+        let (x, y) = (i as f32 * 0.37 - 300.0, i as f32 * 0.11 - 50.0);
+        for n in [noise.sample(x, y), noise.octaves(x, y, 4, 0.5)] {
+            assert!((-1.0..=1.0).contains(&n));
+            lowest = lowest.min(n);
+            highest = highest.max(n);
+        }
+    }
+    // the whole range gets used
+    assert!(lowest < -0.7 && highest > 0.7);
+
+    assert!(Noise2D::new(9).sample(1.5, 2.25) == noise.sample(1.5, 2.25));
+    assert!(Noise2D::new(10).sample(1.5, 2.25) != noise.sample(1.5, 2.25));
+    assert!(noise.octaves(1.5, 2.25, 0, 0.5) == 0.0);
+}
+
+#[test]
+fn test_noise_wraps() {
+    let noise = Noise2D::new(4);
+    for i in 0..64 {
+        // multiples of 1/8 are exact, so shifting by the period changes nothing but the grid cell
+        let (x, y) = (i as f32 * 0.125, (63 - i) as f32 * 0.375);
+        let n = noise.sample_wrapped(x, y, 5);
+        assert!(noise.sample_wrapped(x + 5.0, y, 5) == n);
+        assert!(noise.sample_wrapped(x, y - 10.0, 5) == n);
+    }
+}
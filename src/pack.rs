@@ -0,0 +1,157 @@
+/*!
+ * Packing rectangles into a larger one, such as images into a texture atlas. `AtlasBuilder` uses
+ * this to lay out its pages; it is just as useful for thumbnails generated at run time, or for
+ * laying out a grid of controls.
+ */
+
+use crate::shape;
+
+/// ShelfPacker places rectangles left to right on horizontal shelves, opening a new shelf below
+/// the current one when a rectangle doesn't fit. Feeding it rectangles sorted by decreasing height
+/// wastes very little space; `pack_all` does that sorting for you.
+///
+/// Rectangles never overlap and never go past the packer's bounds. A Rect can't be empty, so a
+/// zero width or height is packed as one pixel.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+    used_width: u32,
+}
+
+impl ShelfPacker {
+    /// Create an empty packer for a `width` by `height` area.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::new_with_padding(width, height, 0)
+    }
+
+    /// Like `new`, but leave `padding` empty pixels between rectangles. One pixel is enough to
+    /// stop neighbouring images bleeding into each other when a texture is drawn with linear
+    /// filtering.
+    pub fn new_with_padding(width: u32, height: u32, padding: u32) -> Self {
+        ShelfPacker {
+            width,
+            height,
+            padding,
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+            used_width: 0,
+        }
+    }
+
+    /// Find room for a `w` by `h` rectangle, or return None if there is none left. A failed
+    /// insert changes nothing, so smaller rectangles may still fit afterwards.
+    pub fn insert(&mut self, w: u32, h: u32) -> Option<shape::Rect> {
+        let (w, h) = (w.max(1), h.max(1));
+        if w > self.width {
+            return None;
+        }
+        let (mut x, mut y, mut shelf_height) = (self.shelf_x, self.shelf_y, self.shelf_height);
+        if x + w > self.width {
+            // start a new shelf under the current one
+            y += shelf_height;
+            x = 0;
+            shelf_height = 0;
+        }
+        if y + h > self.height {
+            return None;
+        }
+
+        self.shelf_x = x;
+        self.shelf_y = y;
+        self.shelf_height = shelf_height;
+        let rect = shape::Rect::new(self.shelf_x as i32, self.shelf_y as i32, w, h);
+        self.used_width = self.used_width.max(self.shelf_x + w);
+        self.shelf_x += w + self.padding;
+        self.shelf_height = self.shelf_height.max(h + self.padding);
+        Some(rect)
+    }
+
+    /// Insert rectangles of all of the `sizes`, tallest first, and return where each one went, in
+    /// the order of `sizes`. If they don't all fit, return None and leave the packer as it was.
+    pub fn pack_all(&mut self, sizes: &[(u32, u32)]) -> Option<Vec<shape::Rect>> {
+        let mut order: Vec<usize> = (0..sizes.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+        let mut packer = self.clone();
+        let mut rects = vec![shape::Rect::new(0, 0, 1, 1); sizes.len()];
+        for i in order {
+            rects[i] = packer.insert(sizes[i].0, sizes[i].1)?;
+        }
+        *self = packer;
+        Some(rects)
+    }
+
+    /// Forget every rectangle inserted so far, making all of the area available again.
+    pub fn reset(&mut self) {
+        *self = Self::new_with_padding(self.width, self.height, self.padding);
+    }
+
+    /// Return the smallest size that contains every rectangle inserted so far.
+    pub fn used_size(&self) -> (u32, u32) {
+        let bottom = self.shelf_y + self.shelf_height.saturating_sub(self.padding);
+        (self.used_width.max(1), bottom.max(1))
+    }
+}
+
+#[test]
+fn test_shelf_packer() {
+    let mut packer = ShelfPacker::new(10, 10);
+    assert!(packer.insert(6, 4) == Some(shape::Rect::new(0, 0, 6, 4)));
+    assert!(packer.insert(4, 3) == Some(shape::Rect::new(6, 0, 4, 3)));
+
+    // doesn't fit on the first shelf, so a second shelf is opened
+    assert!(packer.insert(5, 5) == Some(shape::Rect::new(0, 4, 5, 5)));
+    assert!(packer.used_size() == (10, 9));
+
+    // too tall for what's left, and too wide for any page
+    assert!(packer.insert(6, 2).is_none());
+    assert!(packer.insert(11, 1).is_none());
+
+    // a failed insert leaves the current shelf usable
+    assert!(packer.insert(5, 1) == Some(shape::Rect::new(5, 4, 5, 1)));
+
+    packer.reset();
+    assert!(packer.insert(10, 10) == Some(shape::Rect::new(0, 0, 10, 10)));
+
+    let mut padded = ShelfPacker::new_with_padding(10, 10, 1);
+    assert!(padded.insert(4, 4) == Some(shape::Rect::new(0, 0, 4, 4)));
+    assert!(padded.insert(5, 2) == Some(shape::Rect::new(5, 0, 5, 2)));
+    assert!(padded.insert(1, 1) == Some(shape::Rect::new(0, 5, 1, 1)));
+    assert!(padded.used_size() == (10, 6));
+}
+
+#[test]
+fn test_pack_all_never_overlaps_or_overflows() {
+    let mut rng = crate::rng::Rng::from_seed(11);
+    for &padding in &[0, 1, 3] {
+        let sizes: Vec<(u32, u32)> = (0..300)
+            .map(|_| (rng.range_i32(0, 40) as u32, rng.range_i32(0, 40) as u32))
+            .collect();
+        let mut packer = ShelfPacker::new_with_padding(1024, 1024, padding);
+        let rects = packer.pack_all(&sizes).unwrap();
+
+        let bounds = shape::Rect::new(0, 0, 1024, 1024);
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.size() == (sizes[i].0.max(1), sizes[i].1.max(1)));
+            assert!(bounds.contains_rect(*a));
+            for b in &rects[i + 1..] {
+                // the padding must be clear of every other rectangle, in both directions
+                let apart = |a: &shape::Rect, b: &shape::Rect| {
+                    a.right() + padding as i32 <= b.left() || a.bottom() + padding as i32 <= b.top()
+                };
+                assert!(apart(a, b) || apart(b, a));
+            }
+        }
+    }
+
+    // too much to fit: nothing is packed
+    let mut packer = ShelfPacker::new(10, 10);
+    assert!(packer.pack_all(&[(10, 6), (10, 6)]).is_none());
+    assert!(packer.insert(10, 10).is_some());
+}
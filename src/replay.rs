@@ -0,0 +1,484 @@
+/*!
+ * Recording a session's input to a file and playing it back, to reproduce a bug exactly. See
+ * `Window::start_input_recording` and `Window::play_input_recording`.
+ *
+ * A recording is a text file. Its first line names the format and its version; every line after
+ * that is one record, its fields separated by tabs:
+ *
+ * - `F frame frame_time mouse_x mouse_y mouse_buttons velocity_x velocity_y keys` starts a frame.
+ *   `frame_time` is what `Window::frame_time` reported during it, and the rest is the keyboard
+ *   and mouse snapshot the frame started with: `mouse_buttons` is SDL's button mask, the velocity
+ *   is what `Window::mouse_velocity` reported, and `keys` is the scancodes of the keys that were
+ *   down, separated by commas.
+ * - `P frame ms scancode` is a key press, `ms` milliseconds after the Window was created.
+ * - `E frame ms kind fields...` is an event that was queued, named by its `Event` variant.
+ *
+ * Tabs, newlines and backslashes in text are escaped with a backslash.
+ */
+
+use std::collections::VecDeque;
+use std::io::Write;
+
+use crate::error::SimpleError;
+use crate::event::{Event, Key, MouseButton};
+
+/// The first line of every recording, before the version.
+const FORMAT: &str = "simple input recording";
+/// The version of the format written by this version of simple, and the only one it reads.
+const VERSION: u32 = 2;
+
+/// The input a frame started with: everything `Window::begin_frame` takes from SDL.
+#[derive(Clone, PartialEq, Default)]
+pub(crate) struct FrameInput {
+    pub(crate) frame_time: u32,
+    pub(crate) mouse_position: (i32, i32),
+    pub(crate) mouse_buttons: u32,
+    pub(crate) mouse_velocity: (f32, f32),
+    pub(crate) keys_down: Vec<Key>,
+    // (key, ms since the Window was created) of each key press
+    pub(crate) key_presses: Vec<(Key, u32)>,
+    // (ms since the Window was created, event) of each event, before hooks saw it
+    pub(crate) events: Vec<(u32, Event)>,
+}
+
+/// Recorder writes one FrameInput after another to a recording.
+pub(crate) struct Recorder<W: Write> {
+    out: W,
+    frame: u64,
+    // the input of the frame being taken, written out by end_frame
+    pub(crate) input: FrameInput,
+    // the first write that failed; nothing more is written after it
+    error: Option<SimpleError>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording on `out` by writing the header.
+    pub(crate) fn new(mut out: W) -> Result<Self, SimpleError> {
+        writeln!(out, "{} {}", FORMAT, VERSION).map_err(write_error)?;
+        Ok(Recorder {
+            out,
+            frame: 0,
+            input: FrameInput::default(),
+            error: None,
+        })
+    }
+
+    /// Write out the input collected for this frame and start collecting the next. The output is
+    /// flushed every frame, so the recording survives a crash.
+    pub(crate) fn end_frame(&mut self) {
+        let input = std::mem::take(&mut self.input);
+        if self.error.is_none() {
+            let written =
+                write_frame(&mut self.out, self.frame, &input).and_then(|_| self.out.flush());
+            self.error = written.err().map(write_error);
+        }
+        self.frame += 1;
+    }
+
+    /// End the recording, and return the first error writing it met, if any.
+    pub(crate) fn finish(mut self) -> Result<(), SimpleError> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush().map_err(write_error),
+        }
+    }
+
+    #[cfg(test)]
+    fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn write_error(e: std::io::Error) -> SimpleError {
+    SimpleError::Write(e.to_string())
+}
+
+fn write_frame(out: &mut impl Write, frame: u64, input: &FrameInput) -> std::io::Result<()> {
+    let keys: Vec<String> = input
+        .keys_down
+        .iter()
+        .map(|&k| (k as i32).to_string())
+        .collect();
+    writeln!(
+        out,
+        "F\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        frame,
+        input.frame_time,
+        input.mouse_position.0,
+        input.mouse_position.1,
+        input.mouse_buttons,
+        input.mouse_velocity.0,
+        input.mouse_velocity.1,
+        keys.join(",")
+    )?;
+    for &(key, ms) in &input.key_presses {
+        writeln!(out, "P\t{}\t{}\t{}", frame, ms, key as i32)?;
+    }
+    for (ms, e) in &input.events {
+        if let Some(fields) = encode_event(e) {
+            writeln!(out, "E\t{}\t{}\t{}", frame, ms, fields)?;
+        }
+    }
+    Ok(())
+}
+
+/// Return the fields of the `E` record for `e`. The image loading events aren't input, and are
+/// sent again when a replayed session loads its images, so they aren't recorded.
+fn encode_event(e: &Event) -> Option<String> {
+    let down = |is_down: bool| if is_down { "down" } else { "up" };
+    Some(match *e {
+        Event::Keyboard {
+            is_down,
+            key,
+            logical,
+        } => format!(
+            "Keyboard\t{}\t{}\t{}",
+            down(is_down),
+            key as i32,
+            logical.map_or("-".to_string(), |k| (k as i32).to_string())
+        ),
+        Event::Mouse {
+            is_down,
+            button,
+            mouse_x,
+            mouse_y,
+        } => format!(
+            "Mouse\t{}\t{}\t{}\t{}",
+            down(is_down),
+            button as u8,
+            mouse_x,
+            mouse_y
+        ),
+        Event::MouseMotion {
+            mouse_x,
+            mouse_y,
+            dx,
+            dy,
+        } => format!("MouseMotion\t{}\t{}\t{}\t{}", mouse_x, mouse_y, dx, dy),
+        Event::MouseEnter => "MouseEnter".to_string(),
+        Event::MouseLeave => "MouseLeave".to_string(),
+        Event::TextInput { ref text } => format!("TextInput\t{}", escape(text)),
+        Event::TextEditing {
+            ref text,
+            cursor,
+            selection_len,
+        } => format!(
+            "TextEditing\t{}\t{}\t{}",
+            escape(text),
+            cursor,
+            selection_len
+        ),
+        Event::ControllerAdded { id } => format!("ControllerAdded\t{}", id),
+        Event::ControllerRemoved { id } => format!("ControllerRemoved\t{}", id),
+        Event::ImageLoaded { .. } | Event::ImageLoadFailed { .. } => return None,
+        Event::Shown => "Shown".to_string(),
+        Event::Hidden => "Hidden".to_string(),
+        Event::Exposed => "Exposed".to_string(),
+        Event::RenderTargetsLost => "RenderTargetsLost".to_string(),
+        Event::Quit => "Quit".to_string(),
+    })
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            other => return Err(format!("bad escape {:?} in text", other)),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Playback hands out the frames of a recording one at a time.
+pub(crate) struct Playback {
+    frames: VecDeque<FrameInput>,
+}
+
+impl Playback {
+    /// Read a recording. Recordings made by another version of the format are refused with
+    /// `SimpleError::Unsupported`, and malformed ones with `SimpleError::InvalidArgument` naming
+    /// the line at fault.
+    pub(crate) fn parse(text: &str) -> Result<Self, SimpleError> {
+        let mut lines = text.lines();
+        let version = lines
+            .next()
+            .and_then(|header| header.strip_prefix(FORMAT))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| SimpleError::InvalidArgument("not an input recording".to_string()))?;
+        if version != VERSION {
+            return Err(SimpleError::Unsupported(format!(
+                "input recording format version {} can't be played; this version of simple \
+                 plays version {}",
+                version, VERSION
+            )));
+        }
+
+        let mut frames: VecDeque<FrameInput> = VecDeque::new();
+        for (i, line) in lines.enumerate() {
+            parse_record(line, &mut frames).map_err(|e| {
+                // the header is line 1
+                SimpleError::InvalidArgument(format!("line {}: {}", i + 2, e))
+            })?;
+        }
+        Ok(Playback { frames })
+    }
+
+    /// Return the input of the next frame, or `None` once the recording is over.
+    pub(crate) fn next_frame(&mut self) -> Option<FrameInput> {
+        self.frames.pop_front()
+    }
+}
+
+/// Add the record on `line` to `frames`: an `F` record starts a new frame, and the others add to
+/// the last one, whose index they must give.
+fn parse_record(line: &str, frames: &mut VecDeque<FrameInput>) -> Result<(), String> {
+    let mut fields = line.split('\t');
+    let mut next = || fields.next().ok_or_else(|| "too few fields".to_string());
+    let kind = next()?;
+    let frame: usize = number(next()?)?;
+    let expected = if kind == "F" {
+        frames.len()
+    } else {
+        frames.len().wrapping_sub(1)
+    };
+    if frame != expected {
+        return Err(format!("frame {} is out of order", frame));
+    }
+
+    match kind {
+        "F" => {
+            let frame_time = number(next()?)?;
+            let mouse_position = (number(next()?)?, number(next()?)?);
+            let mouse_buttons = number(next()?)?;
+            let mouse_velocity = (number(next()?)?, number(next()?)?);
+            let keys_down = match next()? {
+                "" => vec![],
+                keys => keys.split(',').map(key).collect::<Result<_, _>>()?,
+            };
+            frames.push_back(FrameInput {
+                frame_time,
+                mouse_position,
+                mouse_buttons,
+                mouse_velocity,
+                keys_down,
+                ..FrameInput::default()
+            });
+        }
+        "P" => {
+            let ms = number(next()?)?;
+            let pressed = key(next()?)?;
+            frames[frame].key_presses.push((pressed, ms));
+        }
+        "E" => {
+            let ms = number(next()?)?;
+            let e = decode_event(next()?, &mut next)?;
+            frames[frame].events.push((ms, e));
+        }
+        _ => return Err(format!("unknown record {:?}", kind)),
+    }
+    Ok(())
+}
+
+/// Read the fields of an `E` record back into an Event, taking the fields after its name from
+/// `next`.
+fn decode_event<'a>(
+    name: &str,
+    next: &mut impl FnMut() -> Result<&'a str, String>,
+) -> Result<Event, String> {
+    let mut is_down = || match next()? {
+        "down" => Ok(true),
+        "up" => Ok(false),
+        other => Err(format!("expected down or up, got {:?}", other)),
+    };
+    Ok(match name {
+        "Keyboard" => {
+            let is_down = is_down()?;
+            let key = key(next()?)?;
+            let logical = match next()? {
+                "-" => None,
+                logical => Some(self::key(logical)?),
+            };
+            Event::Keyboard {
+                is_down,
+                key,
+                logical,
+            }
+        }
+        "Mouse" => Event::Mouse {
+            is_down: is_down()?,
+            button: MouseButton::from_ll(number(next()?)?),
+            mouse_x: number(next()?)?,
+            mouse_y: number(next()?)?,
+        },
+        "MouseMotion" => Event::MouseMotion {
+            mouse_x: number(next()?)?,
+            mouse_y: number(next()?)?,
+            dx: number(next()?)?,
+            dy: number(next()?)?,
+        },
+        "MouseEnter" => Event::MouseEnter,
+        "MouseLeave" => Event::MouseLeave,
+        "TextInput" => Event::TextInput {
+            text: unescape(next()?)?,
+        },
+        "TextEditing" => Event::TextEditing {
+            text: unescape(next()?)?,
+            cursor: number(next()?)?,
+            selection_len: number(next()?)?,
+        },
+        "ControllerAdded" => Event::ControllerAdded {
+            id: number(next()?)?,
+        },
+        "ControllerRemoved" => Event::ControllerRemoved {
+            id: number(next()?)?,
+        },
+        "Shown" => Event::Shown,
+        "Hidden" => Event::Hidden,
+        "Exposed" => Event::Exposed,
+        "RenderTargetsLost" => Event::RenderTargetsLost,
+        "Quit" => Event::Quit,
+        _ => return Err(format!("unknown event {:?}", name)),
+    })
+}
+
+fn number<T: std::str::FromStr>(field: &str) -> Result<T, String> {
+    field
+        .parse()
+        .map_err(|_| format!("expected a number, got {:?}", field))
+}
+
+fn key(field: &str) -> Result<Key, String> {
+    Key::from_i32(number(field)?).ok_or_else(|| format!("unknown scancode {}", field))
+}
+
+#[test]
+fn test_recording_round_trip() {
+    let frames = vec![
+        FrameInput {
+            frame_time: 16,
+            mouse_position: (10, -3),
+            mouse_buttons: 1,
+            mouse_velocity: (-250.5, 1e-3),
+            keys_down: vec![Key::A, Key::LShift],
+            key_presses: vec![(Key::A, 1500)],
+            events: vec![
+                (
+                    1500,
+                    Event::Keyboard {
+                        is_down: true,
+                        key: Key::A,
+                        logical: Some(Key::Q),
+                    },
+                ),
+                (
+                    1501,
+                    Event::TextInput {
+                        text: "tab\there\\ \n日本".to_string(),
+                    },
+                ),
+                (
+                    1502,
+                    Event::Mouse {
+                        is_down: false,
+                        button: MouseButton::Right,
+                        mouse_x: 10,
+                        mouse_y: -3,
+                    },
+                ),
+            ],
+        },
+        // a frame with nothing in it
+        FrameInput::default(),
+        FrameInput {
+            frame_time: 17,
+            events: vec![
+                (
+                    1540,
+                    Event::Keyboard {
+                        is_down: false,
+                        key: Key::Grave,
+                        logical: None,
+                    },
+                ),
+                (1541, Event::ControllerAdded { id: 3 }),
+                (1542, Event::Quit),
+            ],
+            ..FrameInput::default()
+        },
+    ];
+
+    let mut recorder = Recorder::new(vec![]).unwrap();
+    for frame in &frames {
+        recorder.input = frame.clone();
+        recorder.input.events.push((
+            1600,
+            Event::ImageLoaded {
+                key: "ship".to_string(),
+            },
+        ));
+        recorder.end_frame();
+    }
+    let text = String::from_utf8(recorder.into_inner()).unwrap();
+    assert!(
+        text.starts_with("simple input recording 2\nF\t0\t16\t10\t-3\t1\t-250.5\t0.001\t4,225\n")
+    );
+
+    let mut playback = Playback::parse(&text).unwrap();
+    for frame in &frames {
+        assert!(playback.next_frame().as_ref() == Some(frame));
+    }
+    assert!(playback.next_frame().is_none());
+}
+
+#[test]
+fn test_recording_versions_and_errors() {
+    let unsupported = Playback::parse("simple input recording 1\n").err().unwrap();
+    assert!(matches!(unsupported, SimpleError::Unsupported(_)));
+    assert!(unsupported.to_string().contains("version 1"));
+    assert!(Playback::parse("").is_err());
+    assert!(Playback::parse("some other file\n").is_err());
+
+    let cases = [
+        (
+            "F\t1\t16\t0\t0\t0\t0\t0\t\n",
+            "line 2: frame 1 is out of order",
+        ),
+        ("P\t0\t5\t4\n", "line 2: frame 0 is out of order"),
+        (
+            "F\t0\t16\t0\t0\t0\t0\t0\t\nE\t0\t5\tJump\n",
+            "line 3: unknown event \"Jump\"",
+        ),
+        ("F\t0\t16\t0\t0\n", "line 2: too few fields"),
+        (
+            "F\t0\tsoon\t0\t0\t0\t0\t0\t\n",
+            "line 2: expected a number, got \"soon\"",
+        ),
+    ];
+    for (records, message) in cases {
+        let e = Playback::parse(&format!("simple input recording 2\n{}", records));
+        assert!(e.err().unwrap().to_string() == message);
+    }
+}
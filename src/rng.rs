@@ -0,0 +1,179 @@
+/*!
+ * A small, seedable random number generator for games that need to be repeatable: replays,
+ * lockstep networking, or a level generated from a seed the player can share.
+ *
+ * Rng is PCG32 (O'Neill's permuted congruential generator, XSH RR variant). It only does integer
+ * arithmetic, so a seed produces the same sequence on every platform. The sequence for a given
+ * seed, and the way each method draws from it, will not change within a major version of simple.
+ */
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+/// Rng is a deterministic random number generator. See the module documentation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator whose sequence is determined entirely by `seed`.
+    pub fn from_seed(seed: u64) -> Self {
+        // the standard PCG seeding procedure
+        let mut rng = Rng { state: 0 };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+    }
+
+    /// Return a random u32. Every value is equally likely.
+    pub fn u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Return a random number from `0` up to but not including `n`, without the bias that `% n`
+    /// would have. `n` must not be zero.
+    fn below(&mut self, n: u32) -> u32 {
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let r = self.u32();
+            if r >= threshold {
+                return r % n;
+            }
+        }
+    }
+
+    /// Return a random number from `min` up to and including `max`. If `max` is less than `min`,
+    /// return `min`.
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        let span = max as i64 - min as i64 + 1;
+        if span > u32::MAX as i64 {
+            // the whole range of i32
+            return self.u32() as i32;
+        }
+        (min as i64 + self.below(span as u32) as i64) as i32
+    }
+
+    /// Return a random number that is at least 0 and less than 1.
+    pub fn f32(&mut self) -> f32 {
+        (self.u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Return true with probability `p`: never for 0 or less, always for 1 or more.
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.f32() < p
+    }
+
+    /// Return a random item of `items`, each one equally likely, or `None` if there are none.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u32) as usize)
+    }
+
+    /// Return a random item of `items`, each one as likely as its weight says: an item of weight 2
+    /// comes up twice as often as one of weight 1. Items whose weight is zero or less never come
+    /// up. Return `None` if no item has a positive weight.
+    pub fn pick_weighted<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+        let total: f32 = items.iter().map(|&(_, w)| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut r = self.f32() * total;
+        let mut last = None;
+        for (item, weight) in items {
+            if *weight <= 0.0 {
+                continue;
+            }
+            if r < *weight {
+                return Some(item);
+            }
+            r -= weight;
+            last = Some(item);
+        }
+        // rounding can leave r just past the end
+        last
+    }
+
+    /// Put the items of `slice` in a random order. Every order is equally likely.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.below(i as u32 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+}
+
+#[test]
+fn test_sequence_is_pinned() {
+    // If this test fails, the sequence has changed, which breaks every saved replay.
+    let mut rng = Rng::from_seed(42);
+    let first: Vec<u32> = (0..5).map(|_| rng.u32()).collect();
+    assert!(first == [3270867926, 1795671209, 1924641435, 1143034755, 4121910957]);
+
+    let mut rng = Rng::from_seed(0);
+    let first: Vec<u32> = (0..3).map(|_| rng.u32()).collect();
+    assert!(first == [3894649422, 2055130073, 2315086854]);
+
+    let mut rng = Rng::from_seed(7);
+    let first: Vec<i32> = (0..5).map(|_| rng.range_i32(-10, 10)).collect();
+    assert!(first == [5, -1, 7, 5, 6]);
+
+    let mut rng = Rng::from_seed(7);
+    let mut items = [0, 1, 2, 3, 4, 5];
+    rng.shuffle(&mut items);
+    assert!(items == [1, 5, 0, 4, 2, 3]);
+}
+
+#[test]
+fn test_ranges() {
+    let mut rng = Rng::from_seed(1);
+    for _ in 0..1000 {
+        let n = rng.range_i32(3, 5);
+        assert!((3..=5).contains(&n));
+        let f = rng.f32();
+        assert!((0.0..1.0).contains(&f));
+    }
+    assert!(rng.range_i32(5, 5) == 5);
+    assert!(rng.range_i32(5, 1) == 5);
+    rng.range_i32(i32::MIN, i32::MAX);
+    assert!(!rng.chance(0.0));
+    assert!(rng.chance(1.0));
+}
+
+#[test]
+fn test_pick() {
+    let mut rng = Rng::from_seed(5);
+    let items = ["a", "b", "c"];
+    assert!((0..100).all(|_| items.contains(rng.pick(&items).unwrap())));
+    assert!(rng.pick::<u8>(&[]).is_none());
+
+    let weighted = [
+        ("never", 0.0),
+        ("rare", 1.0),
+        ("common", 9.0),
+        ("negative", -5.0),
+    ];
+    let mut common = 0;
+    for _ in 0..1000 {
+        match *rng.pick_weighted(&weighted).unwrap() {
+            "common" => common += 1,
+            "rare" => (),
+            other => panic!("picked {}", other),
+        }
+    }
+    assert!((850..950).contains(&common));
+    assert!(rng.pick_weighted(&[("none", 0.0)]).is_none());
+}
@@ -8,3 +8,476 @@ pub use sdl2::rect::Rect;
 ///
 /// Polygon is mostly being set aside for now. May revisit in the future.
 pub type Polygon = Vec<Point>;
+
+/// Reduce a polyline, such as a stroke recorded from the mouse, to fewer points that stay within
+/// `tolerance` pixels of the original (the Ramer-Douglas-Peucker algorithm). The first and last
+/// points are always kept, so a closed loop stays closed. Polylines of two points or fewer are
+/// returned unchanged.
+pub fn simplify_polyline(points: &[Point], tolerance: f32) -> Vec<Point> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // a stack of (start, end) spans instead of recursion, so long, noisy strokes can't overflow
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = spans.pop() {
+        let mut farthest = (0.0, start);
+        for i in start + 1..end {
+            let d = distance_to_segment(points[i], points[start], points[end]);
+            if d > farthest.0 {
+                farthest = (d, i);
+            }
+        }
+        if farthest.0 > tolerance {
+            keep[farthest.1] = true;
+            spans.push((start, farthest.1));
+            spans.push((farthest.1, end));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter(|&(_, keep)| keep)
+        .map(|(&p, _)| p)
+        .collect()
+}
+
+/// Round off the corners of a polyline by cutting each of them `iterations` times (Chaikin's
+/// algorithm). Every iteration nearly doubles the number of points. The first and last points are
+/// kept exactly, so a closed loop stays closed. Polylines of two points or fewer are returned
+/// unchanged.
+pub fn smooth_polyline(points: &[Point], iterations: u32) -> Vec<Point> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+    let mut line: Vec<(f32, f32)> = points
+        .iter()
+        .map(|p| (p.x() as f32, p.y() as f32))
+        .collect();
+    for _ in 0..iterations {
+        let mut cut = Vec::with_capacity(line.len() * 2);
+        cut.push(line[0]);
+        for pair in line.windows(2) {
+            let ((ax, ay), (bx, by)) = (pair[0], pair[1]);
+            cut.push((0.75 * ax + 0.25 * bx, 0.75 * ay + 0.25 * by));
+            cut.push((0.25 * ax + 0.75 * bx, 0.25 * ay + 0.75 * by));
+        }
+        cut.push(line[line.len() - 1]);
+        line = cut;
+    }
+
+    let mut smoothed: Vec<Point> = line
+        .iter()
+        .map(|&(x, y)| Point::new(x.round() as i32, y.round() as i32))
+        .collect();
+    // rounding makes neighbours collide; the exact last point goes back on below
+    smoothed.pop();
+    smoothed.dedup();
+    smoothed.push(points[points.len() - 1]);
+    smoothed
+}
+
+/// Return how far `p` is from the line segment from `a` to `b`.
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f32 {
+    let (px, py) = (p.x() as f32, p.y() as f32);
+    let (ax, ay) = (a.x() as f32, a.y() as f32);
+    let (dx, dy) = (b.x() as f32 - ax, b.y() as f32 - ay);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((px - ax) * dx + (py - ay) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    let (cx, cy) = (ax + t * dx, ay + t * dy);
+    ((px - cx).powi(2) + (py - cy).powi(2)).sqrt()
+}
+
+/// Extra methods for Rect, which comes from SDL2 and so can't be given methods of its own. Bring
+/// this into scope with `use simple::RectExt`.
+///
+/// SDL2 already provides `Rect::from_center`, `Point + Point` and `Point - Point`. Its
+/// `Rect::offset` moves the Rect in place; `translated` returns a moved copy instead.
+pub trait RectExt {
+    /// Return a copy of the Rect moved by `(dx, dy)`.
+    fn translated(&self, dx: i32, dy: i32) -> Rect;
+
+    /// Return a copy of the Rect grown by `dx` on the left and right and by `dy` on the top and
+    /// bottom, keeping its center. Negative amounts shrink it. A Rect can't be empty, so shrinking
+    /// it past nothing leaves it one pixel wide or high.
+    fn inflate(&self, dx: i32, dy: i32) -> Rect;
+
+    /// Return a copy of the Rect with `margin` taken off every side, or added if it is negative.
+    /// Like `inflate`, this never makes it smaller than one pixel.
+    fn inset(&self, margin: i32) -> Rect;
+
+    /// Cut the Rect in two side by side, the left part `at` pixels wide, and return the left and
+    /// right parts. `at` is kept between 1 and one less than the width, so each part is at least a
+    /// pixel wide; a Rect one pixel wide can't be cut, and comes back as both parts.
+    fn split_h(&self, at: u32) -> (Rect, Rect);
+
+    /// Like `split_h`, but cut the Rect into a top part `at` pixels high and a bottom part.
+    fn split_v(&self, at: u32) -> (Rect, Rect);
+
+    /// Cut the Rect into columns, left to right, as wide in proportion to each other as
+    /// `weights`. The columns cover the Rect exactly, without gaps or overlaps: pixels that don't
+    /// divide evenly go to the columns that lost the most to rounding, the leftmost first when
+    /// they tie. Each column is at least one pixel wide, as long as the Rect has that many
+    /// pixels to give. Weights that are negative or not numbers count as 0, and if none is
+    /// positive the columns are equal.
+    fn split_weighted_h(&self, weights: &[f32]) -> Vec<Rect>;
+
+    /// Like `split_weighted_h`, but cut the Rect into rows, top to bottom.
+    fn split_weighted_v(&self, weights: &[f32]) -> Vec<Rect>;
+
+    /// Return a Rect of `size` placed inside `parent` at `anchor`: `Anchor::BottomRight` puts its
+    /// bottom-right corner on the parent's. A Rect that is bigger than the parent sticks out of
+    /// it equally on both sides when centered.
+    fn anchored(parent: &Rect, anchor: Anchor, size: (u32, u32)) -> Rect;
+
+    /// Return the Rect between two opposite corners, whichever way round they are given: the
+    /// start and the current position of a mouse drag make a selection box. It spans from the
+    /// smaller to the larger coordinate on each axis, and is a pixel wide or high where the
+    /// corners line up.
+    fn from_corners(a: Point, b: Point) -> Rect;
+}
+
+/// Where `RectExt::anchored` places a Rect inside its parent.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Divide `length` pixels into parts as long in proportion to each other as `weights`, by the
+/// largest remainder method, and give every part at least one pixel if there are enough.
+fn weighted_lengths(length: u32, weights: &[f32]) -> Vec<u32> {
+    let sanitized: Vec<f64> = weights
+        .iter()
+        .map(|&w| if w > 0.0 { w as f64 } else { 0.0 })
+        .collect();
+    let total: f64 = sanitized.iter().sum();
+    let shares: Vec<f64> = if total > 0.0 && total.is_finite() {
+        sanitized
+            .iter()
+            .map(|w| w / total * length as f64)
+            .collect()
+    } else {
+        vec![length as f64 / weights.len() as f64; weights.len()]
+    };
+
+    let mut lengths: Vec<u32> = shares.iter().map(|s| s.floor() as u32).collect();
+    let mut left_over = length - lengths.iter().sum::<u32>().min(length);
+    let mut by_remainder: Vec<usize> = (0..lengths.len()).collect();
+    // a stable sort, so ties go to the earlier part
+    by_remainder.sort_by(|&a, &b| {
+        let remainder = |i: usize| shares[i] - lengths[i] as f64;
+        remainder(b).total_cmp(&remainder(a))
+    });
+    for &i in by_remainder.iter().cycle().take(lengths.len() * 2) {
+        if left_over == 0 {
+            break;
+        }
+        lengths[i] += 1;
+        left_over -= 1;
+    }
+
+    // Rects can't be empty, so take a pixel for each empty part from the longest one
+    while let Some(empty) = lengths.iter().position(|&l| l == 0) {
+        let longest = (0..lengths.len()).max_by_key(|&i| (lengths[i], usize::MAX - i));
+        match longest {
+            Some(longest) if lengths[longest] > 1 => {
+                lengths[longest] -= 1;
+                lengths[empty] = 1;
+            }
+            _ => break,
+        }
+    }
+    lengths
+}
+
+/// Where a span of `size` pixels starts when placed at the start, middle or end of a parent span.
+fn anchor_axis(start: i32, parent: u32, size: u32, position: u8) -> i32 {
+    let spare = parent as i64 - size as i64;
+    let offset = match position {
+        0 => 0,
+        1 => spare.div_euclid(2),
+        _ => spare,
+    };
+    (start as i64 + offset).clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+impl RectExt for Rect {
+    fn translated(&self, dx: i32, dy: i32) -> Rect {
+        let mut rect = *self;
+        rect.offset(dx, dy);
+        rect
+    }
+
+    fn inflate(&self, dx: i32, dy: i32) -> Rect {
+        let axis = |start: i32, length: u32, d: i32| {
+            let new_length = length as i64 + 2 * d as i64;
+            if new_length >= 1 {
+                (
+                    start.saturating_sub(d),
+                    new_length.min(u32::MAX as i64) as u32,
+                )
+            } else {
+                (start + (length / 2) as i32, 1)
+            }
+        };
+        let (x, width) = axis(self.x(), self.width(), dx);
+        let (y, height) = axis(self.y(), self.height(), dy);
+        Rect::new(x, y, width, height)
+    }
+
+    fn inset(&self, margin: i32) -> Rect {
+        self.inflate(margin.saturating_neg(), margin.saturating_neg())
+    }
+
+    fn split_h(&self, at: u32) -> (Rect, Rect) {
+        if self.width() < 2 {
+            return (*self, *self);
+        }
+        let at = at.clamp(1, self.width() - 1);
+        (
+            Rect::new(self.x(), self.y(), at, self.height()),
+            Rect::new(
+                self.x() + at as i32,
+                self.y(),
+                self.width() - at,
+                self.height(),
+            ),
+        )
+    }
+
+    fn split_v(&self, at: u32) -> (Rect, Rect) {
+        if self.height() < 2 {
+            return (*self, *self);
+        }
+        let at = at.clamp(1, self.height() - 1);
+        (
+            Rect::new(self.x(), self.y(), self.width(), at),
+            Rect::new(
+                self.x(),
+                self.y() + at as i32,
+                self.width(),
+                self.height() - at,
+            ),
+        )
+    }
+
+    fn split_weighted_h(&self, weights: &[f32]) -> Vec<Rect> {
+        let mut x = self.x();
+        weighted_lengths(self.width(), weights)
+            .into_iter()
+            .map(|width| {
+                let column = Rect::new(x, self.y(), width, self.height());
+                x += width as i32;
+                column
+            })
+            .collect()
+    }
+
+    fn split_weighted_v(&self, weights: &[f32]) -> Vec<Rect> {
+        let mut y = self.y();
+        weighted_lengths(self.height(), weights)
+            .into_iter()
+            .map(|height| {
+                let row = Rect::new(self.x(), y, self.width(), height);
+                y += height as i32;
+                row
+            })
+            .collect()
+    }
+
+    fn anchored(parent: &Rect, anchor: Anchor, size: (u32, u32)) -> Rect {
+        // 0 for the start of an axis, 1 for the middle and 2 for the end
+        let (h, v) = match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        };
+        Rect::new(
+            anchor_axis(parent.x(), parent.width(), size.0, h),
+            anchor_axis(parent.y(), parent.height(), size.1, v),
+            size.0,
+            size.1,
+        )
+    }
+
+    fn from_corners(a: Point, b: Point) -> Rect {
+        // the distance between two Points always fits in a u32
+        let span = |a: i32, b: i32| (a as i64 - b as i64).unsigned_abs() as u32;
+        Rect::new(
+            a.x().min(b.x()),
+            a.y().min(b.y()),
+            span(a.x(), b.x()),
+            span(a.y(), b.y()),
+        )
+    }
+}
+
+#[test]
+fn test_rect_ext() {
+    let rect = Rect::new(10, 20, 30, 40);
+    assert!(rect.translated(-15, 5) == Rect::new(-5, 25, 30, 40));
+    assert!(rect == Rect::new(10, 20, 30, 40));
+    assert!(rect.inflate(2, -5) == Rect::new(8, 25, 34, 30));
+    assert!(rect.inflate(-20, 0) == Rect::new(25, 20, 1, 40));
+
+    // Rects are never empty: zero sizes become one pixel
+    let tiny = Rect::new(-3, -3, 0, 0);
+    assert!(tiny.size() == (1, 1));
+    assert!(tiny.translated(-1, -1) == Rect::new(-4, -4, 1, 1));
+    assert!(tiny.inflate(1, 0) == Rect::new(-4, -3, 3, 1));
+
+    assert!(Rect::from_center(Point::new(0, 0), 4, 2) == Rect::new(-2, -1, 4, 2));
+    assert!(Point::new(1, -2) + Point::new(-3, 4) == Point::new(-2, 2));
+    assert!(Point::new(1, -2) - Point::new(-3, 4) == Point::new(4, -6));
+}
+
+#[test]
+fn test_from_corners_in_every_drag_direction() {
+    let expected = Rect::new(10, 20, 30, 40);
+    let (top_left, bottom_right) = (Point::new(10, 20), Point::new(40, 60));
+    let (top_right, bottom_left) = (Point::new(40, 20), Point::new(10, 60));
+    assert!(Rect::from_corners(top_left, bottom_right) == expected);
+    assert!(Rect::from_corners(bottom_right, top_left) == expected);
+    assert!(Rect::from_corners(top_right, bottom_left) == expected);
+    assert!(Rect::from_corners(bottom_left, top_right) == expected);
+
+    // corners that line up give a Rect one pixel across
+    assert!(Rect::from_corners(Point::new(5, 5), Point::new(5, -5)) == Rect::new(5, -5, 1, 10));
+    let far = Rect::from_corners(Point::new(i32::MIN, 0), Point::new(i32::MAX, 0));
+    // SDL clamps the size, as for any Rect, rather than overflowing
+    assert!(far.x() == i32::MIN / 2);
+}
+
+#[test]
+fn test_rect_layout() {
+    let rect = Rect::new(10, 20, 100, 50);
+    assert!(rect.inset(5) == Rect::new(15, 25, 90, 40));
+    assert!(rect.inset(-1) == Rect::new(9, 19, 102, 52));
+
+    assert!(rect.split_h(30) == (Rect::new(10, 20, 30, 50), Rect::new(40, 20, 70, 50)));
+    assert!(rect.split_v(0) == (Rect::new(10, 20, 100, 1), Rect::new(10, 21, 100, 49)));
+    assert!(rect.split_h(500).1 == Rect::new(109, 20, 1, 50));
+
+    let parent = Rect::new(0, 0, 100, 60);
+    assert!(Rect::anchored(&parent, Anchor::TopLeft, (10, 10)) == Rect::new(0, 0, 10, 10));
+    assert!(Rect::anchored(&parent, Anchor::Center, (11, 10)) == Rect::new(44, 25, 11, 10));
+    assert!(Rect::anchored(&parent, Anchor::BottomRight, (10, 10)) == Rect::new(90, 50, 10, 10));
+    assert!(Rect::anchored(&parent, Anchor::Bottom, (120, 10)) == Rect::new(-10, 50, 120, 10));
+}
+
+#[test]
+fn test_weighted_splits_tile_exactly() {
+    let mut rng = crate::rng::Rng::from_seed(9);
+    for _ in 0..500 {
+        let rect = Rect::new(
+            rng.range_i32(-50, 50),
+            rng.range_i32(-50, 50),
+            rng.range_i32(1, 300) as u32,
+            rng.range_i32(1, 300) as u32,
+        );
+        let weights: Vec<f32> = (0..rng.range_i32(1, 8))
+            .map(|_| rng.range_i32(-1, 10) as f32 / 3.0)
+            .collect();
+
+        let columns = rect.split_weighted_h(&weights);
+        assert!(columns.len() == weights.len());
+        if rect.width() as usize >= weights.len() {
+            let mut x = rect.x();
+            for column in &columns {
+                assert!(column.x() == x && column.y() == rect.y());
+                assert!(column.height() == rect.height());
+                x += column.width() as i32;
+            }
+            assert!(x == rect.right());
+        }
+
+        let rows = rect.split_weighted_v(&weights);
+        if rect.height() as usize >= weights.len() {
+            let mut y = rect.y();
+            for row in &rows {
+                assert!(row.y() == y && row.width() == rect.width());
+                y += row.height() as i32;
+            }
+            assert!(y == rect.bottom());
+        }
+    }
+
+    // the remainder goes to the parts that lost the most to rounding, leftmost first
+    let rect = Rect::new(0, 0, 10, 1);
+    let widths = |weights: &[f32]| -> Vec<u32> {
+        let columns = rect.split_weighted_h(weights);
+        columns.iter().map(|r| r.width()).collect()
+    };
+    assert!(widths(&[1.0, 1.0, 1.0]) == [4, 3, 3]);
+    assert!(widths(&[1.0, 2.0]) == [3, 7]);
+    assert!(widths(&[0.0, 0.0]) == [5, 5]);
+    assert!(widths(&[100.0, 0.0]) == [9, 1]);
+    assert!(widths(&[f32::NAN, -1.0, 1.0]) == [1, 1, 8]);
+}
+
+#[test]
+fn test_simplify_polyline_stays_within_tolerance() {
+    let mut rng = crate::rng::Rng::from_seed(3);
+    for &tolerance in &[0.5, 2.0, 10.0] {
+        // a noisy random walk, as a mouse stroke would be
+        let mut stroke = vec![Point::new(0, 0)];
+        for _ in 0..500 {
+            let last = stroke[stroke.len() - 1];
+            stroke.push(last + Point::new(rng.range_i32(-1, 3), rng.range_i32(-2, 2)));
+        }
+        let simple = simplify_polyline(&stroke, tolerance);
+        assert!(simple.len() < stroke.len());
+        assert!(simple[0] == stroke[0] && simple[simple.len() - 1] == stroke[stroke.len() - 1]);
+
+        let deviation = |p: Point| {
+            simple
+                .windows(2)
+                .map(|s| distance_to_segment(p, s[0], s[1]))
+                .fold(f32::MAX, f32::min)
+        };
+        assert!(stroke.iter().all(|&p| deviation(p) <= tolerance));
+    }
+
+    let square = [(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)].map(Point::from);
+    assert!(simplify_polyline(&square, 1.0) == square);
+    let line = [(0, 0), (5, 5)].map(Point::from);
+    assert!(simplify_polyline(&line, 1.0) == line);
+    assert!(simplify_polyline(&[], 1.0).is_empty());
+}
+
+#[test]
+fn test_smooth_polyline() {
+    let corner = [(0, 0), (40, 0), (40, 40)].map(Point::from);
+    let smooth = smooth_polyline(&corner, 1);
+    assert!(smooth == [(0, 0), (10, 0), (30, 0), (40, 10), (40, 30), (40, 40)].map(Point::from));
+
+    let square = [(0, 0), (100, 0), (100, 100), (0, 100), (0, 0)].map(Point::from);
+    let smooth = smooth_polyline(&square, 4);
+    assert!(smooth.len() > square.len());
+    assert!(smooth[0] == square[0] && smooth[smooth.len() - 1] == square[0]);
+
+    let point = [Point::new(3, 4)];
+    assert!(smooth_polyline(&point, 3) == point);
+    assert!(smooth_polyline(&corner, 0) == corner);
+}
@@ -0,0 +1,183 @@
+/*!
+ * Sprites: an Image together with where and how it is drawn.
+ *
+ * Game code tends to keep every image next to its position, rotation, scale and flips. A Sprite
+ * keeps them together, and draws itself with the same transforms as `Frame::image`.
+ */
+
+use crate::error::SimpleError;
+use crate::frame::ImageDraw;
+use crate::shape;
+use crate::window::{Image, Window};
+
+/**
+ * Sprite owns an Image and the transform it is drawn with.
+ *
+ * ```no_run
+ * # use simple::sprite::Sprite;
+ * # let mut window = simple::Window::new("sprite", 64, 64);
+ * let image = window.load_image_from_file("ship.png")?;
+ * let mut ship = Sprite::new(image).at(10, 10).scale(2.0).flipped(true, false);
+ * ship.set_angle(90.0);
+ * ship.draw(&mut window)?;
+ * # Ok::<(), simple::SimpleError>(())
+ * ```
+ */
+pub struct Sprite {
+    image: Image,
+    x: i32,
+    y: i32,
+    angle: f64,
+    scale: f32,
+    flip_h: bool,
+    flip_v: bool,
+}
+
+impl Sprite {
+    /// Create a Sprite that draws `image` at (0, 0), unrotated, unflipped and at its own size.
+    pub fn new(image: Image) -> Self {
+        Sprite {
+            image,
+            x: 0,
+            y: 0,
+            angle: 0.0,
+            scale: 1.0,
+            flip_h: false,
+            flip_v: false,
+        }
+    }
+
+    /// Put the top-left corner of the scaled image, before it is rotated, at (x, y).
+    pub fn at(mut self, x: i32, y: i32) -> Self {
+        self.set_position(x, y);
+        self
+    }
+
+    /// Rotate the image clockwise by `degrees` around its center.
+    pub fn rotation(mut self, degrees: f64) -> Self {
+        self.set_angle(degrees);
+        self
+    }
+
+    /// Draw the image `scale` times its size.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.set_scale(scale);
+        self
+    }
+
+    /// Mirror the image left to right if `horizontal`, and top to bottom if `vertical`.
+    pub fn flipped(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.set_flip(horizontal, vertical);
+        self
+    }
+
+    /// Return where the top-left corner of the scaled image goes, before it is rotated.
+    pub fn position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// Move the top-left corner of the scaled image, before it is rotated, to (x, y).
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Return how far the image is rotated clockwise around its center, in degrees.
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// Rotate the image clockwise by `degrees` around its center, replacing the previous angle.
+    pub fn set_angle(&mut self, degrees: f64) {
+        self.angle = degrees;
+    }
+
+    /// Return how many times its size the image is drawn.
+    pub fn scale_factor(&self) -> f32 {
+        self.scale
+    }
+
+    /// Draw the image `scale` times its size from now on.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    /// Return whether the image is mirrored (horizontally, vertically).
+    pub fn flip(&self) -> (bool, bool) {
+        (self.flip_h, self.flip_v)
+    }
+
+    /// Mirror the image left to right if `horizontal`, and top to bottom if `vertical`.
+    pub fn set_flip(&mut self, horizontal: bool, vertical: bool) {
+        self.flip_h = horizontal;
+        self.flip_v = vertical;
+    }
+
+    /// Return the Image the sprite draws.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Swap in another image, for instance the next frame of an animation, keeping the transform.
+    pub fn set_image(&mut self, image: Image) {
+        self.image = image;
+    }
+
+    /// Give up the sprite and return its Image.
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Return the rectangle the scaled image covers before it is rotated, rounded the same way
+    /// as when it is drawn.
+    pub fn rect(&self) -> shape::Rect {
+        let scaled = |length: u32| (length as f32 * self.scale).round() as u32;
+        shape::Rect::new(
+            self.x,
+            self.y,
+            scaled(self.image.get_width()),
+            scaled(self.image.get_height()),
+        )
+    }
+
+    /// Draw the sprite, tinted with the Window's color like `Window::draw_image`. Return an
+    /// error, and draw nothing, if the scale isn't a positive number or the angle isn't finite.
+    pub fn draw(&self, window: &mut Window) -> Result<(), SimpleError> {
+        ImageDraw::new(window, &self.image)
+            .at(self.x, self.y)
+            .rotation(self.angle)
+            .scale(self.scale)
+            .flip(self.flip_h, self.flip_v)
+            .draw()
+    }
+}
+
+#[test]
+fn test_sprite_draws_its_transform() {
+    let mut window = Window::new("Test", 16, 16);
+    let image = window.load_image(crate::font::DEFAULT_FONT_BYTES).unwrap();
+    let (width, height) = (image.get_width(), image.get_height());
+    let mut sprite = Sprite::new(image).at(3, 4).scale(0.5).flipped(true, false);
+    assert!(sprite.rect() == shape::Rect::new(3, 4, width.div_ceil(2), height.div_ceil(2)));
+    assert!(sprite.flip() == (true, false) && sprite.position() == (3, 4));
+    assert!(sprite.scale_factor() == 0.5);
+
+    sprite.draw(&mut window).unwrap();
+    sprite.set_angle(f64::NAN);
+    assert!(sprite.draw(&mut window).is_err());
+    sprite.set_angle(30.0);
+    sprite.set_scale(0.0);
+    assert!(sprite.draw(&mut window).is_err());
+    assert!(window.current_draw_stats().texture_copies == 1);
+
+    // a red pixel next to a blue one swap places when the sprite is flipped
+    let pixels = [255, 0, 0, 255, 0, 0, 255, 255];
+    let surf = crate::util::rgba_surface_from_bytes(&pixels, 2, 1).unwrap();
+    let sprite = Sprite::new(window.image_from_surface(surf).unwrap()).flipped(true, false);
+    window.clear();
+    sprite.draw(&mut window).unwrap();
+    let (drawn, _, _) = window
+        .read_pixels(Some(shape::Rect::new(0, 0, 2, 1)))
+        .unwrap();
+    assert!(drawn == [0, 0, 255, 255, 255, 0, 0, 255]);
+}
@@ -0,0 +1,257 @@
+/*!
+ * A single-line text field that an input method can type into.
+ *
+ * Input methods for languages such as Japanese and Chinese build each word up in a composition
+ * before committing it. While that happens the Window sends `Event::TextEditing`; the field shows
+ * the composition underlined at the caret, and replaces it with the committed text when the
+ * `Event::TextInput` that ends it arrives.
+ */
+
+#[cfg(test)]
+use crate::event::key_press;
+use crate::event::{Event, Key};
+use crate::shape::{Rect, RectExt};
+use crate::window::Window;
+
+/// How far, in pixels, the text is drawn from the left edge of the field.
+const PADDING: i32 = 2;
+
+/// Text that an input method is composing, as sent by `Event::TextEditing`.
+#[derive(Clone, PartialEq, Debug)]
+struct Composition {
+    text: String,
+    cursor: usize,
+    selection_len: usize,
+}
+
+/**
+ * TextField holds the text of an editable field and where its caret is, and draws them.
+ *
+ * Pass it the Window's events with `handle_event` and draw it with `draw` every frame. To make the
+ * input method's candidate list appear next to the field, call `Window::set_text_input_rect` with
+ * the field's `rect` when it gains focus.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextField {
+    rect: Rect,
+    text: String,
+    // the caret, as a character index into text
+    caret: usize,
+    composition: Option<Composition>,
+}
+
+impl TextField {
+    /// Create an empty field that covers `rect`.
+    pub fn new(rect: Rect) -> Self {
+        TextField {
+            rect,
+            text: String::new(),
+            caret: 0,
+            composition: None,
+        }
+    }
+
+    /// Return the area the field covers.
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Move or resize the field to cover `rect`.
+    pub fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    /// Return the text typed so far, without the composition.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, put the caret at its end and drop any composition.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.caret = text.chars().count();
+        self.composition = None;
+    }
+
+    /// Return the caret position, as a character index into `text`.
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Return what the input method is composing, if it is composing anything.
+    pub fn composition(&self) -> Option<&str> {
+        self.composition.as_ref().map(|c| c.text.as_str())
+    }
+
+    /// Update the field for `e`. Return true if the event was meant for the field: text, a
+    /// composition, or a key that edits text or moves the caret.
+    ///
+    /// While a composition is showing, the keys belong to the input method, so they are taken
+    /// without changing the text.
+    pub fn handle_event(&mut self, e: &Event) -> bool {
+        match *e {
+            Event::TextInput { ref text } => {
+                self.composition = None;
+                let at = self.byte_index(self.caret);
+                self.text.insert_str(at, text);
+                self.caret += text.chars().count();
+                true
+            }
+            Event::TextEditing {
+                ref text,
+                cursor,
+                selection_len,
+            } => {
+                // an empty composition means the input method cancelled it
+                self.composition = if text.is_empty() {
+                    None
+                } else {
+                    Some(Composition {
+                        text: text.clone(),
+                        cursor,
+                        selection_len,
+                    })
+                };
+                true
+            }
+            Event::Keyboard {
+                is_down: true, key, ..
+            } => {
+                let edits = matches!(
+                    key,
+                    Key::Backspace | Key::Delete | Key::Left | Key::Right | Key::Home | Key::End
+                );
+                if edits && self.composition.is_none() {
+                    self.press(key);
+                }
+                edits
+            }
+            _ => false,
+        }
+    }
+
+    fn press(&mut self, key: Key) {
+        let len = self.text.chars().count();
+        match key {
+            Key::Backspace if self.caret > 0 => {
+                self.caret -= 1;
+                let at = self.byte_index(self.caret);
+                self.text.remove(at);
+            }
+            Key::Delete if self.caret < len => {
+                let at = self.byte_index(self.caret);
+                self.text.remove(at);
+            }
+            Key::Left => self.caret = self.caret.saturating_sub(1),
+            Key::Right => self.caret = (self.caret + 1).min(len),
+            Key::Home => self.caret = 0,
+            Key::End => self.caret = len,
+            _ => (),
+        }
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    /// Draw the field's outline, its text and its caret in the current color and font. Text that
+    /// doesn't fit is cut off at the edge of the field.
+    pub fn draw(&self, window: &mut Window) {
+        let at = self.byte_index(self.caret);
+        let (composing, cursor, selection_len) = match self.composition {
+            Some(ref c) => (c.text.as_str(), c.cursor, c.selection_len),
+            None => ("", 0, 0),
+        };
+        let shown = format!("{}{}{}", &self.text[..at], composing, &self.text[at..]);
+        let composing_len = composing.chars().count();
+
+        let height = window.measure_text(&shown).1;
+        let x = self.rect.x() + PADDING;
+        let y = self.rect.y() + (self.rect.height() as i32 - height as i32) / 2;
+
+        window.save_state();
+        window.draw_rect(self.rect);
+        window.set_clip_rect(Some(self.rect.inset(1)));
+        window.print(&shown, x, y);
+
+        if composing_len > 0 {
+            let start = window.caret_x(&shown, self.caret, x);
+            let end = window.caret_x(&shown, self.caret + composing_len, x);
+            let underline_y = y + height as i32 - 1;
+            window.fill_rect(Rect::new(start, underline_y, (end - start) as u32, 1));
+            if selection_len > 0 {
+                let from = window.caret_x(&shown, self.caret + cursor, x);
+                let to = window.caret_x(&shown, self.caret + cursor + selection_len, x);
+                window.fill_rect(Rect::new(from, underline_y - 1, (to - from) as u32, 1));
+            }
+        }
+        let caret = window.caret_x(&shown, self.caret + cursor, x);
+        window.fill_rect(Rect::new(caret, y, 1, height));
+        window.restore_state();
+    }
+}
+
+#[cfg(test)]
+fn editing(text: &str, cursor: usize) -> Event {
+    Event::TextEditing {
+        text: text.to_string(),
+        cursor,
+        selection_len: 0,
+    }
+}
+
+#[test]
+fn test_editing_then_commit() {
+    let mut field = TextField::new(Rect::new(0, 0, 100, 20));
+    field.set_text("ab");
+    field.handle_event(&key_press(Key::Left));
+    assert!(field.caret() == 1);
+
+    assert!(field.handle_event(&editing("に", 1)));
+    assert!(field.handle_event(&editing("にほ", 2)));
+    assert!(field.composition() == Some("にほ"));
+    assert!(field.text() == "ab");
+
+    // keys belong to the input method while it composes
+    assert!(field.handle_event(&key_press(Key::Backspace)));
+    assert!(field.text() == "ab" && field.caret() == 1);
+
+    let commit = Event::TextInput {
+        text: "日本".to_string(),
+    };
+    assert!(field.handle_event(&commit));
+    assert!(field.composition().is_none());
+    assert!(field.text() == "a日本b");
+    assert!(field.caret() == 3);
+
+    field.handle_event(&key_press(Key::Backspace));
+    assert!(field.text() == "a日b" && field.caret() == 2);
+    field.handle_event(&key_press(Key::Delete));
+    assert!(field.text() == "a日");
+    field.handle_event(&key_press(Key::Delete));
+    assert!(field.text() == "a日");
+}
+
+#[test]
+fn test_editing_then_cancel() {
+    let mut field = TextField::new(Rect::new(0, 0, 100, 20));
+    field.set_text("x");
+    field.handle_event(&editing("か", 1));
+    assert!(field.composition() == Some("か"));
+
+    assert!(field.handle_event(&editing("", 0)));
+    assert!(field.composition().is_none());
+    assert!(field.text() == "x" && field.caret() == 1);
+
+    // with the composition gone, keys edit the text again
+    field.handle_event(&key_press(Key::Home));
+    assert!(field.caret() == 0);
+    field.handle_event(&key_press(Key::End));
+    field.handle_event(&key_press(Key::Backspace));
+    assert!(field.text().is_empty());
+    assert!(!field.handle_event(&key_press(Key::A)));
+    assert!(!field.handle_event(&Event::Quit));
+}
@@ -0,0 +1,272 @@
+/*!
+ * Ownership of the textures a Window makes, so that Images, Fonts and Atlases can be kept in any
+ * struct and dropped at any time, before or after the Window.
+ *
+ * SDL2's `unsafe_textures` feature, which the crate is built with, gives a texture no lifetime:
+ * it is only valid while the renderer that made it is. Each Window therefore keeps a Pool, and
+ * every texture it makes holds on to that Pool. Dropping a texture only queues it, because a
+ * layer may still refer to it; the Window destroys the queued textures once the frame has been
+ * shown. Once the Window is gone its renderer has destroyed every texture along with itself, and
+ * dropping one does nothing at all.
+ *
+ * The Pool also counts how often the renderer has lost what was drawn on its render targets, so
+ * that an Image drawn to with `Window::draw_to_image` can tell whether its texture still holds
+ * what was drawn.
+ *
+ * A texture can have its pixels read back into main memory just before the renderer goes, so that
+ * a Font can be given to another Window without keeping a copy of its pixels all along.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, DerefMut};
+use std::rc::{Rc, Weak};
+
+use sdl2::render;
+use sdl2::sys;
+
+/// The RGBA pixels of a texture, with their width and height, once they have been read back.
+pub(crate) type Pixels = RefCell<Option<(Vec<u8>, u32, u32)>>;
+
+/// Pool stands for the renderer of one Window, for the textures made with it.
+pub(crate) struct Pool {
+    renderer: *mut sys::SDL_Renderer,
+    // false once the renderer, and every texture it made, has been destroyed
+    open: Cell<bool>,
+    dropped: RefCell<Vec<*mut sys::SDL_Texture>>,
+    // how many times the render targets have been lost
+    target_resets: Cell<u64>,
+    // textures whose pixels are read back when the renderer is about to go, if nobody has by then
+    read_on_close: RefCell<Vec<(*mut sys::SDL_Texture, Weak<Pixels>)>>,
+}
+
+/// Contents records whether what was drawn on a render target is still there.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Contents {
+    /// Drawn after the Pool's render targets had been lost this many times.
+    Drawn(u64),
+    Lost,
+}
+
+impl Pool {
+    /// Make the Pool for the textures of `renderer`.
+    pub(crate) fn new(renderer: *mut sys::SDL_Renderer) -> Rc<Pool> {
+        Rc::new(Pool {
+            renderer,
+            open: Cell::new(true),
+            dropped: RefCell::new(vec![]),
+            target_resets: Cell::new(0),
+            read_on_close: RefCell::new(vec![]),
+        })
+    }
+
+    /// Return the Contents of a render target that has just been drawn on.
+    pub(crate) fn drawn_now(&self) -> Contents {
+        Contents::Drawn(self.target_resets.get())
+    }
+
+    /// Return true if a render target whose Contents were `contents` still holds them.
+    pub(crate) fn still_holds(&self, contents: Contents) -> bool {
+        contents == self.drawn_now()
+    }
+
+    /// Record that the renderer has lost what was drawn on every one of its render targets.
+    pub(crate) fn lose_targets(&self) {
+        self.target_resets.set(self.target_resets.get() + 1);
+    }
+
+    /// Return how many dropped textures are waiting to be destroyed.
+    #[cfg(test)]
+    pub(crate) fn dropped_count(&self) -> usize {
+        self.dropped.borrow().len()
+    }
+
+    /// Destroy the textures that have been dropped.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may still be waiting to draw them: the layers and the batch must have been drawn.
+    pub(crate) unsafe fn destroy_dropped(&self) {
+        for raw in self.dropped.borrow_mut().drain(..) {
+            // SAFETY: every queued texture was made by this pool's renderer, which is still open,
+            // and was queued once, when its only owner was dropped. The caller promises that no
+            // draw refers to it.
+            unsafe { sys::SDL_DestroyTexture(raw) };
+        }
+    }
+
+    /// Record that the renderer is being destroyed. Its textures go with it, so those that are
+    /// queued are forgotten, and those that are still alive won't be queued when they are dropped.
+    /// The pixels of the textures given to `read_on_close` are read back first.
+    pub(crate) fn close(&self) {
+        for (raw, pixels) in self.read_on_close.take() {
+            if let Some(pixels) = pixels.upgrade()
+                && pixels.borrow().is_none()
+            {
+                // SAFETY: the texture is still alive, since a texture leaves `read_on_close` when
+                // it is dropped, and so is the renderer that made it
+                *pixels.borrow_mut() = unsafe { read_back(self.renderer, raw) }.ok();
+            }
+        }
+        self.open.set(false);
+        self.dropped.borrow_mut().clear();
+    }
+}
+
+/// Read the RGBA pixels of `texture` back by copying it onto a render target of its size, and
+/// return them with their width and height. The renderer's target, viewport, clip rect and scale,
+/// and the texture's blend mode and color and alpha mods, are put back afterwards.
+///
+/// # Safety
+///
+/// `texture` must have been made by `renderer`, and both must still exist.
+unsafe fn read_back(
+    renderer: *mut sys::SDL_Renderer,
+    texture: *mut sys::SDL_Texture,
+) -> Result<(Vec<u8>, u32, u32), String> {
+    // SAFETY: the caller promises that both exist, and every pointer passed to SDL points to a
+    // local that outlives the call, or to a buffer of the size SDL is told
+    unsafe {
+        let (mut width, mut height) = (0, 0);
+        let (format, access) = (std::ptr::null_mut(), std::ptr::null_mut());
+        sys::SDL_QueryTexture(texture, format, access, &mut width, &mut height);
+        let format = sdl2::pixels::PixelFormatEnum::RGBA32 as u32;
+        let access = sys::SDL_TextureAccess::SDL_TEXTUREACCESS_TARGET as i32;
+        let target = sys::SDL_CreateTexture(renderer, format, access, width, height);
+        if target.is_null() {
+            return Err(sdl2::get_error());
+        }
+
+        // what has to be put back
+        let previous = sys::SDL_GetRenderTarget(renderer);
+        let (mut viewport, mut clip) = (std::mem::zeroed(), std::mem::zeroed());
+        sys::SDL_RenderGetViewport(renderer, &mut viewport);
+        let clipped = sys::SDL_RenderIsClipEnabled(renderer) == sys::SDL_bool::SDL_TRUE;
+        sys::SDL_RenderGetClipRect(renderer, &mut clip);
+        let (mut scale_x, mut scale_y) = (1.0, 1.0);
+        sys::SDL_RenderGetScale(renderer, &mut scale_x, &mut scale_y);
+        let mut blend = sys::SDL_BlendMode::SDL_BLENDMODE_BLEND;
+        sys::SDL_GetTextureBlendMode(texture, &mut blend);
+        let (mut r, mut g, mut b, mut a) = (255, 255, 255, 255);
+        sys::SDL_GetTextureColorMod(texture, &mut r, &mut g, &mut b);
+        sys::SDL_GetTextureAlphaMod(texture, &mut a);
+
+        sys::SDL_SetRenderTarget(renderer, target);
+        sys::SDL_RenderSetViewport(renderer, std::ptr::null());
+        sys::SDL_RenderSetClipRect(renderer, std::ptr::null());
+        sys::SDL_RenderSetScale(renderer, 1.0, 1.0);
+        sys::SDL_SetTextureBlendMode(texture, sys::SDL_BlendMode::SDL_BLENDMODE_NONE);
+        sys::SDL_SetTextureColorMod(texture, 255, 255, 255);
+        sys::SDL_SetTextureAlphaMod(texture, 255);
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        let read = match sys::SDL_RenderCopy(renderer, texture, std::ptr::null(), std::ptr::null())
+        {
+            0 => sys::SDL_RenderReadPixels(
+                renderer,
+                std::ptr::null(),
+                format,
+                pixels.as_mut_ptr() as *mut _,
+                width * 4,
+            ),
+            failed => failed,
+        };
+        let error = sdl2::get_error();
+
+        sys::SDL_SetRenderTarget(renderer, previous);
+        sys::SDL_RenderSetViewport(renderer, &viewport);
+        sys::SDL_RenderSetClipRect(renderer, if clipped { &clip } else { std::ptr::null() });
+        sys::SDL_RenderSetScale(renderer, scale_x, scale_y);
+        sys::SDL_SetTextureBlendMode(texture, blend);
+        sys::SDL_SetTextureColorMod(texture, r, g, b);
+        sys::SDL_SetTextureAlphaMod(texture, a);
+        sys::SDL_DestroyTexture(target);
+        match read {
+            0 => Ok((pixels, width as u32, height as u32)),
+            _ => Err(error),
+        }
+    }
+}
+
+/**
+ * Texture is an SDL texture tied to the Pool of the Window that made it. It derefs to the SDL
+ * texture, which is only valid while `belongs_to` the Window's Pool.
+ */
+pub(crate) struct Texture {
+    texture: render::Texture,
+    pool: Rc<Pool>,
+}
+
+impl Texture {
+    /// Tie `texture`, which must have been made by the renderer `pool` stands for, to the pool.
+    pub(crate) fn new(texture: render::Texture, pool: &Rc<Pool>) -> Self {
+        Texture {
+            texture,
+            pool: pool.clone(),
+        }
+    }
+
+    /// Return true if the texture was made by the renderer `pool` stands for, and so can be drawn
+    /// by it.
+    pub(crate) fn belongs_to(&self, pool: &Rc<Pool>) -> bool {
+        Rc::ptr_eq(&self.pool, pool)
+    }
+
+    /// Read the texture's RGBA pixels back from the renderer, with their width and height. This
+    /// fails once the Window that made it is gone, or if its renderer can't draw to textures.
+    pub(crate) fn read_back(&self) -> Result<(Vec<u8>, u32, u32), String> {
+        if !self.pool.open.get() {
+            return Err("the Window that made the texture is gone".to_string());
+        }
+        // SAFETY: the renderer is open, and made this texture, which is alive as long as `self`
+        unsafe { read_back(self.pool.renderer, self.texture.raw()) }
+    }
+
+    /// Read the texture's pixels back into `pixels` just before the renderer goes, unless they
+    /// have been read by then, so that they outlive it.
+    pub(crate) fn read_on_close(&self, pixels: &Rc<Pixels>) {
+        let entry = (self.texture.raw(), Rc::downgrade(pixels));
+        self.pool.read_on_close.borrow_mut().push(entry);
+    }
+}
+
+impl Deref for Texture {
+    type Target = render::Texture;
+
+    fn deref(&self) -> &render::Texture {
+        &self.texture
+    }
+}
+
+impl DerefMut for Texture {
+    fn deref_mut(&mut self) -> &mut render::Texture {
+        &mut self.texture
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        if self.pool.open.get() {
+            let raw = self.texture.raw();
+            self.pool
+                .read_on_close
+                .borrow_mut()
+                .retain(|&(r, _)| r != raw);
+            self.pool.dropped.borrow_mut().push(raw);
+        }
+    }
+}
+
+#[test]
+fn test_lost_targets() {
+    let pool = Pool::new(std::ptr::null_mut());
+    let drawn = pool.drawn_now();
+    assert!(pool.still_holds(drawn));
+    assert!(!pool.still_holds(Contents::Lost));
+
+    pool.lose_targets();
+    assert!(!pool.still_holds(drawn));
+    // drawing again after the loss is kept, until the next one
+    let redrawn = pool.drawn_now();
+    assert!(redrawn != drawn && pool.still_holds(redrawn));
+    pool.lose_targets();
+    assert!(!pool.still_holds(redrawn));
+}
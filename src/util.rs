@@ -5,8 +5,10 @@
 use std::collections::HashMap;
 
 extern crate sdl2;
+use crate::shape;
 use sdl2::pixels;
 use sdl2::render;
+use sdl2::surface;
 
 /// Return true if any of the characters in `s` appear anywhere else in `s`.
 pub fn string_has_duplicate_chars(s: String) -> bool {
@@ -37,9 +39,426 @@ fn dup_chars() {
     ));
 }
 
-/// Utility method to set the texture's color and alpha mods to the Color.
+/// Utility method to set the texture's color and alpha mods to the Color. Every texture copy calls
+/// this first, so the mods never leak from one draw call into the next.
 pub fn set_texture_color(color: &pixels::Color, texture: &mut render::Texture) {
     // configure the texture for drawing according to the current foreground_color
     texture.set_color_mod(color.r, color.g, color.b);
     texture.set_alpha_mod(color.a);
 }
+
+/// Create a transparent RGBA Surface. Blits from it copy pixels exactly instead of blending them.
+pub fn new_rgba_surface(width: u32, height: u32) -> Result<surface::Surface<'static>, String> {
+    let mut surf = surface::Surface::new(width, height, pixels::PixelFormatEnum::RGBA32)?;
+    surf.set_blend_mode(render::BlendMode::None)?;
+    Ok(surf)
+}
+
+/// Copy the Surface into a new RGBA Surface that owns its pixels, preserving alpha exactly. This
+/// normalizes whatever format an image was decoded in, and frees the copy from the lifetime of the
+/// RWops it was loaded from.
+pub fn to_rgba_surface(
+    surf: &mut surface::SurfaceRef,
+) -> Result<surface::Surface<'static>, String> {
+    let mut copy = new_rgba_surface(surf.width(), surf.height())?;
+    surf.set_blend_mode(render::BlendMode::None)?;
+    surf.blit(None, &mut copy, None)?;
+    Ok(copy)
+}
+
+/// Copy the pixels of an RGBA Surface into a tightly packed Vec, four bytes per pixel.
+pub fn rgba_surface_bytes(surf: &surface::SurfaceRef) -> Vec<u8> {
+    let (width, height) = (surf.width() as usize, surf.height() as usize);
+    let pitch = surf.pitch() as usize;
+    surf.with_lock(|pixels| {
+        let mut bytes = Vec::with_capacity(width * height * 4);
+        for row in 0..height {
+            bytes.extend_from_slice(&pixels[row * pitch..row * pitch + width * 4]);
+        }
+        bytes
+    })
+}
+
+/// Build an RGBA Surface from tightly packed RGBA bytes, the inverse of `rgba_surface_bytes`.
+pub fn rgba_surface_from_bytes(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<surface::Surface<'static>, String> {
+    let row_len = width as usize * 4;
+    if bytes.len() != row_len * height as usize {
+        return Err(format!(
+            "expected {} bytes for a {}x{} RGBA image, got {}",
+            row_len * height as usize,
+            width,
+            height,
+            bytes.len()
+        ));
+    }
+
+    let mut surf = new_rgba_surface(width, height)?;
+    let pitch = surf.pitch() as usize;
+    surf.with_lock_mut(|pixels| {
+        for (row, src) in bytes.chunks(row_len).enumerate() {
+            pixels[row * pitch..row * pitch + row_len].copy_from_slice(src);
+        }
+    });
+    Ok(surf)
+}
+
+/// Return the axis-aligned bounding box of `rect` after rotating it clockwise by `angle_degrees`
+/// around `pivot`, the way SDL's `copy_ex` rotates.
+pub fn rotated_bounds(rect: shape::Rect, pivot: shape::Point, angle_degrees: f64) -> shape::Rect {
+    let (sin, cos) = angle_degrees.to_radians().sin_cos();
+    let corners = [
+        (rect.left(), rect.top()),
+        (rect.right(), rect.top()),
+        (rect.left(), rect.bottom()),
+        (rect.right(), rect.bottom()),
+    ];
+
+    // snap values that are integers up to floating point noise, so right angles come out exact
+    let snap = |v: f64| {
+        if (v - v.round()).abs() < 1e-6 {
+            v.round()
+        } else {
+            v
+        }
+    };
+    let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+    let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+    for &(cx, cy) in corners.iter() {
+        // in f64, since a corner and the pivot can be further apart than an i32 reaches
+        let dx = cx as f64 - pivot.x() as f64;
+        let dy = cy as f64 - pivot.y() as f64;
+        let x = snap(pivot.x() as f64 + dx * cos - dy * sin);
+        let y = snap(pivot.y() as f64 + dx * sin + dy * cos);
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+
+    // the float to int casts saturate, so bounds beyond an i32 are clamped rather than wrapped
+    let (left, top) = (min_x.floor() as i32, min_y.floor() as i32);
+    shape::Rect::new(
+        left,
+        top,
+        (max_x.ceil() - left as f64) as u32,
+        (max_y.ceil() - top as f64) as u32,
+    )
+}
+
+#[test]
+fn test_rotated_bounds() {
+    let rect = shape::Rect::new(10, 20, 100, 16);
+    let pivot = shape::Point::new(10, 20);
+
+    assert!(rotated_bounds(rect, pivot, 0.0) == rect);
+    assert!(rotated_bounds(rect, pivot, 90.0) == shape::Rect::new(-6, 20, 16, 100));
+    assert!(rotated_bounds(rect, pivot, 180.0) == shape::Rect::new(-90, 4, 100, 16));
+    assert!(rotated_bounds(rect, pivot, 270.0) == shape::Rect::new(10, -80, 16, 100));
+    assert!(rotated_bounds(rect, pivot, -90.0) == rotated_bounds(rect, pivot, 270.0));
+
+    // 45 degrees: the box is as wide as the diagonal projections
+    let square = shape::Rect::new(0, 0, 10, 10);
+    let bounds = rotated_bounds(square, shape::Point::new(0, 0), 45.0);
+    assert!(bounds == shape::Rect::new(-8, 0, 16, 15));
+
+    // corners further from the pivot than an i32 reaches clamp instead of overflowing
+    let far = shape::Rect::new(i32::MAX, i32::MAX, u32::MAX, u32::MAX);
+    rotated_bounds(far, shape::Point::new(i32::MIN, i32::MIN), 90.0);
+    rotated_bounds(far, shape::Point::new(i32::MIN, i32::MIN), 180.0);
+}
+
+/// Return the pixels of the outline that `draw_rect(rect)` draws, after rotating it clockwise by
+/// `angle_degrees` around `pivot` the way `copy_ex` rotates. Each corner pixel is moved by its
+/// center, and the corners are joined with straight lines.
+pub fn rotated_outline(
+    rect: shape::Rect,
+    pivot: shape::Point,
+    angle_degrees: f64,
+) -> Vec<shape::Point> {
+    let (sin, cos) = angle_degrees.to_radians().sin_cos();
+    let rotate = |x: i64, y: i64| {
+        let dx = x as f64 + 0.5 - pivot.x() as f64;
+        let dy = y as f64 + 0.5 - pivot.y() as f64;
+        (
+            (pivot.x() as f64 + dx * cos - dy * sin).floor() as i64,
+            (pivot.y() as f64 + dx * sin + dy * cos).floor() as i64,
+        )
+    };
+    let (left, top) = (rect.x() as i64, rect.y() as i64);
+    let (right, bottom) = (
+        left + rect.width() as i64 - 1,
+        top + rect.height() as i64 - 1,
+    );
+    let corners = [
+        rotate(left, top),
+        rotate(right, top),
+        rotate(right, bottom),
+        rotate(left, bottom),
+    ];
+    let mut points = vec![];
+    for (i, &from) in corners.iter().enumerate() {
+        line_points(from, corners[(i + 1) % corners.len()], &mut points);
+    }
+    points
+}
+
+/// Add the pixels of the line from `from` to `to`, both ends included, to `points`. Pixels beyond
+/// what an i32 reaches are left out.
+fn line_points(from: (i64, i64), to: (i64, i64), points: &mut Vec<shape::Point>) {
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let step = (
+        if from.0 < to.0 { 1 } else { -1 },
+        if from.1 < to.1 { 1 } else { -1 },
+    );
+    let (mut x, mut y, mut error) = (from.0, from.1, dx + dy);
+    loop {
+        if let (Ok(px), Ok(py)) = (i32::try_from(x), i32::try_from(y)) {
+            points.push(shape::Point::new(px, py));
+        }
+        if (x, y) == to {
+            return;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step.0;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step.1;
+        }
+    }
+}
+
+#[test]
+fn test_rotated_outline() {
+    let sorted = |mut points: Vec<shape::Point>| {
+        points.sort_by_key(|p| (p.x(), p.y()));
+        points.dedup();
+        points.iter().map(|p| (p.x(), p.y())).collect::<Vec<_>>()
+    };
+    let rect = shape::Rect::new(0, 0, 3, 2);
+    let origin = shape::Point::new(0, 0);
+    let upright = [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)];
+    assert!(sorted(rotated_outline(rect, origin, 0.0)) == upright);
+    // a quarter turn clockwise puts the box to the left of the pivot
+    let turned = [(-2, 0), (-2, 1), (-2, 2), (-1, 0), (-1, 1), (-1, 2)];
+    assert!(sorted(rotated_outline(rect, origin, 90.0)) == turned);
+
+    // a larger box only has its edges
+    let outline = sorted(rotated_outline(shape::Rect::new(5, 5, 4, 4), origin, 0.0));
+    assert!(outline.len() == 12 && !outline.contains(&(6, 6)));
+
+    // far from the pivot the pixels that don't fit in an i32 are left out
+    let far = shape::Point::new(i32::MIN, i32::MIN);
+    assert!(rotated_outline(shape::Rect::new(i32::MAX - 1, 0, 2, 2), far, 90.0).is_empty());
+}
+
+/// Divide `rect` into a checkerboard of `cell` by `cell` squares, starting from its top-left
+/// corner. Squares on the right and bottom edges are cut short to stay inside `rect`. Each square
+/// comes with a flag that alternates like the colors of a checkerboard, and is false for the
+/// top-left square.
+pub fn checker_cells(rect: shape::Rect, cell: u32) -> Vec<(shape::Rect, bool)> {
+    let cell = cell.max(1);
+    let mut cells = vec![];
+    for (row, y) in (0..rect.height()).step_by(cell as usize).enumerate() {
+        for (col, x) in (0..rect.width()).step_by(cell as usize).enumerate() {
+            let square = shape::Rect::new(
+                rect.x() + x as i32,
+                rect.y() + y as i32,
+                cell.min(rect.width() - x),
+                cell.min(rect.height() - y),
+            );
+            cells.push((square, (row + col) % 2 == 1));
+        }
+    }
+    cells
+}
+
+#[test]
+fn test_checker_cells() {
+    let cells = checker_cells(shape::Rect::new(10, 20, 5, 3), 2);
+    assert!(cells.len() == 6);
+    assert!(cells[0] == (shape::Rect::new(10, 20, 2, 2), false));
+    assert!(cells[1] == (shape::Rect::new(12, 20, 2, 2), true));
+    assert!(cells[2] == (shape::Rect::new(14, 20, 1, 2), false));
+    assert!(cells[3] == (shape::Rect::new(10, 22, 2, 1), true));
+    assert!(cells[5] == (shape::Rect::new(14, 22, 1, 1), true));
+
+    // a cell size of zero is treated as one
+    assert!(checker_cells(shape::Rect::new(0, 0, 2, 2), 0).len() == 4);
+}
+
+/// Replace the color of every RGBA pixel with the gray of its luminance (Rec. 709 weights),
+/// leaving alpha alone.
+pub fn grayscale_rgba(pixels: &mut [u8]) {
+    for p in pixels.chunks_mut(4) {
+        let luma = 0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32;
+        let gray = luma.round().min(255.0) as u8;
+        p[0] = gray;
+        p[1] = gray;
+        p[2] = gray;
+    }
+}
+
+#[test]
+fn test_grayscale_rgba() {
+    let mut pixels = [255, 0, 0, 10, 0, 255, 0, 20, 255, 255, 255, 30, 0, 0, 0, 40];
+    grayscale_rgba(&mut pixels);
+    assert!(
+        pixels
+            == [
+                54, 54, 54, 10, 182, 182, 182, 20, 255, 255, 255, 30, 0, 0, 0, 40
+            ]
+    );
+}
+
+/// Resize an image of RGBA `pixels`, `width` by `height`, to `new_width` by `new_height`. Without
+/// `smooth`, each new pixel is a copy of the old one under its center. With it, each new pixel is
+/// the average of the old ones it covers when shrinking, and is interpolated between its nearest
+/// old neighbours when growing. Colors are weighted by their alpha, so that the color of
+/// transparent pixels doesn't bleed into the edges of what is visible.
+pub fn resize_rgba(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    (new_width, new_height): (u32, u32),
+    smooth: bool,
+) -> Vec<u8> {
+    let premultiplied: Vec<[f32; 4]> = pixels
+        .chunks(4)
+        .map(|p| {
+            let a = p[3] as f32 / 255.0;
+            [
+                p[0] as f32 * a,
+                p[1] as f32 * a,
+                p[2] as f32 * a,
+                p[3] as f32,
+            ]
+        })
+        .collect();
+
+    // resize the rows, then the columns
+    let columns = resample_weights(width, new_width, smooth);
+    let rows = resample_weights(height, new_height, smooth);
+    let mix = |weights: &[(usize, f32)], sample: &dyn Fn(usize) -> [f32; 4]| {
+        let mut sum = [0.0; 4];
+        for &(i, weight) in weights {
+            for (s, v) in sum.iter_mut().zip(sample(i)) {
+                *s += v * weight;
+            }
+        }
+        sum
+    };
+    let wide: Vec<[f32; 4]> = premultiplied
+        .chunks(width as usize)
+        .flat_map(|row| columns.iter().map(|weights| mix(weights, &|x| row[x])))
+        .collect();
+    let mut resized = Vec::with_capacity(new_width as usize * new_height as usize * 4);
+    for weights in &rows {
+        for x in 0..new_width as usize {
+            let [r, g, b, a] = mix(weights, &|y| wide[y * new_width as usize + x]);
+            let unpremultiply = if a > 0.0 { 255.0 / a } else { 0.0 };
+            for v in [r * unpremultiply, g * unpremultiply, b * unpremultiply, a] {
+                resized.push(v.round().clamp(0.0, 255.0) as u8);
+            }
+        }
+    }
+    resized
+}
+
+/// Return, for each of the `new_len` pixels along one side of a resized image, which of the
+/// `len` old pixels make it up and with what weights. See `resize_rgba`.
+fn resample_weights(len: u32, new_len: u32, smooth: bool) -> Vec<Vec<(usize, f32)>> {
+    let scale = len as f64 / new_len as f64;
+    let last = len as usize - 1;
+    (0..new_len as usize)
+        .map(|i| {
+            let (start, end) = (i as f64 * scale, (i + 1) as f64 * scale);
+            if !smooth {
+                vec![((((start + end) / 2.0) as usize).min(last), 1.0)]
+            } else if scale > 1.0 {
+                (start as usize..(end.ceil() as usize).min(len as usize))
+                    .map(|j| {
+                        let covered = end.min(j as f64 + 1.0) - start.max(j as f64);
+                        (j, (covered / scale) as f32)
+                    })
+                    .collect()
+            } else {
+                let center = ((start + end) / 2.0 - 0.5).clamp(0.0, last as f64);
+                let (j, t) = (center as usize, center.fract() as f32);
+                vec![(j, 1.0 - t), ((j + 1).min(last), t)]
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_resize_rgba() {
+    let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+    let pixels = [red, blue].concat();
+    assert!(resize_rgba(&pixels, (2, 1), (4, 1), false) == [red, red, blue, blue].concat());
+    assert!(resize_rgba(&pixels, (2, 1), (2, 2), true) == [red, blue, red, blue].concat());
+    assert!(resize_rgba(&pixels, (2, 1), (1, 1), true) == [128, 0, 128, 255]);
+    assert!(resize_rgba(&pixels, (2, 1), (1, 1), false) == blue);
+    // growing interpolates between the two middle pixels
+    assert!(resize_rgba(&pixels, (2, 1), (4, 1), true)[4..8] == [191, 0, 64, 255]);
+
+    // a transparent pixel's color doesn't show
+    let pixels = [[255, 0, 0, 0], blue].concat();
+    assert!(resize_rgba(&pixels, (2, 1), (1, 1), true) == [0, 0, 255, 128]);
+
+    // shrinking by a fraction weights the pixels by how much of each is covered
+    let gray = |v| [v, v, v, 255];
+    let pixels = [gray(0), gray(90), gray(180)].concat();
+    assert!(resize_rgba(&pixels, (3, 1), (2, 1), true) == [gray(30), gray(150)].concat());
+}
+
+/// Enlarge an image of RGBA `pixels`, `width` pixels wide, `factor` times in both directions by
+/// repeating each pixel, so its edges stay as sharp as they were.
+pub fn upscale_rgba(pixels: &[u8], width: u32, factor: u32) -> Vec<u8> {
+    let (width, factor) = (width as usize, factor as usize);
+    let mut result = Vec::with_capacity(pixels.len() * factor * factor);
+    for row in pixels.chunks(width * 4) {
+        let wide: Vec<u8> = row
+            .chunks(4)
+            .flat_map(|p| std::iter::repeat_n(p, factor).flatten().copied())
+            .collect();
+        for _ in 0..factor {
+            result.extend_from_slice(&wide);
+        }
+    }
+    result
+}
+
+#[test]
+fn test_upscale_rgba() {
+    let (a, b, c, d) = ([1, 1, 1, 1], [2, 2, 2, 2], [3, 3, 3, 3], [4, 4, 4, 4]);
+    let pixels = [a, b, c, d].concat();
+    let doubled = [a, a, b, b, a, a, b, b, c, c, d, d, c, c, d, d].concat();
+    assert!(upscale_rgba(&pixels, 2, 2) == doubled);
+    assert!(upscale_rgba(&pixels, 2, 1) == pixels);
+    assert!(upscale_rgba(&pixels, 4, 3).len() == pixels.len() * 9);
+}
+
+/// Return the offsets of the grid lines across a `length` pixel span divided into `cell` pixel
+/// cells. Lines are `cell` apart starting at 0. A line that would fall exactly at `length`, just
+/// outside the span, is moved onto its last pixel instead.
+pub fn grid_lines(length: u32, cell: u32) -> Vec<u32> {
+    let mut lines: Vec<u32> = (0..length).step_by(cell.max(1) as usize).collect();
+    if length > 0 && length.is_multiple_of(cell.max(1)) && lines.last() != Some(&(length - 1)) {
+        lines.push(length - 1);
+    }
+    lines
+}
+
+#[test]
+fn test_grid_lines() {
+    assert!(grid_lines(30, 10) == vec![0, 10, 20, 29]);
+    assert!(grid_lines(25, 10) == vec![0, 10, 20]);
+    assert!(grid_lines(0, 10).is_empty());
+    assert!(grid_lines(1, 1) == vec![0]);
+}
@@ -1,55 +1,366 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Read};
 use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
 
 extern crate sdl2;
+use crate::atlas;
+use crate::collision::CollisionMask;
+#[cfg(any(debug_assertions, feature = "console"))]
+use crate::console;
+use crate::decode;
+use crate::error::{self, SimpleError};
 use crate::event::{self, Event};
+use crate::font::{self, DEFAULT_FONT_BYTES, DEFAULT_FONT_STR, Font};
+use crate::frame::Frame;
+use crate::hook::{self, HookId, HookResult};
+use crate::layer::{self, DrawStats};
+use crate::replay;
+use crate::rng::Rng;
 use crate::shape;
+use crate::texture;
 use crate::util;
 use sdl2::image::ImageRWops;
 use sdl2::image::LoadSurface;
-use sdl2::image::LoadTexture;
 use sdl2::pixels;
 use sdl2::render;
 use sdl2::rwops;
 use sdl2::surface;
 
+/// How drawing is combined with the pixels already on the Window. See `Window::set_blend_mode`.
+pub use sdl2::render::BlendMode;
+
 /**
  * A Window can display graphics and handle events.
  *
  * A Window has a draw color at all times, and that color is applied to every operation. If you set
- * the color to `(255, 0, 0)`, all drawn graphics and images will have a red tint.
+ * the color to `(255, 0, 0)`, all drawn graphics and images will have a red tint. The alpha of the
+ * color applies too: images and text drawn with an alpha of 128 are half transparent, just like
+ * rectangles.
  *
  * Creating multiple Windows is untested and will probably crash!
  *
  */
 pub struct Window {
     // sdl graphics
+    sdl_context: sdl2::Sdl,
     event_pump: sdl2::EventPump,
     timer_subsystem: sdl2::TimerSubsystem,
     canvas: sdl2::render::Canvas<sdl2::video::Window>,
+    // what every Image, Font and Atlas this Window makes ties its textures to
+    textures: std::rc::Rc<texture::Pool>,
     foreground_color: pixels::Color,
     font: Option<Font>,
+    layout_rules: font::LayoutRules,
+    crisp_text: bool,
+    // tokens print_template has logged as having no substitution
+    unknown_template_tokens: HashSet<String>,
+    // reused by print_fmt, so formatting doesn't allocate every frame
+    format_buffer: String,
+    viewport: Option<shape::Rect>,
+    draw_offset: (i32, i32),
+    saved_states: Vec<RenderState>,
+    layers: layer::Layers,
+
+    // partial redraw: the frame kept from one frame to the next, and what was marked dirty on it
+    retained_frame: Option<render::Texture>,
+    dirty_rects: Vec<shape::Rect>,
+    // where print_textured puts its text together, kept to be reused
+    text_target: Option<render::Texture>,
+    // the texture of the Image draw_to_image is drawing to, while it runs; owned by the Image
+    image_target: Option<render::Texture>,
+
+    // clearing at the start of every frame
+    clear_color: pixels::Color,
+    auto_clear: bool,
+
+    // controllers, keyed by instance id
+    controller_subsystem: Option<sdl2::GameControllerSubsystem>,
+    controllers: HashMap<u32, sdl2::controller::GameController>,
 
     // events and event logic
     running: bool,
     event_queue: std::vec::Vec<Event>,
+    event_queue_limit: Option<usize>,
+    dropped_events: usize,
+    coalesce_motion: bool,
 
     // timing
     target_ticks_per_frame: u32,
     ticks_at_previous_frame: u32,
+    ticks_in_previous_frame: u32,
+    // frames ended so far
+    frame_count: u64,
+    // the frame times of those frames added up, the clock every_millis reads
+    frame_clock: u32,
+    // when the Window was created, the zero of elapsed_seconds
+    created_ticks: u32,
+
+    // input snapshot, taken once per frame
+    keys_down: HashSet<event::Key>,
+    keys_down_previous_frame: HashSet<event::Key>,
+    mouse_state: sdl2::mouse::MouseState,
+    mouse_position: (i32, i32),
+    // when warp_mouse was last called, until its motion event has been dropped
+    warped_at: Option<u32>,
+
+    // (key, timestamp) of every key press taken by the last begin_frame
+    key_presses: Vec<(event::Key, u32)>,
+
+    // input recording and playback; see start_input_recording
+    recorder: Option<replay::Recorder<io::BufWriter<fs::File>>>,
+    playback: Option<replay::Playback>,
+
+    // mouse movement: recent (timestamp, x, y) samples, and the positions reported this frame
+    mouse_samples: VecDeque<(u32, i32, i32)>,
+    mouse_velocity_window: u32,
+    // the velocity the frame being played back had when it was recorded
+    played_mouse_velocity: Option<(f32, f32)>,
+    mouse_path: Vec<(i32, i32)>,
+
+    rng: Rng,
+
+    // hooks; frame hooks are moved out of the Window while they run
+    next_hook_id: u64,
+    event_hooks: Vec<(HookId, hook::EventHook)>,
+    event_logger: Option<hook::EventLogger>,
+    frame_hooks: Vec<(HookId, hook::FrameHook)>,
+    running_frame_hooks: bool,
+    hooks_removed_while_running: Vec<HookId>,
+
+    // debugging
+    debug_overlay: bool,
+    debug_font: Option<Font>,
+    previous_draw_stats: DrawStats,
+    // (threshold_ms, f) from on_slow_frame
+    slow_frame_hook: Option<(u32, hook::SlowFrameHook)>,
+    #[cfg(any(debug_assertions, feature = "console"))]
+    console: console::Console,
+
+    // asynchronous loading
+    image_load_sender: mpsc::Sender<ImageLoadResult>,
+    image_load_receiver: mpsc::Receiver<ImageLoadResult>,
+    // the SDL event type the worker threads push when they finish, to wake wait_event
+    image_load_event: u32,
+    loaded_images: HashMap<String, Image>,
+
+    // load failures
+    placeholder_on_load_failure: bool,
+    last_load_error: RefCell<Option<String>>,
+}
+
+/// ControllerInfo describes a connected game controller. See `Window::controllers`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ControllerInfo {
+    /// The id to pass to controller methods such as `Window::rumble`. It stays the same for as
+    /// long as the controller is connected.
+    pub id: u32,
+    pub name: String,
+    pub is_attached: bool,
+}
+
+/// How textures are filtered when they are drawn at a different size than their own. See
+/// `Window::new_with_hints`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScaleQuality {
+    /// Nearest-pixel sampling, which keeps pixel art crisp. This is SDL's default.
+    Nearest,
+    /// Linear filtering.
+    Linear,
+    /// Anisotropic filtering where the renderer supports it, linear otherwise.
+    Best,
+}
+
+impl ScaleQuality {
+    /// The value SDL expects for its render scale quality hint.
+    fn hint_value(self) -> &'static str {
+        match self {
+            ScaleQuality::Nearest => "0",
+            ScaleQuality::Linear => "1",
+            ScaleQuality::Best => "2",
+        }
+    }
+}
+
+/// How `Window::draw_background` fits an image to the area it covers.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BackgroundFit {
+    /// Stretch the image to exactly the size of the area, whatever its shape.
+    Stretch,
+    /// Scale the image, keeping its shape, until it covers the whole area, and center it. Parts of
+    /// the image may be cut off.
+    Cover,
+    /// Repeat the image at its own size, starting from the top-left corner.
+    Tile,
+}
+
+/// Everything `save_state` records and `restore_state` puts back.
+struct RenderState {
+    foreground_color: pixels::Color,
+    viewport: Option<shape::Rect>,
+    draw_offset: (i32, i32),
+    clip_rect: Option<shape::Rect>,
+    blend_mode: render::BlendMode,
+    scale: (f32, f32),
+}
+
+/// PointMapping is how a pixel of the Window, which SDL reports the mouse in, maps to the
+/// coordinates drawing happens in: less the black bars of `Window::enable_pixel_perfect`, and
+/// divided by the scale.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct PointMapping {
+    bars: (i32, i32),
+    scale: (f32, f32),
+}
+
+impl PointMapping {
+    /// Return the drawing coordinates of the Window pixel `point`.
+    fn to_drawing(self, (x, y): (i32, i32)) -> (i32, i32) {
+        let map = |v: i32, bar: i32, scale: f32| ((v as f64 - bar as f64) / scale as f64).floor();
+        (
+            map(x, self.bars.0, self.scale.0) as i32,
+            map(y, self.bars.1, self.scale.1) as i32,
+        )
+    }
+
+    /// Return the Window pixel at the middle of the drawing pixel `point`, which `to_drawing`
+    /// maps back to `point`.
+    fn to_window(self, (x, y): (i32, i32)) -> (i32, i32) {
+        let map =
+            |v: i32, bar: i32, scale: f32| ((v as f64 + 0.5) * scale as f64 + bar as f64).floor();
+        (
+            map(x, self.bars.0, self.scale.0) as i32,
+            map(y, self.bars.1, self.scale.1) as i32,
+        )
+    }
+}
+
+/// Return where to draw an `image_width` by `image_height` image to cover a `width` by `height`
+/// area as `fit` says.
+fn background_rects(
+    image_width: u32,
+    image_height: u32,
+    width: u32,
+    height: u32,
+    fit: BackgroundFit,
+) -> Vec<shape::Rect> {
+    if image_width == 0 || image_height == 0 || width == 0 || height == 0 {
+        return vec![];
+    }
+    match fit {
+        BackgroundFit::Stretch => vec![shape::Rect::new(0, 0, width, height)],
+        BackgroundFit::Cover => {
+            let scale = f32::max(
+                width as f32 / image_width as f32,
+                height as f32 / image_height as f32,
+            );
+            let w = (image_width as f32 * scale).ceil() as u32;
+            let h = (image_height as f32 * scale).ceil() as u32;
+            let center = shape::Point::new((width / 2) as i32, (height / 2) as i32);
+            let (x, y) = centered(center, w, h);
+            vec![shape::Rect::new(x, y, w, h)]
+        }
+        BackgroundFit::Tile => {
+            let mut rects = vec![];
+            for y in (0..height).step_by(image_height as usize) {
+                for x in (0..width).step_by(image_width as usize) {
+                    rects.push(shape::Rect::new(
+                        x as i32,
+                        y as i32,
+                        image_width,
+                        image_height,
+                    ));
+                }
+            }
+            rects
+        }
+    }
+}
+
+/// Return `len` bytes of `bundle` starting at `offset`, or an error if they aren't all there.
+fn bundle_slice(bundle: &[u8], offset: usize, len: usize) -> Result<&[u8], SimpleError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| bundle.get(offset..end))
+        .ok_or(SimpleError::OutOfRange {
+            offset,
+            len,
+            bundle_len: bundle.len(),
+        })
+}
+
+/// Read everything that is left in `reader`.
+fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>, SimpleError> {
+    let mut data = vec![];
+    reader
+        .read_to_end(&mut data)
+        .map_err(|e| SimpleError::Io(e.to_string()))?;
+    Ok(data)
+}
+
+/// Start the message of an error decoding the bytes of a bundle or a reader with "decode failed",
+/// as it always has been.
+fn decode_failed(e: SimpleError) -> SimpleError {
+    match e {
+        SimpleError::ImageLoad(e) => SimpleError::ImageLoad(format!("decode failed: {}", e)),
+        SimpleError::FontParse(e) => SimpleError::FontParse(format!("decode failed: {}", e)),
+        e => e,
+    }
+}
+
+/**
+ * Return true if there is a display that a Window could open on. Use this to skip graphics on a
+ * headless machine, such as a CI server, where `Window::new` would panic.
+ *
+ * This starts and stops only SDL's video subsystem, which is much cheaper than creating a Window.
+ * It can be called while a Window exists, and then returns true.
+ */
+pub fn is_display_available() -> bool {
+    let video = sdl2::sys::SDL_INIT_VIDEO;
+    // SAFETY: SDL counts how many times each subsystem has been started, so stopping the video
+    // subsystem here leaves it running if a Window started it too.
+    unsafe {
+        if sdl2::sys::SDL_InitSubSystem(video) != 0 {
+            return false;
+        }
+        let displays = sdl2::sys::SDL_GetNumVideoDisplays();
+        sdl2::sys::SDL_QuitSubSystem(video);
+        displays > 0
+    }
 }
 
+/// How far back, in milliseconds, `mouse_velocity` looks by default.
+const DEFAULT_MOUSE_VELOCITY_WINDOW: u32 = 50;
+
+/// The size, in pixels along each side, of the Image that replaces one that failed to load.
+const PLACEHOLDER_SIZE: u32 = 64;
+
+/// A key passed to `load_image_async`, and the decoded image.
+type ImageLoadResult = (String, Result<decode::DecodedImage, String>);
+
 /// Top-level Running / Creation Methods
 /// ====================================
 impl Window {
     /// Intialize a new running window. `name` is used as a caption.
     pub fn new_fullscreen(name: &str) -> Self {
-        Self::new_inner(name, None)
+        Self::new_inner(name, None, None)
     }
     pub fn new(name: &str, width: u16, height: u16) -> Self {
-        Self::new_inner(name, Some((width, height)))
+        Self::new_inner(name, Some((width, height)), None)
+    }
+
+    /// Like `new`, but choose how every texture is filtered when drawn scaled. SDL reads this
+    /// setting when a texture is created, so it has to be decided here, before the renderer and
+    /// the default font exist; changing SDL's hint later only affects textures created after that.
+    pub fn new_with_hints(name: &str, width: u16, height: u16, quality: ScaleQuality) -> Self {
+        Self::new_inner(name, Some((width, height)), Some(quality))
     }
-    fn new_inner(name: &str, dim: Option<(u16, u16)>) -> Self {
+
+    fn new_inner(name: &str, dim: Option<(u16, u16)>, quality: Option<ScaleQuality>) -> Self {
         // SDL2 Initialization calls. This section here is the reason we can't easily create
         // multiple Windows. There would have to be some kind of global variable that tracked
         // whether SDL2 had already been init'd.
@@ -65,11 +376,16 @@ impl Window {
         // TODO: solve this problem
         //
         let sdl_context = sdl2::init().unwrap();
-        let timer_subsystem = sdl_context.timer().unwrap();
+        let mut timer_subsystem = sdl_context.timer().unwrap();
+        let created_ticks = timer_subsystem.ticks();
         sdl2::image::init(sdl2::image::InitFlag::all()).unwrap();
 
+        // Controllers are optional: a failure here just means none will ever show up.
+        let controller_subsystem = sdl_context.game_controller().ok();
+
         let video_subsystem = sdl_context.video().unwrap();
         let event_pump = sdl_context.event_pump().unwrap();
+        let image_load_event = unsafe { sdl_context.event().unwrap().register_event() }.unwrap();
         let sdl_window = if let Some((width, height)) = dim {
             video_subsystem
                 .window(name, width as u32, height as u32)
@@ -83,21 +399,83 @@ impl Window {
                 .build()
                 .unwrap()
         };
+        if let Some(quality) = quality {
+            sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", quality.hint_value());
+        }
         let mut canvas = sdl_window.into_canvas().build().unwrap();
+        let renderer = canvas.raw();
 
         // for transparency
         canvas.set_blend_mode(render::BlendMode::Blend);
 
+        let mouse_state = event_pump.mouse_state();
+        let (image_load_sender, image_load_receiver) = mpsc::channel();
         let mut window = Window {
+            sdl_context,
             timer_subsystem,
             event_pump,
             canvas,
+            textures: texture::Pool::new(renderer),
+            controller_subsystem,
+            controllers: HashMap::new(),
             running: true,
             event_queue: vec![],
+            event_queue_limit: Some(DEFAULT_EVENT_QUEUE_LIMIT),
+            dropped_events: 0,
+            coalesce_motion: false,
             foreground_color: pixels::Color::RGBA(0, 0, 0, 255),
             target_ticks_per_frame: (1000.0 / 60.0) as u32,
             ticks_at_previous_frame: 0,
+            ticks_in_previous_frame: 0,
+            frame_count: 0,
+            created_ticks,
+            frame_clock: 0,
+            keys_down: HashSet::new(),
+            keys_down_previous_frame: HashSet::new(),
+            mouse_position: (mouse_state.x(), mouse_state.y()),
+            mouse_state,
+            warped_at: None,
+            key_presses: vec![],
+            recorder: None,
+            playback: None,
+            mouse_samples: VecDeque::new(),
+            mouse_velocity_window: DEFAULT_MOUSE_VELOCITY_WINDOW,
+            played_mouse_velocity: None,
+            mouse_path: vec![],
             font: None,
+            layout_rules: font::LayoutRules::default(),
+            crisp_text: false,
+            unknown_template_tokens: HashSet::new(),
+            format_buffer: String::new(),
+            viewport: None,
+            draw_offset: (0, 0),
+            saved_states: vec![],
+            layers: layer::Layers::default(),
+            retained_frame: None,
+            text_target: None,
+            image_target: None,
+            dirty_rects: vec![],
+            clear_color: pixels::Color::RGB(0, 0, 0),
+            auto_clear: false,
+            rng: Rng::from_seed(time_seed()),
+            next_hook_id: 0,
+            event_hooks: vec![],
+            event_logger: None,
+            frame_hooks: vec![],
+            running_frame_hooks: false,
+            hooks_removed_while_running: vec![],
+            debug_overlay: false,
+            previous_draw_stats: DrawStats::default(),
+            slow_frame_hook: None,
+            #[cfg(any(debug_assertions, feature = "console"))]
+            console: console::Console::new(),
+            debug_font: None,
+            image_load_sender,
+            image_load_receiver,
+            image_load_event,
+            loaded_images: HashMap::new(),
+            placeholder_on_load_failure: false,
+            last_load_error: RefCell::new(None),
         };
 
         // clear first, then load the default font
@@ -114,12 +492,120 @@ impl Window {
         window
     }
 
-    /// Get the canvas drawable size
+    /// Get the canvas drawable size. This is the size of the whole Window, even while a viewport
+    /// is set.
     pub fn drawable_size(&self) -> (u32, u32) {
-        (
-            self.canvas.viewport().w as u32,
-            self.canvas.viewport().h as u32,
-        )
+        self.canvas.output_size().unwrap_or_else(|_| {
+            let viewport = self.canvas.viewport();
+            (viewport.width(), viewport.height())
+        })
+    }
+
+    /// Return the SDL canvas the Window draws with, for the features simple doesn't wrap.
+    ///
+    /// This is an escape hatch, and the Window can't see what is done with it. Changing the
+    /// viewport, clip rect, scale or render target behind the Window's back leaves `viewport`,
+    /// `save_state` and partial redraw working from stale values. Anything drawn here is drawn
+    /// straight away, before whatever has been recorded on layers. The draw color and the blend
+    /// mode may be changed freely: the Window sets the draw color before every draw, but a changed
+    /// blend mode stays in effect for the Window's drawing too.
+    pub fn canvas_mut(&mut self) -> &mut render::Canvas<sdl2::video::Window> {
+        self.flush_batch();
+        &mut self.canvas
+    }
+
+    /// Return the diagonal, horizontal and vertical DPI of the display the Window is on, or `None`
+    /// if SDL can't tell. No default is made up: many platforms report nothing, and a fake 96
+    /// would look like a real answer.
+    pub fn display_dpi(&self) -> Option<(f32, f32, f32)> {
+        let index = self.canvas.window().display_index().ok()?;
+        self.display_dpi_at(index).ok()
+    }
+
+    /// Return the diagonal, horizontal and vertical DPI of the display at `index`, as reported by
+    /// SDL. Returns an error if SDL can't tell, rather than guessing.
+    pub fn display_dpi_at(&self, index: i32) -> Result<(f32, f32, f32), SimpleError> {
+        self.canvas
+            .window()
+            .subsystem()
+            .display_dpi(index)
+            .map_err(SimpleError::Sdl)
+    }
+
+    /// Return how much to scale the user interface by so that it looks about the same size on the
+    /// display the Window is on as at 96 DPI: 1 on an ordinary monitor, 2 on a typical 4K laptop.
+    /// The result is rounded to a quarter and kept between 1 and 4. It is 1 when the DPI is
+    /// unknown. Feed it to `print_scaled` and to layout arithmetic.
+    pub fn suggested_ui_scale(&self) -> f32 {
+        ui_scale_for_dpi(self.display_dpi().map(|(_, hdpi, _)| hdpi))
+    }
+
+    /// Return true if the Window stays above other windows.
+    pub fn is_always_on_top(&self) -> bool {
+        let flag = sdl2::sys::SDL_WindowFlags::SDL_WINDOW_ALWAYS_ON_TOP as u32;
+        self.canvas.window().window_flags() & flag != 0
+    }
+
+    /// Keep the Window above other windows, or stop doing so.
+    ///
+    /// Changing this on an open window needs `SDL_SetWindowAlwaysOnTop`, from SDL 2.0.16, which
+    /// the sdl2 crate simple is built on has no binding for, so it is looked up in the SDL the
+    /// program is running with. With an older SDL this returns an error unless the Window is
+    /// already in the state asked for.
+    pub fn set_always_on_top(&mut self, on: bool) -> Result<(), SimpleError> {
+        if self.is_always_on_top() == on {
+            return Ok(());
+        }
+        let Some(function) = newer_sdl_function("SDL_SetWindowAlwaysOnTop") else {
+            return Err(SimpleError::Unsupported(format!(
+                "changing always-on-top needs SDL 2.0.16, this is SDL {}",
+                sdl2::version::version()
+            )));
+        };
+        type SetAlwaysOnTop = unsafe extern "C" fn(*mut sdl2::sys::SDL_Window, sdl2::sys::SDL_bool);
+        let on = if on {
+            sdl2::sys::SDL_bool::SDL_TRUE
+        } else {
+            sdl2::sys::SDL_bool::SDL_FALSE
+        };
+        // SAFETY: the function was found under this name, whose signature SDL keeps stable, and
+        // the window is alive
+        unsafe {
+            let set: SetAlwaysOnTop = std::mem::transmute(function);
+            set(self.canvas.window().raw(), on);
+        }
+        Ok(())
+    }
+
+    /// Ask for the user's attention, for instance by flashing the Window's taskbar entry, when
+    /// something happens while the program is in the background. If `briefly` is true the Window
+    /// flashes once, otherwise until it gets focus.
+    ///
+    /// This needs `SDL_FlashWindow`, from SDL 2.0.16, which the sdl2 crate simple is built on has
+    /// no binding for, so it is looked up in the SDL the program is running with. Where that SDL
+    /// is older, or can't flash a Window on this platform, this does nothing and returns Ok, so
+    /// notifications needn't check. It only returns an error if SDL fails to flash the Window.
+    pub fn flash(&mut self, briefly: bool) -> Result<(), SimpleError> {
+        let Some(function) = newer_sdl_function("SDL_FlashWindow") else {
+            return Ok(());
+        };
+        // SDL_FlashOperation: SDL_FLASH_BRIEFLY or SDL_FLASH_UNTIL_FOCUSED
+        type FlashWindow = unsafe extern "C" fn(*mut sdl2::sys::SDL_Window, i32) -> i32;
+        let operation = if briefly { 1 } else { 2 };
+        // SAFETY: the function was found under this name, whose signature SDL keeps stable, and
+        // the window is alive
+        let result = unsafe {
+            let flash: FlashWindow = std::mem::transmute(function);
+            flash(self.canvas.window().raw(), operation)
+        };
+        if result != 0 {
+            let error = sdl2::get_error();
+            // the message SDL_Unsupported sets, for platforms that can't flash
+            if error != "That operation is not supported" {
+                return Err(SimpleError::Sdl(error));
+            }
+        }
+        Ok(())
     }
 
     /// Redrawing and update the display, while maintaining a consistent framerate and updating the
@@ -127,36 +613,419 @@ impl Window {
     ///
     /// NOTE: This function returns false if the program should terminate. This allows for nice
     /// constructs like `while app.next_frame() { ... }`
+    ///
+    /// This is `end_frame` followed by `begin_frame`, for programs written before those existed.
     pub fn next_frame(&mut self) -> bool {
+        if !self.end_frame() {
+            return false;
+        }
+        self.poll_events();
+        true
+    }
+
+    /// Start a frame: take the events that have arrived since the last one and put them on the
+    /// event queue, and update the keyboard and mouse state. If `set_auto_clear` is on, the screen
+    /// has already been cleared, so the frame starts blank. Draw after this, then call `end_frame`.
+    ///
+    /// Together the two make the order of a frame plain to see: `begin_frame`, handle the events,
+    /// update and draw, and stop when `end_frame` returns false. `next_frame` does the same work,
+    /// but ends one frame and begins the next in a single call.
+    ///
+    /// The returned Frame can be kept to draw through, which stops the frame being ended by
+    /// mistake before the drawing is done; see `Frame`. Programs that draw on the Window directly
+    /// can ignore it.
+    pub fn begin_frame(&mut self) -> Frame<'_> {
+        self.poll_events();
+        Frame::new(self)
+    }
+
+    /// The work of `begin_frame`.
+    fn poll_events(&mut self) {
+        let first_new_event = self.event_queue.len();
+        self.mouse_path.clear();
+        self.key_presses.clear();
+        match self
+            .playback
+            .as_mut()
+            .and_then(|playback| playback.next_frame())
+        {
+            Some(input) => self.play_back_frame(input),
+            None => {
+                self.playback = None;
+                self.played_mouse_velocity = None;
+                self.take_sdl_events();
+            }
+        }
+        self.record_frame();
+        // a warp's motion event is queued straight away, so if it hasn't come by now it won't
+        self.warped_at = None;
+        self.finish_image_loads();
+        #[cfg(any(debug_assertions, feature = "console"))]
+        self.console.collect_pending();
+        if self.coalesce_motion {
+            event::coalesce_motion(&mut self.event_queue, first_new_event);
+        }
+        if let Some(limit) = self.event_queue_limit {
+            self.dropped_events += event::enforce_queue_limit(&mut self.event_queue, limit);
+        }
+    }
+
+    /// Take the events SDL has received, and the keyboard and mouse state.
+    fn take_sdl_events(&mut self) {
+        while let Some(sdl_event) = self.event_pump.poll_event() {
+            if self.is_warp_motion(&sdl_event) {
+                continue;
+            }
+            self.track_mouse(&sdl_event);
+            if let sdl2::event::Event::KeyDown {
+                scancode: Some(key),
+                repeat: false,
+                timestamp,
+                ..
+            } = sdl_event
+            {
+                self.key_presses.push((key, timestamp));
+            }
+            let ms = self.ticks_since_created();
+            if let Some(e) = self.track_controllers(&sdl_event) {
+                self.queue_input_event(ms, e);
+            }
+            if let Some(e) = Event::from_sdl2_event(sdl_event) {
+                if e == Event::RenderTargetsLost {
+                    self.lose_render_targets();
+                }
+                self.queue_input_event(ms, e);
+            }
+        }
+        self.take_input_snapshot();
+    }
+
+    /// Take the input of a frame of the recording being played instead of SDL's. SDL's events
+    /// are dropped, except that closing the Window still quits.
+    fn play_back_frame(&mut self, input: replay::FrameInput) {
+        while let Some(sdl_event) = self.event_pump.poll_event() {
+            if let sdl2::event::Event::Quit { .. } = sdl_event {
+                self.quit();
+            }
+            // the recording has its own RenderTargetsLost events, but the loss is real
+            if event::is_render_reset(&sdl_event) {
+                self.lose_render_targets();
+            }
+        }
+        // the recorded frame time takes the place of the real one on the frame clock too
+        self.frame_clock = self
+            .frame_clock
+            .wrapping_sub(self.ticks_in_previous_frame)
+            .wrapping_add(input.frame_time);
+        self.ticks_in_previous_frame = input.frame_time;
+        let keys_down = input.keys_down.into_iter().collect();
+        self.keys_down_previous_frame = std::mem::replace(&mut self.keys_down, keys_down);
+        self.mouse_state = sdl2::mouse::MouseState::from_sdl_state(input.mouse_buttons);
+        self.mouse_position = input.mouse_position;
+        self.mouse_samples.clear();
+        self.played_mouse_velocity = Some(input.mouse_velocity);
+        let created = self.created_ticks;
+        self.key_presses = input
+            .key_presses
+            .into_iter()
+            .map(|(key, ms)| (key, created.wrapping_add(ms)))
+            .collect();
+        for (ms, e) in input.events {
+            if let Event::MouseMotion {
+                mouse_x, mouse_y, ..
+            } = e
+            {
+                self.mouse_path.push((mouse_x, mouse_y));
+            }
+            self.queue_input_event(ms, e);
+        }
+    }
+
+    /// Record that what was drawn on the render targets is gone: render target Images draw as a
+    /// checkerboard until they are drawn again, and the kept frame of partial redraw is all dirty.
+    fn lose_render_targets(&mut self) {
+        self.textures.lose_targets();
+        if self.retained_frame.is_some() {
+            let (width, height) = self.drawable_size();
+            self.mark_dirty(shape::Rect::new(0, 0, width, height));
+        }
+    }
+
+    /// Queue an event that came from the user, recording it first if a recording is running.
+    /// `ms` is when it arrived, in milliseconds since the Window was created.
+    fn queue_input_event(&mut self, ms: u32, e: Event) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.input.events.push((ms, e.clone()));
+        }
+        self.queue_event(e);
+    }
+
+    /// Write the input of the frame that is starting to the recording, if one is running.
+    fn record_frame(&mut self) {
+        let mouse_velocity = self.mouse_velocity();
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        let input = &mut recorder.input;
+        input.frame_time = self.ticks_in_previous_frame;
+        input.mouse_velocity = mouse_velocity;
+        input.mouse_position = self.mouse_position;
+        input.mouse_buttons = self.mouse_state.to_sdl_state();
+        input.keys_down = self.keys_down.iter().copied().collect();
+        input.keys_down.sort_by_key(|&key| key as i32);
+        input.key_presses = self
+            .key_presses
+            .iter()
+            .map(|&(key, timestamp)| (key, timestamp.wrapping_sub(self.created_ticks)))
+            .collect();
+        recorder.end_frame();
+    }
+
+    /// Start recording the input of every frame to the file at `path`, replacing it: the events
+    /// `begin_frame` (or `next_frame`) takes, the keyboard and mouse state, and `frame_time`, so
+    /// that `play_input_recording` can give a later run exactly the same input. Events taken by
+    /// `wait_event` aren't recorded. The file is written as each frame starts, so it survives a
+    /// crash.
+    ///
+    /// To reproduce a bug from a recording, the program must also do the same thing with the same
+    /// input: seed the random number generator with `seed_rng`, and step the simulation by
+    /// `frame_time` rather than by the clock. A recording already running is stopped first.
+    pub fn start_input_recording(&mut self, path: impl AsRef<Path>) -> Result<(), SimpleError> {
+        let path = path.as_ref();
+        // a previous recording's write error is of no interest once a new one has started
+        let _ = self.stop_input_recording();
+        let recorder = fs::File::create(path)
+            .map_err(|e| SimpleError::Write(e.to_string()))
+            .and_then(|file| replay::Recorder::new(io::BufWriter::new(file)))
+            .map_err(|e| e.in_file(path))?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Stop the recording started by `start_input_recording`. If writing the file failed at any
+    /// point, the recording stopped there, and the error is returned now.
+    pub fn stop_input_recording(&mut self) -> Result<(), SimpleError> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Play back the recording at `path`, made by `start_input_recording`. From the next frame on,
+    /// each frame takes the next recorded frame's input instead of the real keyboard and mouse,
+    /// and `frame_time` reports what it did when the recording was made. Real input is ignored,
+    /// apart from closing the Window, until the recording runs out. Event hooks and the console
+    /// see the recorded events just as they saw the real ones.
+    ///
+    /// Recordings from a different version of the file format are refused with
+    /// `SimpleError::Unsupported`. Either way, the error names the file.
+    pub fn play_input_recording(&mut self, path: impl AsRef<Path>) -> Result<(), SimpleError> {
+        let path = path.as_ref();
+        let playback = fs::read_to_string(path)
+            .map_err(|e| SimpleError::Io(e.to_string()))
+            .and_then(|text| replay::Playback::parse(&text))
+            .map_err(|e| e.in_file(path))?;
+        self.playback = Some(playback);
+        Ok(())
+    }
+
+    /// Return true while a recording started with `play_input_recording` is being played.
+    pub fn is_playing_input_recording(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Finish a frame: show everything drawn since `begin_frame`, then wait as long as the
+    /// framerate limit asks. Return false if the program should terminate, in which case nothing
+    /// is shown.
+    ///
+    /// The steps always happen in this order, so code drawing on top of the application can
+    /// rely on it:
+    ///
+    /// 1. the calls recorded on layers are drawn;
+    /// 2. the frame hooks run, and can see this frame's `current_draw_stats`;
+    /// 3. the console and the debug overlay are drawn, if they are showing;
+    /// 4. the frame is shown, and `draw_stats` starts reporting it;
+    /// 5. the Window waits for the framerate limit.
+    ///
+    /// `begin_frame`, which `next_frame` calls next, then takes the new events.
+    pub fn end_frame(&mut self) -> bool {
         if !self.running {
             return false;
         }
 
-        self.canvas.present();
+        self.present_frame();
 
         let mut current_ticks = self.timer_subsystem.ticks();
         while current_ticks - self.ticks_at_previous_frame < self.target_ticks_per_frame {
             self.timer_subsystem.delay(3);
+            // SDL timestamps events when it takes them from the OS, so take them while waiting
+            // rather than all at once in begin_frame; see key_press_times
+            self.event_pump.pump_events();
             current_ticks = self.timer_subsystem.ticks();
         }
+        let first_frame = self.ticks_at_previous_frame == 0;
+        self.ticks_in_previous_frame = current_ticks - self.ticks_at_previous_frame;
         self.ticks_at_previous_frame = current_ticks;
+        self.frame_clock = self.frame_clock.wrapping_add(self.ticks_in_previous_frame);
+        self.frame_count += 1;
+        if let Some((threshold_ms, f)) = self.slow_frame_hook.as_mut()
+            && !first_frame
+            && self.ticks_in_previous_frame > *threshold_ms
+        {
+            f(self.ticks_in_previous_frame);
+        }
+        true
+    }
+
+    /// Show what has been drawn so far, then sleep until the next event arrives and return it. This
+    /// is meant for tools that only need to redraw in response to input; games should keep using
+    /// `next_frame`, which polls.
+    ///
+    /// Events already waiting in the queue are returned first, without blocking. If the event is
+    /// `Quit`, the Window is marked as quitting exactly as `next_frame` would, so the following
+    /// call to `next_frame` returns false. A `load_image_async` finishing wakes it too, and its
+    /// `ImageLoaded` or `ImageLoadFailed` event is returned.
+    pub fn wait_event(&mut self) -> Event {
+        self.finish_image_loads();
+        if self.has_event() {
+            return self.next_event();
+        }
+
+        self.present_frame();
 
-        // Handle events
         loop {
-            let sdl_event = self.event_pump.poll_event();
-            match sdl_event {
-                None => break,
-                Some(sdl_event) => match Event::from_sdl2_event(sdl_event) {
-                    Some(Event::Quit) => self.quit(),
-
-                    // any other unrecognized event
-                    Some(e) => self.event_queue.push(e),
-                    None => (),
-                },
-            };
+            let sdl_event = self.event_pump.wait_event();
+            self.finish_image_loads();
+            if !self.is_warp_motion(&sdl_event) {
+                self.track_mouse(&sdl_event);
+                let e = self
+                    .track_controllers(&sdl_event)
+                    .or_else(|| Event::from_sdl2_event(sdl_event));
+                if e == Some(Event::RenderTargetsLost) {
+                    self.lose_render_targets();
+                }
+                if let Some(e) = e.and_then(|e| self.filter_event(e)) {
+                    self.take_input_snapshot();
+                    if e == Event::Quit {
+                        self.quit();
+                    }
+                    self.event_queue.push(e);
+                }
+            }
+            if self.has_event() {
+                return self.next_event();
+            }
         }
+    }
 
-        true
+    /// Return the Window's random number generator. It is seeded from the time the Window was
+    /// created, so every run is different unless `seed_rng` is called.
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Restart the Window's random number generator from `seed`. Games that record replays or
+    /// run in lockstep over a network call this with a shared seed, so every run draws the same
+    /// numbers.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::from_seed(seed);
+    }
+
+    /// Show the event to the logger, to the console, if it is compiled in, and then to the event
+    /// hooks. Return it unless the console or a hook consumed it.
+    fn filter_event(&mut self, e: Event) -> Option<Event> {
+        if let Some(logger) = self.event_logger.as_mut() {
+            logger(&e);
+        }
+        #[cfg(any(debug_assertions, feature = "console"))]
+        if self.console.handle_event(&e) == HookResult::Consume {
+            return None;
+        }
+        hook::dispatch(&mut self.event_hooks, e)
+    }
+
+    /// Show the event to the console and the event hooks, then put it on the queue unless one of
+    /// them consumed it. `Quit` isn't queued, but makes the Window quit.
+    fn queue_event(&mut self, e: Event) {
+        match self.filter_event(e) {
+            Some(Event::Quit) => self.quit(),
+            Some(e) => self.event_queue.push(e),
+            None => (),
+        }
+    }
+
+    /// Call `f` with every event before it reaches the event queue, including `Quit`. If `f`
+    /// returns `HookResult::Consume`, the event goes no further: later hooks and the application
+    /// never see it, and a consumed `Quit` doesn't quit. Hooks are called in the order they were
+    /// added. This lets a library built on simple, like an overlay console, handle its own input
+    /// without the application forwarding events to it.
+    pub fn add_event_hook(&mut self, f: impl FnMut(&Event) -> HookResult + 'static) -> HookId {
+        let id = self.new_hook_id();
+        self.event_hooks.push((id, Box::new(f)));
+        id
+    }
+
+    /// Call `f` with every event the Window takes from SDL, before the console, the event hooks
+    /// or the queue limit can filter it out. This is meant for debugging input: unlike an event
+    /// hook, the logger can't consume events, and it sees the ones that a hook or the console
+    /// consumes. Setting a logger replaces the previous one.
+    pub fn set_event_logger(&mut self, f: impl FnMut(&Event) + 'static) {
+        self.event_logger = Some(Box::new(f));
+    }
+
+    /// Stop calling the logger set with `set_event_logger`.
+    pub fn clear_event_logger(&mut self) {
+        self.event_logger = None;
+    }
+
+    /// Call `f` once per frame, after everything the application drew and just before the frame
+    /// is shown, so it can draw on top. Layers have been drawn by then, and only the console and
+    /// the debug overlay are drawn over the hooks; see `end_frame` for the whole order. Hooks draw
+    /// immediately, even if the application has selected a layer. Frame hooks are called in the
+    /// order they were added, and may add and remove hooks themselves: a frame hook added by
+    /// another one first runs on the next frame, and one removed while hooks are running isn't
+    /// called again.
+    pub fn add_frame_hook(&mut self, f: impl FnMut(&mut Window) + 'static) -> HookId {
+        let id = self.new_hook_id();
+        self.frame_hooks.push((id, Box::new(f)));
+        id
+    }
+
+    /// Remove an event or frame hook. Ids of hooks that were already removed are ignored.
+    pub fn remove_hook(&mut self, id: HookId) {
+        self.event_hooks.retain(|(i, _)| *i != id);
+        self.frame_hooks.retain(|(i, _)| *i != id);
+        if self.running_frame_hooks {
+            self.hooks_removed_while_running.push(id);
+        }
+    }
+
+    fn new_hook_id(&mut self) -> HookId {
+        self.next_hook_id += 1;
+        HookId(self.next_hook_id)
+    }
+
+    fn run_frame_hooks(&mut self) {
+        if self.running_frame_hooks {
+            return;
+        }
+        let mut hooks = std::mem::take(&mut self.frame_hooks);
+        self.running_frame_hooks = true;
+        // the layers have already been drawn, so anything recorded now would show a frame late
+        let layer = self.layers.pause();
+        for (id, f) in hooks.iter_mut() {
+            if !self.hooks_removed_while_running.contains(id) {
+                f(self);
+            }
+        }
+        self.layers.resume(layer);
+        self.running_frame_hooks = false;
+
+        let removed = std::mem::take(&mut self.hooks_removed_while_running);
+        hooks.retain(|(id, _)| !removed.contains(id));
+        // hooks added while running go after the ones that were already there
+        hooks.append(&mut self.frame_hooks);
+        self.frame_hooks = hooks;
     }
 
     /// Return true when there is an event waiting in the queue for processing.
@@ -175,333 +1044,4048 @@ impl Window {
         self.event_queue.remove(0)
     }
 
-    /// Return true if the button is currently pressed. NOTE: This function is probably not
-    /// performant.
-    pub fn is_key_down(&self, key: event::Key) -> bool {
-        self.event_pump.keyboard_state().is_scancode_pressed(key)
+    /// Cap the number of events that may wait in the queue, or pass `None` to let it grow without
+    /// bound. The limit is enforced once per `next_frame`, after new events have been pumped.
+    ///
+    /// When the queue is too long, consecutive `MouseMotion` events are first merged into one
+    /// (keeping the final position and the summed deltas). If that isn't enough, the oldest events
+    /// are dropped. `Quit`, `ImageLoaded` and `ImageLoadFailed` are never dropped. The default
+    /// limit is generous enough that a program which drains its events every frame will never hit
+    /// it.
+    pub fn set_event_queue_limit(&mut self, limit: Option<usize>) {
+        self.event_queue_limit = limit;
     }
 
-    /// Return true if the specified button is down. NOTE: Unknown mouse buttons are NOT handled
-    /// and will always return `false`.
-    pub fn is_mouse_button_down(&self, button: event::MouseButton) -> bool {
-        let mouse_state = self.event_pump.mouse_state();
-        mouse_state.is_mouse_button_pressed(button)
+    /// When enabled, consecutive `MouseMotion` events received during a single `next_frame` are
+    /// merged into one event carrying the final position and the summed `dx`/`dy`. Any other event
+    /// in between keeps the motions on either side apart, so ordering relative to clicks is kept.
+    /// Off by default, which delivers every intermediate position.
+    pub fn set_motion_coalescing(&mut self, enabled: bool) {
+        self.coalesce_motion = enabled;
     }
 
-    /// Return the current position of the mouse, relative to the top-left corner of the Window.
-    pub fn mouse_position(&self) -> (i32, i32) {
-        let mouse_state = self.event_pump.mouse_state();
-        (mouse_state.x(), mouse_state.y())
+    /// Return how many events have been merged or dropped because the event queue was over its
+    /// limit. If this number keeps rising, the program isn't keeping up with its input.
+    pub fn dropped_event_count(&self) -> usize {
+        self.dropped_events
     }
 
-    /// Use this Font for future calls to `print()`.
-    pub fn set_font(&mut self, font: Font) {
-        self.font = Some(font)
+    /// Record the keyboard and mouse state for the frame that is about to start. Every input query
+    /// made during the frame reads from this snapshot, so they all agree with each other.
+    fn take_input_snapshot(&mut self) {
+        let keys_down = self
+            .event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .collect();
+        self.keys_down_previous_frame = std::mem::replace(&mut self.keys_down, keys_down);
+        self.mouse_state = self.event_pump.mouse_state();
+        self.mouse_position = self
+            .point_mapping()
+            .to_drawing((self.mouse_state.x(), self.mouse_state.y()));
     }
 
-    /// This does not cause the program to exit immediately. It just means that next_frame
-    /// will return false on the next call.
-    pub fn quit(&mut self) {
-        self.running = false;
+    /// Return how a pixel of the Window maps to drawing coordinates. See `PointMapping`.
+    fn point_mapping(&self) -> PointMapping {
+        let scale = self.canvas.scale();
+        let (logical_width, logical_height) = self.canvas.logical_size();
+        if logical_width == 0 || logical_height == 0 {
+            return PointMapping {
+                bars: (0, 0),
+                scale,
+            };
+        }
+        // the design is centered the way SDL centers it
+        let (width, height) = self.drawable_size();
+        let bar = |size: u32, logical: u32, scale: f32| {
+            ((size as f64 - logical as f64 * scale as f64) / 2.0).floor() as i32
+        };
+        PointMapping {
+            bars: (
+                bar(width, logical_width, scale.0),
+                bar(height, logical_height, scale.1),
+            ),
+            scale,
+        }
     }
-}
 
-/// Drawing Methods
-/// ===============
-impl Window {
-    /// Windows have a color set on them at all times. This color is applied to every draw
-    /// operation. To "unset" the color, call set_color with (255,255,255,255)
-    pub fn set_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
-        self.foreground_color = pixels::Color::RGBA(red, green, blue, alpha);
+    /// Return true if the button is pressed. The keyboard is sampled once per frame in
+    /// `next_frame`, so every call during a frame gives the same answer.
+    pub fn is_key_down(&self, key: event::Key) -> bool {
+        self.keys_down.contains(&key)
     }
 
-    /// Set up the color according to the internal state of the Window.
-    fn prepare_to_draw(&mut self) {
-        self.canvas.set_draw_color(self.foreground_color);
+    /// Return true if a key that types what `key` types on a US keyboard is pressed, with the
+    /// user's keyboard layout. On a French keyboard `is_key_down_logical(Key::Z)` is true while
+    /// the key labelled Z is down, which is the one a US keyboard has W on. Like `is_key_down`,
+    /// this reads the per-frame snapshot.
+    pub fn is_key_down_logical(&self, key: event::Key) -> bool {
+        self.keys_down
+            .iter()
+            .any(|&physical| self.logical_key(physical) == Some(key))
     }
 
-    // These functions are just aliases onto self.canvas.as you can see.
-    pub fn draw_rect(&mut self, rect: shape::Rect) {
-        self.prepare_to_draw();
-        self.canvas.draw_rect(rect).unwrap();
-    }
-    pub fn fill_rect(&mut self, rect: shape::Rect) {
-        self.prepare_to_draw();
-        self.canvas.fill_rect(rect).unwrap();
-    }
-    pub fn draw_point(&mut self, point: shape::Point) {
-        self.prepare_to_draw();
-        self.canvas.draw_point(point).unwrap();
+    /// Return the logical key that the physical key `key` is with the current layout: the key that
+    /// types the same thing on a US keyboard. Return `None` if `key` types something a US keyboard
+    /// has no key for. Keys that don't type anything, like the arrows, are the same in both.
+    pub fn logical_key(&self, key: event::Key) -> Option<event::Key> {
+        event::key_for_keycode(keycode_of(key))
     }
-    pub fn draw_polygon(&mut self, polygon: shape::Polygon) {
-        self.prepare_to_draw();
-        self.canvas.draw_points(&polygon[..]).unwrap();
+
+    /// Return the physical key that types what `key` types on a US keyboard, with the current
+    /// layout, or `None` if the layout has no such key. This is the inverse of `logical_key`, and
+    /// is what a key prompt for a mnemonic shortcut should point at.
+    pub fn physical_key(&self, key: event::Key) -> Option<event::Key> {
+        // SAFETY: see keycode_of
+        let scancode = unsafe { sdl2::sys::SDL_GetScancodeFromKey(event::keycode_for_key(key)) };
+        event::Key::from_i32(scancode as i32)
     }
 
-    /// Display the image with its top-left corner at (x, y)
-    pub fn draw_image(&mut self, image: &mut Image, x: i32, y: i32) {
-        // first, configure the texture for drawing according to the current foreground_color
-        util::set_texture_color(&self.foreground_color, &mut image.texture);
+    /// Return true if the button went down since the previous frame: it is down now, but was up
+    /// during the previous frame.
+    pub fn is_key_pressed(&self, key: event::Key) -> bool {
+        self.keys_down.contains(&key) && !self.keys_down_previous_frame.contains(&key)
+    }
 
-        // copy the texture onto the drawer()
-        self.canvas
-            .copy(
-                &(image.texture),
-                Some(shape::Rect::new(
-                    x,
-                    y,
-                    image.get_width(),
-                    image.get_height(),
-                )),
-                None,
-            )
-            .unwrap();
+    /// Return when `key` was pressed, on the `elapsed_seconds` clock, each time it was pressed
+    /// among the events the last `begin_frame` (or `next_frame`) took, oldest first. Key repeat
+    /// isn't counted. A rhythm game uses this to score a hit by when it happened rather than by
+    /// which frame saw it.
+    ///
+    /// The times are SDL's event timestamps, which are whole milliseconds taken when SDL receives
+    /// the event from the OS. While `end_frame` waits for the framerate limit it has SDL receive
+    /// events every 3 milliseconds or so, so at 60 frames per second a press is usually placed
+    /// within about 3 ms of when it happened. Time spent drawing, or waiting for vsync in
+    /// `present`, isn't covered: presses during it are stamped when the wait is over.
+    pub fn key_press_times(&self, key: event::Key) -> Vec<f64> {
+        self.key_presses
+            .iter()
+            .filter(|&&(k, _)| k == key)
+            .map(|&(_, timestamp)| self.seconds_since_created(timestamp))
+            .collect()
     }
 
-    /// Write the text to the screen at (x, y) using the currently set font on the Window. Return a
-    /// Rectangle describing the area of the screen that was modified.
-    // TODO: Implement print_rect that wraps text to fit inside of a Rectangle.
-    pub fn print(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
-        self.prepare_to_draw();
-        let font = match self.font {
-            Some(ref mut r) => r,
+    /// Return how many seconds have passed since the Window was created, to the millisecond.
+    /// `key_press_times` is measured on this clock.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.ticks_since_created() as f64 / 1000.0
+    }
 
-            // FIXME: shouldn't be possible to have no font, and the `font` field on Window should
-            // be updated to reflect this.
-            None => panic!("no font set on window"),
-        };
-        util::set_texture_color(&self.foreground_color, &mut font.texture);
+    fn seconds_since_created(&self, ticks: u32) -> f64 {
+        ticks.wrapping_sub(self.created_ticks) as f64 / 1000.0
+    }
 
-        let mut current_x = x;
+    /// Return how many milliseconds have passed since the Window was created.
+    fn ticks_since_created(&self) -> u32 {
+        // SAFETY: SDL_GetTicks only reads the clock, and the timer subsystem outlives self.
+        // (TimerSubsystem::ticks takes &mut self for no reason.)
+        unsafe { sdl2::sys::SDL_GetTicks() }.wrapping_sub(self.created_ticks)
+    }
 
-        for ch in text.chars() {
-            let font_rect = match font.get_rect(ch) {
-                None => {
-                    // Our Font cannot represent the current character. Leave a little space.
-                    current_x += 5;
-                    continue;
-                }
-                Some(r) => r,
-            };
+    /// Return the character that `key` types with the current keyboard layout and no modifiers,
+    /// or `None` for keys that don't type one, like the arrows or Escape. `Key` names a position
+    /// on the keyboard, laid out as on a US keyboard, so on a German layout `key_to_char(Key::Y)`
+    /// is `'z'`. Use this to show key prompts that match the player's keyboard. Letters come back
+    /// in lower case.
+    pub fn key_to_char(&self, key: event::Key) -> Option<char> {
+        event::keycode_char(keycode_of(key))
+    }
 
-            let rect = shape::Rect::new(current_x, y, font_rect.width(), font_rect.height());
-            self.canvas
-                .copy(&(font.texture), Some(*font_rect), rect)
-                .unwrap();
+    /// Return true if the specified button is down. NOTE: Unknown mouse buttons are NOT handled
+    /// and will always return `false`. Like `is_key_down`, this reads the per-frame snapshot.
+    pub fn is_mouse_button_down(&self, button: event::MouseButton) -> bool {
+        self.mouse_state.is_mouse_button_pressed(button)
+    }
 
-            current_x += font_rect.width() as i32;
-        }
+    /// Return the position of the mouse at the start of the frame, relative to the top-left corner
+    /// of the Window, in the coordinates drawing happens in: with `enable_pixel_perfect` it is in
+    /// design pixels measured from the corner of the design, like the positions of mouse events,
+    /// and with `set_scale` it is divided by the scale. This ignores any viewport; see
+    /// `viewport_relative_mouse`.
+    pub fn mouse_position(&self) -> (i32, i32) {
+        self.mouse_position
+    }
 
-        shape::Rect::new(x, y, (current_x - x) as u32, font.get_height() as u32)
+    /// Return the mouse position relative to the top-left corner of `rect`, which is usually a
+    /// viewport passed to `set_viewport`. The position may be negative or beyond `rect` when the
+    /// mouse is outside it.
+    pub fn viewport_relative_mouse(&self, rect: shape::Rect) -> (i32, i32) {
+        let (x, y) = self.mouse_position();
+        (x.saturating_sub(rect.x()), y.saturating_sub(rect.y()))
     }
 
-    /// Clear the screen to black. Does not affect the current rendering color.
-    pub fn clear(&mut self) {
-        self.canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
-        self.canvas.clear();
+    /// Move the mouse pointer to (x, y), in the same coordinates as `mouse_position`: design pixels
+    /// with `enable_pixel_perfect`, divided by the scale with `set_scale`. Menus use this to put
+    /// the pointer on their default button. `mouse_position` reports the new position
+    /// straight away, and the move doesn't count as mouse motion: no `MouseMotion` event is
+    /// queued for it, and `mouse_velocity` doesn't see a jump. Nothing happens unless the Window
+    /// has input focus.
+    pub fn warp_mouse(&mut self, x: i32, y: i32) {
+        self.warped_at = Some(self.timer_subsystem.ticks());
+        let (x, y) = self.point_mapping().to_window((x, y));
+        self.sdl_context
+            .mouse()
+            .warp_mouse_in_window(self.canvas.window(), x, y);
+        self.mouse_state = self.event_pump.mouse_state();
+        self.mouse_position = self
+            .point_mapping()
+            .to_drawing((self.mouse_state.x(), self.mouse_state.y()));
+        self.mouse_samples.clear();
     }
 
-    /// Clear the screen to the color you specify.
-    pub fn clear_to_color(&mut self, r: u8, g: u8, b: u8) {
-        self.canvas.set_draw_color(pixels::Color::RGB(r, g, b));
-        self.canvas.clear();
+    /// Return true for the motion event caused by `warp_mouse`, which should be dropped.
+    fn is_warp_motion(&mut self, e: &sdl2::event::Event) -> bool {
+        match (e, self.warped_at) {
+            (&sdl2::event::Event::MouseMotion { timestamp, .. }, Some(at)) if timestamp >= at => {
+                self.warped_at = None;
+                true
+            }
+            _ => false,
+        }
     }
-}
 
-/**
- * Image represents a texture that can be drawn on the screen.
- *
- * Images are immutable, in the sense that they have no methods to modify their contents.
- */
-pub struct Image {
-    texture: render::Texture,
-    width: u32,
-    height: u32,
-}
+    /// Record where the mouse went, for `mouse_velocity` and `mouse_path_since_last_frame`.
+    fn track_mouse(&mut self, e: &sdl2::event::Event) {
+        if let sdl2::event::Event::MouseMotion {
+            timestamp, x, y, ..
+        } = *e
+        {
+            self.mouse_samples.push_back((timestamp, x, y));
+            self.mouse_path.push((x, y));
+        }
+        let now = self.timer_subsystem.ticks();
+        while let Some(&(t, _, _)) = self.mouse_samples.front() {
+            if now.saturating_sub(t) <= self.mouse_velocity_window {
+                break;
+            }
+            self.mouse_samples.pop_front();
+        }
+    }
 
-impl Image {
-    pub fn get_width(&self) -> u32 {
-        self.width
+    /// Return how fast the mouse was moving at the start of the frame, in pixels per second,
+    /// averaged over the preceding 50 milliseconds (see `set_mouse_velocity_window`). This reads
+    /// zero once the mouse has been still for that long.
+    pub fn mouse_velocity(&self) -> (f32, f32) {
+        if let Some(velocity) = self.played_mouse_velocity {
+            return velocity;
+        }
+        event::mouse_velocity(
+            self.mouse_samples.iter().copied(),
+            self.ticks_at_previous_frame,
+            self.mouse_velocity_window,
+        )
     }
-    pub fn get_height(&self) -> u32 {
-        self.height
+
+    /// Set how many milliseconds of movement `mouse_velocity` averages over. Longer is smoother;
+    /// shorter reacts more quickly.
+    pub fn set_mouse_velocity_window(&mut self, ms: u32) {
+        self.mouse_velocity_window = ms;
     }
-}
 
-/**
- * Font is a way to render text, loaded from a specially formatted image.
- *
- * Note that Font is not loaded from a TrueType file, but instead, from a specially formatted
- * image. Loading from an image is a little faster and a little simpler and a little more portable,
- * but has a couple disadvantages. For one, the font size is fixed by the file. To have two
- * different font sizes, you have to create two different Fonts from two different files. Another
- * disadvantage is that these special images are less widely available.
- *
- * This link describes how ImageFonts work: https://love2d.org/wiki/Tutorial:Fonts_and_Text
- */
-pub struct Font {
-    texture: render::Texture,
-    chars: HashMap<char, shape::Rect>,
-    height: u32,
-}
+    /// Return every position the mouse was reported at during the last `next_frame`, oldest first.
+    /// Paint programs can join these up to draw unbroken strokes, even when the mouse moves many
+    /// pixels per frame. The list is empty if the mouse didn't move.
+    pub fn mouse_path_since_last_frame(&self) -> &[(i32, i32)] {
+        &self.mouse_path
+    }
 
-impl Font {
-    /// Determine whether "ch" exists in this Font.
-    pub fn is_printable(&self, ch: char) -> bool {
-        self.chars.contains_key(&ch)
+    /// Return true if the mouse is currently over this Window. Returns false when the cursor has
+    /// left the Window or another window has mouse focus.
+    pub fn mouse_in_window(&self) -> bool {
+        self.sdl_context.mouse().focused_window_id() == Some(self.canvas.window().id())
     }
 
-    /// Return the number of printable characters that the Font contains.
-    pub fn len(&self) -> usize {
-        self.chars.len()
+    /// Start sending `TextInput` and `TextEditing` events, and show the on-screen keyboard on
+    /// platforms that have one. On desktop platforms SDL starts text input when the Window is
+    /// created, so this is only needed after `stop_text_input`.
+    pub fn start_text_input(&mut self) {
+        self.sdl_context.video().unwrap().text_input().start();
     }
 
-    /// Returns `true` if the `chars` contains no elements.
-    pub fn is_empty(&self) -> bool {
-        self.chars.is_empty()
+    /// Stop sending `TextInput` and `TextEditing` events, so that keys only arrive as `Keyboard`
+    /// events and an input method doesn't pop up while the game is being played.
+    pub fn stop_text_input(&mut self) {
+        self.sdl_context.video().unwrap().text_input().stop();
     }
 
-    /// Return the height of the Font. This is constant for every possible character, while the
-    /// individual character widths vary. Note that certain characters (such a single quote `'`)
-    /// might not actually take up all of `height`. However, no character may exceed this limit.
-    pub fn get_height(&self) -> u32 {
-        self.height
+    /// Return true if `TextInput` and `TextEditing` events are being sent.
+    pub fn is_text_input_active(&self) -> bool {
+        self.sdl_context.video().unwrap().text_input().is_active()
+    }
+
+    /// Tell the input method where the text being typed is, in Window coordinates, so that the
+    /// candidate list it shows while composing appears next to it instead of in a corner of the
+    /// screen. Call this when a text field gains focus, with the field's rectangle.
+    pub fn set_text_input_rect(&mut self, rect: shape::Rect) {
+        self.sdl_context
+            .video()
+            .unwrap()
+            .text_input()
+            .set_rect(rect);
+    }
+
+    /// Open controllers as they are plugged in and forget them when they are unplugged. SDL reports
+    /// controllers that were already connected at startup as being plugged in, too. Return the
+    /// event the application should see, if any.
+    fn track_controllers(&mut self, e: &sdl2::event::Event) -> Option<Event> {
+        match *e {
+            sdl2::event::Event::ControllerDeviceAdded { which, .. } => {
+                let controller = self.controller_subsystem.as_ref()?.open(which).ok()?;
+                let id = controller.instance_id() as u32;
+                self.controllers.insert(id, controller);
+                Some(Event::ControllerAdded { id })
+            }
+            sdl2::event::Event::ControllerDeviceRemoved { which, .. } => {
+                let id = which as u32;
+                self.controllers.remove(&id)?;
+                Some(Event::ControllerRemoved { id })
+            }
+            _ => None,
+        }
+    }
+
+    /// Return every connected controller. The list follows controllers being plugged in and
+    /// unplugged, which is also reported through `Event::ControllerAdded` and
+    /// `Event::ControllerRemoved`.
+    pub fn controllers(&self) -> Vec<ControllerInfo> {
+        let mut controllers: Vec<ControllerInfo> = self
+            .controllers
+            .iter()
+            .map(|(&id, controller)| ControllerInfo {
+                id,
+                name: controller.name(),
+                is_attached: controller.attached(),
+            })
+            .collect();
+        controllers.sort_by_key(|c| c.id);
+        controllers
+    }
+
+    /// Start the controller's rumble motors at the given strengths (0 to 65535), stopping after
+    /// `duration_ms` milliseconds. `controller_id` is the instance id SDL gave the controller.
+    ///
+    /// Controllers without rumble support silently do nothing and return Ok. An error is only
+    /// returned if no connected controller has that id.
+    pub fn rumble(
+        &mut self,
+        controller_id: u32,
+        low_freq: u16,
+        high_freq: u16,
+        duration_ms: u32,
+    ) -> Result<(), SimpleError> {
+        match self.controllers.get_mut(&controller_id) {
+            Some(controller) => {
+                // SDL reports "not supported" as an error; that counts as a successful no-op
+                let _ = controller.set_rumble(low_freq, high_freq, duration_ms);
+                Ok(())
+            }
+            None => Err(SimpleError::InvalidArgument(format!(
+                "no controller with id {}",
+                controller_id
+            ))),
+        }
+    }
+
+    /// Use this Font for future calls to `print()`. A Font made by another Window is given to
+    /// this one first, as `adopt_font` does; if that fails the current font is kept.
+    pub fn set_font(&mut self, mut font: Font) {
+        if self.adopt_font(&mut font).is_ok() {
+            self.font = Some(font)
+        }
+    }
+
+    /// This does not cause the program to exit immediately. It just means that next_frame (or
+    /// end_frame) will return false on the next call.
+    pub fn quit(&mut self) {
+        self.running = false;
+    }
+
+    /// Return the number of milliseconds the previous frame took, measured between the last two
+    /// calls to `next_frame` or `end_frame`.
+    pub fn frame_time(&self) -> u32 {
+        self.ticks_in_previous_frame
+    }
+
+    /// Return how many frames have been shown: how many times `next_frame` or `end_frame` has
+    /// returned true. Once the Window has quit it stops counting.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Return true when `frame_count` is a multiple of `n`, so once every `n` frames, starting
+    /// before the first frame is shown, for work that doesn't need doing every frame. An `n` of
+    /// 0 counts as 1.
+    pub fn every_n_frames(&self, n: u64) -> bool {
+        self.frame_count.is_multiple_of(n.max(1))
+    }
+
+    /// Return true in the first frame after each multiple of `period_ms` milliseconds since the
+    /// Window was created, so at most once per period, for work such as autosaving. Time is the
+    /// sum of the `frame_time` of every frame shown, so the answer stays the same for the whole
+    /// frame, a recording plays back on the times it was recorded with, and a frame long enough to
+    /// span several periods still counts once. It is false before the first frame has been shown.
+    /// A `period_ms` of 0 is true every frame.
+    pub fn every_millis(&self, period_ms: u32) -> bool {
+        if self.frame_count == 0 {
+            return false;
+        }
+        let now = self.frame_clock;
+        crossed_period(
+            now.saturating_sub(self.ticks_in_previous_frame),
+            now,
+            period_ms,
+        )
+    }
+
+    /// Call `f` from `next_frame` (or `end_frame`) with the frame's `frame_time` whenever a frame
+    /// takes longer than `threshold_ms` milliseconds, to find stutters without timing the loop
+    /// by hand. The first frame, which includes whatever the program did before it, isn't
+    /// reported. Setting a callback replaces the previous one; there is none by default.
+    pub fn on_slow_frame(&mut self, threshold_ms: u32, f: impl FnMut(u32) + 'static) {
+        self.slow_frame_hook = Some((threshold_ms, Box::new(f)));
+    }
+
+    /// Stop calling the callback set with `on_slow_frame`.
+    pub fn clear_slow_frame_callback(&mut self) {
+        self.slow_frame_hook = None;
+    }
+
+    /// Return how much drawing the previous frame did: the calls to the renderer made between the
+    /// last two times a frame was shown, layers and frame hooks included and the debug overlay
+    /// left out. Compare frames to find out whether a slow one issued more draw calls or just
+    /// more expensive ones.
+    pub fn draw_stats(&self) -> DrawStats {
+        self.previous_draw_stats
+    }
+
+    /// Return how much drawing this frame has done so far. Calls recorded on layers are counted
+    /// when they are drawn, so a frame hook, which runs after the layers, sees everything the
+    /// application drew this frame.
+    pub fn current_draw_stats(&self) -> DrawStats {
+        self.layers.stats
+    }
+
+    /// Return the value of a high-resolution counter, for timing code more precisely than the
+    /// milliseconds of `frame_time` allow. It counts `performance_frequency` times per second
+    /// from an arbitrary starting point, so only the difference between two readings means
+    /// anything: divide it by the frequency to get seconds.
+    pub fn performance_counter(&self) -> u64 {
+        self.timer_subsystem.performance_counter()
+    }
+
+    /// Return how many times per second `performance_counter` counts. This doesn't change while
+    /// the program runs.
+    pub fn performance_frequency(&self) -> u64 {
+        self.timer_subsystem.performance_frequency()
+    }
+
+    /// Return the current framerate, derived from the duration of the previous frame. Returns 0
+    /// before the first frame has completed.
+    pub fn fps(&self) -> f32 {
+        if self.ticks_in_previous_frame == 0 {
+            return 0.0;
+        }
+        1000.0 / self.ticks_in_previous_frame as f32
+    }
+
+    /// When enabled, `next_frame` draws the FPS, frame time, event queue length and the previous
+    /// frame's `draw_stats` in the top-left corner using the default font, right before
+    /// presenting. The foreground color and font set on the Window are left untouched.
+    pub fn set_debug_overlay(&mut self, on: bool) {
+        if on && self.debug_font.is_none() {
+            let font = self
+                .load_font(DEFAULT_FONT_BYTES, DEFAULT_FONT_STR.to_string())
+                .unwrap();
+            self.debug_font = Some(font);
+        }
+        self.debug_overlay = on;
+    }
+
+    /// Finish drawing the frame and show it: replay the layers, run the frame hooks, and draw the
+    /// debug overlay.
+    fn present_frame(&mut self) {
+        self.flush_layers();
+        self.run_frame_hooks();
+        self.flush_batch();
+        let (clip, scale) = (self.canvas.clip_rect(), self.canvas.scale());
+        if let Some(frame) = &self.retained_frame {
+            set_render_target(&mut self.canvas, None);
+            self.canvas.set_viewport(None);
+            self.canvas.set_clip_rect(None);
+            let _ = self.canvas.set_scale(1.0, 1.0);
+            self.canvas.copy(frame, None, None).unwrap();
+            self.layers.stats.texture_copies += 1;
+        }
+        // the console and the overlay aren't part of what the application drew
+        let stats = self.layers.stats;
+        #[cfg(any(debug_assertions, feature = "console"))]
+        if self.console.is_open() {
+            self.draw_console();
+        }
+        if self.debug_overlay {
+            self.draw_debug_overlay();
+        }
+        self.flush_batch();
+        self.layers.stats = stats;
+        self.canvas.present();
+        // SAFETY: the layers and the batch have all been drawn, so no draw refers to a dropped
+        // texture
+        unsafe { self.textures.destroy_dropped() };
+        self.previous_draw_stats = self.layers.take_stats();
+        if let Some(frame) = &self.retained_frame {
+            let query = frame.query();
+            if (query.width, query.height) == self.drawable_size() {
+                self.target_retained_frame(clip, scale);
+                self.dirty_rects.clear();
+            } else if self.retain_frame(clip, scale).is_err() {
+                // can't make a frame of the new size: go back to drawing straight to the screen
+                self.drop_retained_frame(clip, scale);
+            }
+        }
+        if self.auto_clear {
+            self.fill_background(self.clear_color);
+        }
+    }
+
+    /// Draw the debug overlay on top of everything else drawn this frame.
+    fn draw_debug_overlay(&mut self) {
+        let stats = self.previous_draw_stats;
+        let text = format!(
+            "fps {:.1} frame {}ms events {} draws {} copies {} glyphs {} state {}",
+            self.fps(),
+            self.frame_time(),
+            self.event_queue.len(),
+            stats.primitives,
+            stats.texture_copies,
+            stats.glyphs,
+            stats.state_changes
+        );
+
+        self.with_debug_state(|window| {
+            window.set_color(255, 255, 0, 255);
+            window.print(&text, 4, 4);
+        });
+    }
+
+    /// Return the debug console: log to it, and register the commands it can run. See the
+    /// `console` module. It isn't compiled into release builds unless the `console` feature is on.
+    #[cfg(any(debug_assertions, feature = "console"))]
+    pub fn console(&mut self) -> &mut console::Console {
+        self.console.collect_pending();
+        &mut self.console
+    }
+
+    /// Draw the console over the top half of the frame: as much of the log as fits, above the
+    /// command line and its cursor.
+    #[cfg(any(debug_assertions, feature = "console"))]
+    fn draw_console(&mut self) {
+        let (width, height) = self.drawing_size();
+        self.with_debug_state(|window| {
+            let line_height = current_font(&window.font).get_height().max(1) as i32;
+            let fit = (height as i32 / 2 / line_height - 1).max(0) as usize;
+            let skip = window.console.lines().count().saturating_sub(fit);
+            let mut lines: Vec<String> = window
+                .console
+                .lines()
+                .skip(skip)
+                .map(String::from)
+                .collect();
+            lines.push(format!("> {}_", window.console.input()));
+
+            let panel_height = lines.len() as u32 * line_height as u32 + 8;
+            let blend = window.canvas.blend_mode();
+            window.set_blend_mode(BlendMode::Blend);
+            window.set_color(0, 0, 0, 192);
+            window.fill_rect(shape::Rect::new(0, 0, width, panel_height));
+            window.set_blend_mode(blend);
+
+            window.set_color(255, 255, 255, 255);
+            for (i, line) in lines.iter().enumerate() {
+                window.print(line, 4, 4 + i as i32 * line_height);
+            }
+        });
     }
 
-    /// Return the portion of the Font's texture that is used to draw the `char` you provide. If
-    /// the character can't be drawn by this Font, return None.
-    fn get_rect(&self, ch: char) -> Option<&shape::Rect> {
-        self.chars.get(&ch)
+    /// Call `f` with the default font, no draw offset and immediate drawing, then put the user's
+    /// font, color, offset and layer back. The debug overlay and the console draw this way.
+    fn with_debug_state(&mut self, f: impl FnOnce(&mut Self)) {
+        if self.debug_font.is_none() {
+            let font = self
+                .load_font(DEFAULT_FONT_BYTES, DEFAULT_FONT_STR.to_string())
+                .unwrap();
+            self.debug_font = Some(font);
+        }
+        let (saved_color, saved_offset) = (self.foreground_color, self.draw_offset);
+        let saved_font = std::mem::replace(&mut self.font, self.debug_font.take());
+        let saved_layer = self.layers.pause();
+        self.draw_offset = (0, 0);
+
+        f(self);
+
+        self.layers.resume(saved_layer);
+        self.debug_font = std::mem::replace(&mut self.font, saved_font);
+        self.foreground_color = saved_color;
+        self.draw_offset = saved_offset;
     }
 }
 
-/// This is the default font.
-const DEFAULT_FONT_BYTES: &[u8] = include_bytes!("default_font.png");
-const DEFAULT_FONT_STR: &str =
-    " abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,!?-+/():;%&`'*#=[]\"";
+impl Drop for Window {
+    fn drop(&mut self) {
+        // the renderer is about to destroy every texture it made, and the fonts that are still
+        // kept elsewhere have their pixels read back first
+        self.font = None;
+        self.textures.close();
+    }
+}
 
-/// Resource Loading Methods
-/// ========================
+/// Drawing Methods
+/// ===============
 impl Window {
-    /// Load the image at the path you specify.
-    pub fn load_image_from_file(&self, filename: &Path) -> Result<Image, String> {
-        let mut texture = self.canvas.texture_creator().load_texture(filename)?;
-        texture.set_blend_mode(render::BlendMode::Blend);
-        Ok(Image {
-            width: texture.query().width,
-            height: texture.query().height,
-            texture,
-        })
+    /// Windows have a color set on them at all times. This color is applied to every draw
+    /// operation. To "unset" the color, call set_color with (255,255,255,255)
+    pub fn set_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
+        self.foreground_color = pixels::Color::RGBA(red, green, blue, alpha);
     }
 
-    /// Load an image from a slice of bytes. This function is particularly powerful when
-    /// used in conjunction with the `include_bytes` macro that embeds data in the compiled
-    /// executable. In this way, you can pack all of your game data into your executable.
-    pub fn load_image(&self, data: &[u8]) -> Result<Image, String> {
-        let rwops = rwops::RWops::from_bytes(data)?;
-        let surf: surface::Surface = rwops.load()?;
-        let mut texture = match self
-            .canvas
-            .texture_creator()
-            .create_texture_from_surface(&surf)
-        {
-            Ok(t) => t,
-            Err(e) => return Err(e.to_string()),
+    /// Return the area that drawing can currently affect, in drawing coordinates: the viewport,
+    /// narrowed down to the clip rectangle if one is set.
+    fn visible_area(&self) -> shape::Rect {
+        let viewport = self.canvas.viewport();
+        let viewport = shape::Rect::new(0, 0, viewport.width(), viewport.height());
+        match self.canvas.clip_rect() {
+            Some(clip) => clip.intersection(viewport).unwrap_or(clip),
+            None => viewport,
+        }
+    }
+
+    /// Draw only inside `rect`, or pass `None` to draw on the whole Window again. While a viewport
+    /// is set, every drawing method measures its coordinates from the top-left corner of the
+    /// viewport and nothing is drawn outside it. This is what split-screen games use to give each
+    /// player their own part of the Window.
+    ///
+    /// The clip rect (see `set_clip_rect`) is applied within the viewport: its coordinates are
+    /// relative to the viewport too, and drawing is limited to the area both of them cover.
+    /// Changing the viewport leaves the clip rect as it is.
+    pub fn set_viewport(&mut self, rect: Option<shape::Rect>) {
+        self.flush_batch();
+        self.viewport = rect;
+        self.canvas.set_viewport(rect);
+    }
+
+    /// Return the viewport set with `set_viewport`, if any.
+    pub fn viewport(&self) -> Option<shape::Rect> {
+        self.viewport
+    }
+
+    /// Limit drawing to `rect`, measured from the top-left corner of the viewport, or pass `None`
+    /// to draw on the whole viewport again.
+    pub fn set_clip_rect(&mut self, rect: Option<shape::Rect>) {
+        self.flush_batch();
+        self.canvas.set_clip_rect(rect);
+    }
+
+    /// Set the viewport to `rect`, call `f`, then put back the viewport and clip rect that were
+    /// set before, so `f` is free to change either.
+    pub fn with_viewport<F: FnOnce(&mut Window)>(&mut self, rect: shape::Rect, f: F) {
+        let (viewport, clip) = (self.viewport, self.canvas.clip_rect());
+        self.set_viewport(Some(rect));
+        f(self);
+        self.set_viewport(viewport);
+        self.set_clip_rect(clip);
+    }
+
+    /// Move everything that is drawn afterwards by `(x, y)`. A scrolling game sets this to minus
+    /// the position of its camera, then draws the world in world coordinates; `camera::Follow`
+    /// does exactly that. The offset is applied inside the viewport and doesn't move the viewport
+    /// or the clip rect. Reset it to `(0, 0)` before drawing a HUD.
+    pub fn set_draw_offset(&mut self, x: i32, y: i32) {
+        self.draw_offset = (x, y);
+    }
+
+    /// Return the offset set with `set_draw_offset`.
+    pub fn draw_offset(&self) -> (i32, i32) {
+        self.draw_offset
+    }
+
+    /// Move `rect` by the draw offset.
+    fn offset_rect(&self, mut rect: shape::Rect) -> shape::Rect {
+        rect.offset(self.draw_offset.0, self.draw_offset.1);
+        rect
+    }
+
+    /// Set how rectangles, points, polygons and images are combined with what is already drawn.
+    /// The default is `BlendMode::Blend`, which makes the alpha of the draw color act as
+    /// transparency. An Image with a blend mode of its own (see `Image::set_blend_mode`) keeps it,
+    /// and text is always blended normally.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.flush_batch();
+        self.layers.set_blend_mode(&mut self.canvas, mode);
+    }
+
+    /// Scale everything that is drawn afterwards by `x` horizontally and `y` vertically. Returns
+    /// an error if SDL refuses the scale.
+    pub fn set_scale(&mut self, x: f32, y: f32) -> Result<(), SimpleError> {
+        self.flush_batch();
+        self.canvas.set_scale(x, y).map_err(SimpleError::Sdl)
+    }
+
+    /// Set the Window up for pixel art drawn at `design_width` by `design_height` pixels.
+    /// Everything is drawn in design pixels, and the whole design is scaled up by the largest whole
+    /// number that fits the Window, with black bars around it, so every design pixel is the same
+    /// size on screen. Images loaded afterwards use nearest-pixel filtering. Mouse positions are
+    /// reported in design pixels too, by `mouse_position` as by mouse events.
+    ///
+    /// The draw offset is a whole number of design pixels, so a camera such as
+    /// `camera::Follow` that sets it moves the world one design pixel at a time, without
+    /// shimmering. Call this once, right after creating the Window; calling it again with the
+    /// same size changes nothing.
+    pub fn enable_pixel_perfect(
+        &mut self,
+        design_width: u32,
+        design_height: u32,
+    ) -> Result<(), SimpleError> {
+        self.flush_batch();
+        sdl2::hint::set(
+            "SDL_RENDER_SCALE_QUALITY",
+            ScaleQuality::Nearest.hint_value(),
+        );
+        self.canvas
+            .set_logical_size(design_width, design_height)
+            .map_err(|e| SimpleError::Sdl(e.to_string()))?;
+        // SAFETY: the renderer is alive for as long as the canvas is
+        let status = unsafe {
+            sdl2::sys::SDL_RenderSetIntegerScale(self.canvas.raw(), sdl2::sys::SDL_bool::SDL_TRUE)
         };
-        texture.set_blend_mode(render::BlendMode::Blend);
-        Ok(Image {
-            width: texture.query().width,
-            height: texture.query().height,
-            texture,
-        })
+        if status != 0 {
+            return Err(SimpleError::Sdl(sdl2::get_error()));
+        }
+        Ok(())
     }
 
-    // TODO: Split this out so it can be tested.
+    /// Return the size of the area drawing happens in: the viewport if one is set, or else the
+    /// design size set with `enable_pixel_perfect`, or else the whole Window.
+    fn drawing_size(&self) -> (u32, u32) {
+        if let Some(viewport) = self.viewport {
+            return (viewport.width(), viewport.height());
+        }
+        match self.canvas.logical_size() {
+            (0, _) | (_, 0) => self.drawable_size(),
+            size => size,
+        }
+    }
 
-    /// Parse a font from the Surface, using the string as a guideline.
-    fn parse_image_font(&self, surf: surface::Surface, string: String) -> Result<Font, String> {
-        if util::string_has_duplicate_chars(string.clone()) {
-            return Err("image font string has duplicate characters".to_string());
-        }
-
-        let surf = surf;
-        let mut chars: HashMap<char, shape::Rect> = HashMap::new();
-
-        let surf_width = surf.width();
-        let surf_height = surf.height();
-        let mut current_rect: Option<shape::Rect> = None;
-
-        surf.with_lock(|pixels| {
-            // `pixels` is an array of [u8; width * height]
-            let border_color = pixels[0];
-
-            // Move through the surface and divide it into rectangles according to the color of the
-            // topmost pixel.
-            for (i, pixel) in pixels.iter().enumerate().take(surf_width as usize) {
-                if *pixel == border_color {
-                    if let Some(mut rect) = current_rect {
-                        let c = match string.chars().nth(chars.len()) {
-                            Some(c) => c,
-                            None => {
-                                // Out of characters to add to the hashmap, so just return with
-                                // what have parsed so far.
-                                return;
-                            }
-                        };
-                        rect = shape::Rect::new(
-                            rect.x(),
-                            rect.y(),
-                            (i as i32 - rect.x()) as u32,
-                            rect.height(),
-                        );
-                        chars.insert(c, rect);
-                        current_rect = None;
-                    }
-                } else if current_rect.is_none() {
-                    current_rect = Some(shape::Rect::new(i as i32, 0, 1, surf_height));
-                }
-            }
+    /// Remember the draw color, viewport, draw offset, clip rect, blend mode and scale, so that
+    /// they can be put back with `restore_state`. Calls can be nested: each `restore_state` undoes
+    /// the changes since the matching `save_state`. This lets a drawing routine change whatever it
+    /// needs without its caller having to know.
+    pub fn save_state(&mut self) {
+        self.saved_states.push(RenderState {
+            foreground_color: self.foreground_color,
+            viewport: self.viewport,
+            draw_offset: self.draw_offset,
+            clip_rect: self.canvas.clip_rect(),
+            blend_mode: self.canvas.blend_mode(),
+            scale: self.canvas.scale(),
         });
+    }
 
-        let mut texture = match self
-            .canvas
-            .texture_creator()
-            .create_texture_from_surface(&surf)
+    /// Put back the state remembered by the most recent `save_state`. Does nothing if there is no
+    /// saved state left.
+    pub fn restore_state(&mut self) {
+        let state = match self.saved_states.pop() {
+            Some(state) => state,
+            None => return,
+        };
+        self.foreground_color = state.foreground_color;
+        self.set_viewport(state.viewport);
+        self.draw_offset = state.draw_offset;
+        self.set_clip_rect(state.clip_rect);
+        self.flush_batch();
+        self.layers
+            .set_blend_mode(&mut self.canvas, state.blend_mode);
+        // this scale was accepted before, so it will be again
+        let _ = self.canvas.set_scale(state.scale.0, state.scale.1);
+    }
+
+    /// Draw the lines of a grid of `cell_width` by `cell_height` cells over `bounds`, in the
+    /// current color. The first lines lie on the top and left edges of `bounds`. When a side of
+    /// `bounds` is an exact multiple of the cell size, the last line lies on its bottom or right
+    /// edge, exactly where `draw_rect(bounds)` would draw it. The grid moves with the draw offset,
+    /// so it scrolls with the world in an editor.
+    ///
+    /// Returns an error if either cell size is zero.
+    pub fn draw_grid(
+        &mut self,
+        bounds: shape::Rect,
+        cell_width: u32,
+        cell_height: u32,
+    ) -> Result<(), SimpleError> {
+        let color = self.foreground_color;
+        self.draw_grid_lines(bounds, cell_width, cell_height, 0, color)
+    }
+
+    /// Like `draw_grid`, but every `major_every`th line, counting from the top and left edges, is
+    /// drawn in `major_color` (red, green, blue, alpha) instead. Graph paper uses this to mark out
+    /// larger squares.
+    pub fn draw_grid_with_major(
+        &mut self,
+        bounds: shape::Rect,
+        cell_width: u32,
+        cell_height: u32,
+        major_every: u32,
+        major_color: (u8, u8, u8, u8),
+    ) -> Result<(), SimpleError> {
+        if major_every == 0 {
+            return Err(SimpleError::InvalidArgument(
+                "grid major line interval must not be zero".to_string(),
+            ));
+        }
+        let (r, g, b, a) = major_color;
+        let major_color = pixels::Color::RGBA(r, g, b, a);
+        self.draw_grid_lines(bounds, cell_width, cell_height, major_every, major_color)
+    }
+
+    fn draw_grid_lines(
+        &mut self,
+        bounds: shape::Rect,
+        cell_width: u32,
+        cell_height: u32,
+        major_every: u32,
+        major_color: pixels::Color,
+    ) -> Result<(), SimpleError> {
+        if cell_width == 0 || cell_height == 0 {
+            return Err(SimpleError::InvalidArgument(format!(
+                "grid cells must not be empty, got {}x{}",
+                cell_width, cell_height
+            )));
+        }
+        let bounds = self.offset_rect(bounds);
+        let color_of = |i: usize| {
+            if major_every != 0 && i.is_multiple_of(major_every as usize) {
+                major_color
+            } else {
+                self.foreground_color
+            }
+        };
+
+        let mut lines = vec![];
+        for (i, x) in util::grid_lines(bounds.width(), cell_width)
+            .into_iter()
+            .enumerate()
         {
-            Ok(t) => t,
-            Err(e) => return Err(e.to_string()),
+            let line = shape::Rect::new(bounds.x() + x as i32, bounds.y(), 1, bounds.height());
+            lines.push((line, color_of(i)));
+        }
+        for (i, y) in util::grid_lines(bounds.height(), cell_height)
+            .into_iter()
+            .enumerate()
+        {
+            let line = shape::Rect::new(bounds.x(), bounds.y() + y as i32, bounds.width(), 1);
+            lines.push((line, color_of(i)));
+        }
+        for (line, color) in lines {
+            self.layers.submit(
+                &mut self.canvas,
+                layer::Draw::FillRect(line),
+                color,
+                self.viewport,
+            );
+        }
+        Ok(())
+    }
+
+    /// Fill `rect` with a checkerboard of `cell` by `cell` squares in two colors, each given as
+    /// `(red, green, blue, alpha)`. The top-left square gets `color_a`. The draw color is left as
+    /// it was.
+    pub fn fill_rect_checker(
+        &mut self,
+        rect: shape::Rect,
+        cell: u32,
+        color_a: (u8, u8, u8, u8),
+        color_b: (u8, u8, u8, u8),
+    ) {
+        let colors = [color_a, color_b].map(|(r, g, b, a)| pixels::Color::RGBA(r, g, b, a));
+        for (square, odd) in util::checker_cells(self.offset_rect(rect), cell) {
+            self.layers.submit(
+                &mut self.canvas,
+                layer::Draw::FillRect(square),
+                colors[odd as usize],
+                self.viewport,
+            );
+        }
+    }
+
+    /// Draw to the layer with this `index` from now on. Layers let a program draw, say, its HUD
+    /// from the same code as the world, without worrying about which is drawn first: draw calls
+    /// are recorded instead of executed, and `next_frame` draws every layer in ascending order
+    /// of `index`, each in the order its calls were made, before presenting.
+    ///
+    /// Until this is called for the first time, drawing happens immediately and nothing is
+    /// recorded. Recorded calls keep the draw color, draw offset, viewport and clip rect they were
+    /// made with. Blend mode and scale are the ones in effect when the layers are drawn, and
+    /// `clear` always happens immediately.
+    ///
+    /// The buffers that hold recorded calls are emptied every frame but keep their memory, so a
+    /// program that draws about the same amount every frame stops allocating after the first
+    /// few frames.
+    pub fn layer(&mut self, index: i32) {
+        self.layers.select(index);
+    }
+
+    /// Return the TextureId to draw `texture` with, or `None` if another Window made it, so this
+    /// one can't draw it.
+    fn texture_id(&self, texture: &texture::Texture) -> Option<layer::TextureId> {
+        texture
+            .belongs_to(&self.textures)
+            .then(|| layer::TextureId::of(texture))
+    }
+
+    /// Draw, or record, one operation with the current color.
+    fn submit(&mut self, draw: layer::Draw) {
+        self.layers
+            .submit(&mut self.canvas, draw, self.foreground_color, self.viewport);
+    }
+
+    /// Draw the rectangles and points waiting to be drawn together. See `set_batching`.
+    fn flush_batch(&mut self) {
+        self.layers.flush_batch(&mut self.canvas);
+    }
+
+    /// Choose whether rectangles and points are batched, which is on by default. With batching
+    /// on, rectangles, rectangle outlines or points drawn one after another in the same color
+    /// are held back and drawn with a single call to SDL, which makes scenes built from many
+    /// small tiles much faster to draw; `examples/batch-benchmark.rs` measures the difference.
+    ///
+    /// Batching never changes what ends up on the screen: the batch is drawn as soon as something
+    /// else is drawn, the viewport, clip rect, blend mode or scale changes, the pixels are read,
+    /// the canvas is borrowed with `canvas_mut` or the frame ends. Turn it off only for code that
+    /// draws on the renderer through SDL directly between simple's draw calls.
+    pub fn set_batching(&mut self, on: bool) {
+        self.flush_batch();
+        self.layers.immediate = !on;
+    }
+
+    /// Draw everything recorded on layers, leaving the canvas state as it was.
+    fn flush_layers(&mut self) {
+        let clip_rect = self.canvas.clip_rect();
+        self.layers
+            .flush(&mut self.canvas, self.viewport, clip_rect);
+    }
+
+    pub fn draw_rect(&mut self, rect: shape::Rect) {
+        let rect = self.offset_rect(rect);
+        self.submit(layer::Draw::Rect(rect));
+    }
+    pub fn fill_rect(&mut self, rect: shape::Rect) {
+        let rect = self.offset_rect(rect);
+        self.submit(layer::Draw::FillRect(rect));
+    }
+    pub fn draw_point(&mut self, point: shape::Point) {
+        let (dx, dy) = self.draw_offset;
+        self.submit(layer::Draw::Points(vec![point.offset(dx, dy)]));
+    }
+    pub fn draw_polygon(&mut self, polygon: shape::Polygon) {
+        let (dx, dy) = self.draw_offset;
+        let points = polygon.iter().map(|p| p.offset(dx, dy)).collect();
+        self.submit(layer::Draw::Points(points));
+    }
+
+    // The same again, but in `color` (red, green, blue, alpha). The Window's color is left as it
+    // was.
+    pub fn draw_rect_color(&mut self, rect: shape::Rect, color: (u8, u8, u8, u8)) {
+        self.with_color(color, |w| w.draw_rect(rect));
+    }
+    pub fn fill_rect_color(&mut self, rect: shape::Rect, color: (u8, u8, u8, u8)) {
+        self.with_color(color, |w| w.fill_rect(rect));
+    }
+    pub fn draw_point_color(&mut self, point: shape::Point, color: (u8, u8, u8, u8)) {
+        self.with_color(color, |w| w.draw_point(point));
+    }
+    pub fn draw_polygon_color(&mut self, polygon: shape::Polygon, color: (u8, u8, u8, u8)) {
+        self.with_color(color, |w| w.draw_polygon(polygon));
+    }
+
+    /// Call `f` with the Window's color set to `color`, then put the previous color back.
+    fn with_color<F: FnOnce(&mut Window)>(&mut self, color: (u8, u8, u8, u8), f: F) {
+        let saved = self.foreground_color;
+        self.set_color(color.0, color.1, color.2, color.3);
+        f(self);
+        self.foreground_color = saved;
+    }
+
+    /// Display the image with its top-left corner at (x, y), tinted with the Window's color. The
+    /// tint is applied to the texture when the draw is carried out, so the Image itself is never
+    /// changed and can be drawn from a shared reference.
+    pub fn draw_image(&mut self, image: &Image, x: i32, y: i32) {
+        self.draw_image_with_color(image, x, y, self.foreground_color);
+    }
+
+    /// Like `draw_image`, but tint the image with `tint`, given as `(red, green, blue, alpha)`,
+    /// instead of the Window's color. The Window's color is left alone, so flashing a sprite or
+    /// giving it a team color doesn't affect anything else that is drawn.
+    pub fn draw_image_tinted(&mut self, image: &Image, x: i32, y: i32, tint: (u8, u8, u8, u8)) {
+        let (r, g, b, a) = tint;
+        self.draw_image_with_color(image, x, y, pixels::Color::RGBA(r, g, b, a));
+    }
+
+    /// Draw `image` with its top-left corner at (x, y), `scale` times its size, mirrored as `flip`
+    /// says (horizontally, vertically) and rotated clockwise by `angle` degrees around its center.
+    /// See `frame::ImageDraw`.
+    pub(crate) fn draw_image_transformed(
+        &mut self,
+        image: &Image,
+        (x, y): (i32, i32),
+        angle: f64,
+        scale: f32,
+        flip: (bool, bool),
+    ) {
+        let scaled = |length: u32| (length as f32 * scale).round() as u32;
+        let (width, height) = (scaled(image.get_width()), scaled(image.get_height()));
+        let dst = self.offset_rect(shape::Rect::new(x, y, width, height));
+        let Some(texture) = self.texture_id(&image.texture) else {
+            return;
         };
-        texture.set_blend_mode(render::BlendMode::Blend);
-        Ok(Font {
-            height: texture.query().height,
-            texture,
-            chars,
-        })
+        if self.draw_lost_target(image, dst) {
+            return;
+        }
+        self.submit(if angle == 0.0 && flip == (false, false) {
+            layer::Draw::Copy {
+                texture,
+                src: None,
+                dst,
+                blend: image.blend_mode,
+                glyph: false,
+            }
+        } else {
+            layer::Draw::CopyRotated {
+                texture,
+                src: shape::Rect::new(0, 0, image.get_width(), image.get_height()),
+                dst,
+                angle,
+                center: shape::Point::new((dst.width() / 2) as i32, (dst.height() / 2) as i32),
+                flip,
+                blend: image.blend_mode,
+                glyph: false,
+            }
+        });
     }
 
-    /// Load a Font from the hard drive. See the documentation on `Font` for details.
-    pub fn load_font_from_file(&self, filename: &Path, string: String) -> Result<Font, String> {
-        let surf: surface::Surface = LoadSurface::from_file(filename)?;
-        self.parse_image_font(surf, string)
+    fn draw_image_with_color(&mut self, image: &Image, x: i32, y: i32, color: pixels::Color) {
+        let Some(texture) = self.texture_id(&image.texture) else {
+            return;
+        };
+        let dst = self.offset_rect(shape::Rect::new(
+            x,
+            y,
+            image.get_width(),
+            image.get_height(),
+        ));
+        if self.draw_lost_target(image, dst) {
+            return;
+        }
+        let draw = layer::Draw::Copy {
+            texture,
+            src: None,
+            dst,
+            blend: image.blend_mode,
+            glyph: false,
+        };
+        self.layers
+            .submit(&mut self.canvas, draw, color, self.viewport);
     }
 
-    /// Load a Font from a slice of bytes. See the documentation on `Font` for details. This
-    /// function is particularly powerful when used in conjunction with the `include_bytes` macro
-    /// that embeds data in the compiled executable.
-    pub fn load_font(&self, data: &[u8], string: String) -> Result<Font, String> {
-        let rwops = rwops::RWops::from_bytes(data)?;
-        let surf: surface::Surface = rwops.load()?;
-        self.parse_image_font(surf, string)
+    /// Cover the viewport, or the whole Window if no viewport is set, with the image, fitted as
+    /// `fit` says. The draw offset is ignored, so the background stays put while the world
+    /// scrolls.
+    pub fn draw_background(&mut self, image: &Image, fit: BackgroundFit) {
+        let (width, height) = self.drawing_size();
+        let Some(texture) = self.texture_id(&image.texture) else {
+            return;
+        };
+        for dst in background_rects(image.get_width(), image.get_height(), width, height, fit) {
+            if self.draw_lost_target(image, dst) {
+                continue;
+            }
+            self.submit(layer::Draw::Copy {
+                texture,
+                src: None,
+                dst,
+                blend: image.blend_mode,
+                glyph: false,
+            });
+        }
     }
-}
+
+    /// Return true if `image` is a render target that has lost what was drawn on it, after
+    /// drawing the placeholder checkerboard over `dst`, in drawing coordinates, in its place. The
+    /// checkerboard is made of filled rectangles, so it can't be rotated or flipped with the
+    /// image.
+    fn draw_lost_target(&mut self, image: &Image, dst: shape::Rect) -> bool {
+        if image
+            .target
+            .is_none_or(|contents| self.textures.still_holds(contents))
+        {
+            return false;
+        }
+        for (square, odd) in util::checker_cells(dst, PLACEHOLDER_SIZE / 8) {
+            let draw = layer::Draw::FillRect(square);
+            self.layers.submit(
+                &mut self.canvas,
+                draw,
+                placeholder_color(odd),
+                self.viewport,
+            );
+        }
+        true
+    }
+
+    /// Display the image with its center at `center`. When a dimension is odd, the extra pixel
+    /// goes to the right of (or below) `center`, the same as `print_centered`.
+    pub fn draw_image_centered(&mut self, image: &Image, center: shape::Point) {
+        let (x, y) = centered(center, image.get_width(), image.get_height());
+        self.draw_image(image, x, y);
+    }
+
+    /// Display one region of an Atlas with its top-left corner at (x, y). An Atlas made by another
+    /// Window draws nothing.
+    pub fn draw_region(
+        &mut self,
+        atlas: &atlas::Atlas,
+        region: atlas::AtlasRegion,
+        x: i32,
+        y: i32,
+    ) {
+        let Some(texture) = self.texture_id(&atlas.pages[region.page]) else {
+            return;
+        };
+        let dst = self.offset_rect(shape::Rect::new(
+            x,
+            y,
+            region.get_width(),
+            region.get_height(),
+        ));
+        self.submit(layer::Draw::Copy {
+            texture,
+            src: Some(region.rect),
+            dst,
+            blend: Some(BlendMode::Blend),
+            glyph: false,
+        });
+    }
+
+    /// Write the text to the screen at (x, y) using the currently set font on the Window. Return a
+    /// Rectangle describing the area of the screen that was modified.
+    ///
+    /// Glyphs that fall entirely outside the visible area are skipped without being drawn, so
+    /// printing long lines that are mostly off-screen stays cheap. The returned Rectangle always
+    /// describes the whole string, visible or not.
+    ///
+    /// Positions are clamped instead of overflowing, so text printed near the limits of an
+    /// i32, or pushed there by the draw offset, stops at the limit rather than wrapping around to
+    /// the other side; like any Rect, the returned one is further clamped to within
+    /// `i32::MAX / 2` of the origin.
+    pub fn print(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
+        self.print_styled(text, x, y, PrintStyle::default())
+    }
+
+    /// Like `print`, but format `args` first, as in
+    /// `window.print_fmt(format_args!("Score: {}", score), 10, 10)`. The text is formatted into a
+    /// buffer the Window keeps, so unlike `format!` this doesn't allocate once the buffer is big
+    /// enough.
+    pub fn print_fmt(&mut self, args: std::fmt::Arguments, x: i32, y: i32) -> shape::Rect {
+        let mut buffer = std::mem::take(&mut self.format_buffer);
+        buffer.clear();
+        // writing to a String only fails if a Display impl reports an error, and format! panics
+        // then too
+        std::fmt::Write::write_fmt(&mut buffer, args).expect("a Display implementation failed");
+        let bounds = self.print(&buffer, x, y);
+        self.format_buffer = buffer;
+        bounds
+    }
+
+    /// Print anything that implements `Display`, like a number, without making a String of it.
+    /// See `print_fmt`.
+    pub fn print_display(&mut self, value: &impl std::fmt::Display, x: i32, y: i32) -> shape::Rect {
+        self.print_fmt(format_args!("{}", value), x, y)
+    }
+
+    /// Like `print`, but every glyph is drawn `scale` times its size in the font. Scaling up by a
+    /// whole number keeps pixel fonts crisp.
+    pub fn print_scaled(&mut self, text: &str, x: i32, y: i32, scale: f32) -> shape::Rect {
+        let style = PrintStyle {
+            scale,
+            ..PrintStyle::default()
+        };
+        self.print_styled(text, x, y, style)
+    }
+
+    /// Like `print`, but with `extra_px` more pixels between neighbouring characters, or fewer if
+    /// it is negative.
+    pub fn print_spaced(&mut self, text: &str, x: i32, y: i32, extra_px: i32) -> shape::Rect {
+        let style = PrintStyle {
+            extra_px,
+            ..PrintStyle::default()
+        };
+        self.print_styled(text, x, y, style)
+    }
+
+    /// Like `print`, but for right-to-left scripts such as Hebrew or Arabic: `x` is where the right
+    /// edge of the text goes, and the first character is drawn rightmost, each following one to
+    /// the left of the one before. The returned Rectangle covers the text, which extends to the
+    /// left of `x`.
+    ///
+    /// This only reverses the direction. There is no bidirectional reordering, so numbers and
+    /// Latin words in the text come out backwards too, and no contextual shaping: Arabic letters
+    /// are drawn in the one form the font has for each, not joined to their neighbours.
+    pub fn print_rtl(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
+        let style = PrintStyle {
+            right_to_left: true,
+            ..PrintStyle::default()
+        };
+        self.print_styled(text, x, y, style)
+    }
+
+    /// Like `print`, but draw only the edges of each glyph, leaving the inside of the strokes
+    /// empty, for titles that should let what is behind them show through. An edge is a pixel of
+    /// a glyph with an empty pixel, or the glyph's border, above, below, left or right of it, so
+    /// the outline is one pixel wide. This is meant for bold display fonts: in a font whose
+    /// strokes are a pixel or two wide, like the default one, nearly every pixel is an edge and
+    /// hollow text looks much like `print`.
+    ///
+    /// A Font keeps the outlines in a second texture, made the first time it prints hollow text
+    /// from its image read back from the GPU. That takes as much texture memory again, keeps the
+    /// image in main memory, and makes the first call a little slower. After that, printing hollow
+    /// text costs the same as `print`. If the outlines can't be made, the text is printed solid.
+    pub fn print_hollow(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
+        let style = PrintStyle {
+            hollow: true,
+            ..PrintStyle::default()
+        };
+        self.print_styled(text, x, y, style)
+    }
+
+    /// Like `print`, but fill the glyphs with a vertical gradient from `top` at the top of the
+    /// line of text to `bottom` at its bottom, each given as `(red, green, blue, alpha)`, instead
+    /// of the Window's color. The glyphs' own transparency, at their smoothed edges, is kept.
+    ///
+    /// Each row of the font's pixels in a glyph is drawn separately, so this costs as many texture
+    /// copies per glyph as the font is tall. That is fine for a title, not for pages of text.
+    pub fn print_gradient(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        top: (u8, u8, u8, u8),
+        bottom: (u8, u8, u8, u8),
+    ) -> shape::Rect {
+        let color = |(r, g, b, a)| pixels::Color::RGBA(r, g, b, a);
+        let style = PrintStyle {
+            gradient: Some((color(top), color(bottom))),
+            ..PrintStyle::default()
+        };
+        self.print_styled(text, x, y, style)
+    }
+
+    /// Like `print`, but fill the glyphs with `pattern`, repeated from the top-left corner of the
+    /// text, instead of the Window's color. The glyphs' own transparency, at their smoothed edges,
+    /// is kept, and the alpha of the Window's color makes the whole text more transparent; the
+    /// pattern's own alpha is ignored.
+    ///
+    /// The text is put together in a texture that the Window keeps for the next call, growing it
+    /// when the text doesn't fit, then drawn straight away, even when a layer is selected. If the
+    /// renderer can't draw to textures this prints the text like `print`. The pattern is
+    /// borrowed mutably only to change how it is blended while the text is put together; it is
+    /// left as it was. A pattern made by another Window is ignored in the same way.
+    pub fn print_textured(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        pattern: &mut Image,
+    ) -> shape::Rect {
+        let (width, height) = self.measure_text(text);
+        let rect = shape::Rect::new(x, y, width, height);
+        if width == 0 || height == 0 || pattern.width == 0 || pattern.height == 0 {
+            return rect;
+        }
+        if !self.canvas.render_target_supported()
+            || !pattern.texture.belongs_to(&self.textures)
+            || pattern
+                .target
+                .is_some_and(|contents| !self.textures.still_holds(contents))
+            || self.grow_text_target(width, height).is_err()
+        {
+            return self.print(text, x, y);
+        }
+        self.flush_batch();
+        let (clip, scale, blend) = (
+            self.canvas.clip_rect(),
+            self.canvas.scale(),
+            self.canvas.blend_mode(),
+        );
+        let target = self.text_target.as_ref().unwrap();
+        let area = shape::Rect::new(0, 0, width, height);
+        set_render_target(&mut self.canvas, Some(target));
+        self.canvas.set_viewport(None);
+        self.canvas.set_clip_rect(None);
+        let _ = self.canvas.set_scale(1.0, 1.0);
+        let layers = &mut self.layers;
+        layers.set_blend_mode(&mut self.canvas, BlendMode::None);
+        layers.set_draw_color(&mut self.canvas, pixels::Color::RGBA(0, 0, 0, 0));
+        self.canvas.fill_rect(area).unwrap();
+
+        // the glyphs in white, with their own alpha, replacing what is under them
+        let font = current_font(&self.font);
+        let white = pixels::Color::RGBA(255, 255, 255, 255);
+        let mut glyphs = layer::texture_of(&self.canvas, layer::TextureId::of(&font.texture));
+        layers.set_texture_state(&mut glyphs, white, BlendMode::None);
+        layers.set_draw_color(&mut self.canvas, white);
+        let (canvas, mut copies, mut boxes) = (&mut self.canvas, 0, 0);
+        font.layout_styled(text, 1.0, 0, self.layout_rules, |src, dst| match src {
+            Some(src) => {
+                canvas.copy(&glyphs, *src, dst).unwrap();
+                copies += 1;
+            }
+            None => {
+                canvas.draw_rect(dst).unwrap();
+                boxes += 1;
+            }
+        });
+        layers.set_texture_state(&mut glyphs, white, BlendMode::Blend);
+
+        // the pattern multiplies the white into its own colors and leaves the alpha alone
+        let pattern_blend = pattern.texture.blend_mode();
+        layers.set_texture_state(&mut pattern.texture, white, BlendMode::Mod);
+        let mut tiles = 0;
+        for tile_y in (0..height).step_by(pattern.height as usize) {
+            for tile_x in (0..width).step_by(pattern.width as usize) {
+                let tile =
+                    shape::Rect::new(tile_x as i32, tile_y as i32, pattern.width, pattern.height);
+                self.canvas.copy(&pattern.texture, None, tile).unwrap();
+                tiles += 1;
+            }
+        }
+        layers.set_texture_state(&mut pattern.texture, white, pattern_blend);
+
+        set_render_target(&mut self.canvas, None);
+        self.target_retained_frame(clip, scale);
+        self.layers.set_blend_mode(&mut self.canvas, blend);
+        let dst = self.offset_rect(rect);
+        let target = self.text_target.as_mut().unwrap();
+        let alpha = self.foreground_color.a;
+        let tint = pixels::Color::RGBA(255, 255, 255, alpha);
+        self.layers
+            .set_texture_state(target, tint, BlendMode::Blend);
+        self.canvas.copy(target, area, dst).unwrap();
+
+        let stats = &mut self.layers.stats;
+        stats.glyphs += copies;
+        stats.texture_copies += copies + tiles + 1;
+        stats.primitives += boxes + 1;
+        rect
+    }
+
+    /// Make sure the texture that `print_textured` draws into is at least `width` by `height`.
+    fn grow_text_target(&mut self, width: u32, height: u32) -> Result<(), String> {
+        let size = self.text_target.as_ref().map(|t| {
+            let query = t.query();
+            (query.width, query.height)
+        });
+        if let Some((w, h)) = size
+            && w >= width
+            && h >= height
+        {
+            return Ok(());
+        }
+        let (w, h) = size.unwrap_or((0, 0));
+        let target = self
+            .canvas
+            .create_texture_target(None, w.max(width), h.max(height))
+            .map_err(|e| e.to_string())?;
+        if let Some(old) = self.text_target.replace(target) {
+            // SAFETY: the text target is never recorded in a layer, so nothing refers to it
+            unsafe { old.destroy() };
+        }
+        Ok(())
+    }
+
+    /// Print `text` in `style`.
+    fn print_styled(&mut self, text: &str, x: i32, y: i32, style: PrintStyle) -> shape::Rect {
+        let PrintStyle {
+            scale,
+            extra_px,
+            right_to_left,
+            hollow,
+            gradient,
+            spans,
+        } = style;
+        let visible = self.visible_area();
+        let (dx, dy) = self.draw_offset;
+        let (color, viewport) = (self.foreground_color, self.viewport);
+        let rules = self.layout_rules;
+        let factor = if self.crisp_text {
+            crisp_factor(self.canvas.scale(), scale)
+        } else {
+            1
+        };
+        if factor > 1 || hollow {
+            self.prepare_font(factor, hollow);
+        }
+        let font = current_font(&self.font);
+        let (canvas, layers) = (&mut self.canvas, &mut self.layers);
+        // glyphs are looked up in the upscaled textures at `factor` times their size, and drawn
+        // at the size the layout gives them
+        let ((texture, outlines), factor) = match font.upscaled.get(&factor) {
+            Some((texture, hollow_texture)) => ((texture, hollow_texture), factor),
+            None => ((&font.texture, &font.hollow_texture), 1),
+        };
+        let texture = layer::TextureId::of(match outlines {
+            Some(outlines) if hollow => outlines,
+            _ => texture,
+        });
+        let (top, line_height) = (y.saturating_add(dy), scaled_font_height(font, scale));
+
+        let width = font.layout_indexed(text, scale, extra_px, rules, |index, src, mut dst| {
+            if right_to_left {
+                dst.set_x(-dst.right());
+            }
+            dst.offset(x.saturating_add(dx), top);
+            if !dst.has_intersection(visible) {
+                return;
+            }
+            let color = spans
+                .iter()
+                .find(|&&(start, end, _)| (start..end).contains(&index))
+                .map_or(color, |&(_, _, color)| color);
+            let src = match src {
+                Some(src) => shape::Rect::new(
+                    src.x() * factor as i32,
+                    src.y() * factor as i32,
+                    src.width() * factor,
+                    src.height() * factor,
+                ),
+                None => {
+                    let color = match gradient {
+                        Some((first, last)) => mix_colors(first, last, 0.5),
+                        None => color,
+                    };
+                    layers.submit(canvas, layer::Draw::Rect(dst), color, viewport);
+                    return;
+                }
+            };
+            let Some((first, last)) = gradient else {
+                let draw = layer::Draw::Copy {
+                    texture,
+                    src: Some(src),
+                    dst,
+                    blend: Some(BlendMode::Blend),
+                    glyph: true,
+                };
+                layers.submit(canvas, draw, color, viewport);
+                return;
+            };
+            // one copy per row of the glyph, each tinted by where its middle is in the line, and
+            // only the first counted as the glyph
+            let mut glyph = true;
+            for row in 0..src.height() {
+                let (from, to) = (
+                    glyph_row_edge(dst, src.height(), row),
+                    glyph_row_edge(dst, src.height(), row + 1),
+                );
+                if from == to {
+                    continue;
+                }
+                let middle = (from + to) as f32 / 2.0 - top as f32;
+                let color = mix_colors(first, last, middle / line_height.max(1) as f32);
+                let draw = layer::Draw::Copy {
+                    texture,
+                    src: Some(shape::Rect::new(
+                        src.x(),
+                        src.y() + row as i32,
+                        src.width(),
+                        1,
+                    )),
+                    dst: shape::Rect::new(dst.x(), from, dst.width(), (to - from) as u32),
+                    blend: Some(BlendMode::Blend),
+                    glyph,
+                };
+                layers.submit(canvas, draw, color, viewport);
+                glyph = false;
+            }
+        });
+
+        let left = if right_to_left {
+            x.saturating_sub(width)
+        } else {
+            x
+        };
+        shape::Rect::new(left, y, width as u32, scaled_font_height(font, scale))
+    }
+
+    /// Keep text sharp when it is drawn scaled, by `set_scale`, `enable_pixel_perfect` or
+    /// `print_scaled`. With this on, printing at a scale of 1.5 or more draws from a copy of the
+    /// font's glyphs enlarged by the nearest whole number, each pixel repeated, so the renderer
+    /// only has to stretch them by what is left over instead of blurring small glyphs across many
+    /// screen pixels. Text is laid out and measured exactly as without it.
+    ///
+    /// Each font keeps one enlarged copy, as large as its image times the factor squared, for
+    /// every factor it has been printed at, made the first time it is needed. The first time also
+    /// reads the font's image back from the GPU, and keeps it in main memory from then on.
+    /// `print_rotated` is not affected. It is off by default.
+    pub fn set_crisp_text(&mut self, on: bool) {
+        self.crisp_text = on;
+    }
+
+    /// Choose what `print` and its relatives do with characters that the font has no glyph for.
+    /// Measuring text follows the same choice, so layout doesn't change between drawing and
+    /// measuring. `print_rotated` leaves the space for a box, but doesn't draw it.
+    pub fn set_missing_glyph_mode(&mut self, mode: font::MissingGlyphMode) {
+        self.layout_rules.missing = mode;
+    }
+
+    /// Make a tab in printed text move on to the next multiple of `px` pixels from the start of
+    /// the string, so text can be lined up in columns. The default, which a `px` of 0 goes back
+    /// to, is the width of four spaces in the current font. Text printed with `print_scaled` has
+    /// its tab stops scaled too.
+    pub fn set_tab_width(&mut self, px: u32) {
+        self.layout_rules.tab_width = if px == 0 { None } else { Some(px) };
+    }
+
+    /// Return the width and height that `print` would cover when writing `text` in the current
+    /// font, without drawing anything.
+    pub fn measure_text(&self, text: &str) -> (u32, u32) {
+        self.measure_text_ex(text, 1.0, 0)
+    }
+
+    /// Like `measure_text`, but for `print_scaled` and `print_spaced`: return the size the text
+    /// would cover when printed at `scale` with `extra_px` between characters.
+    pub fn measure_text_ex(&self, text: &str, scale: f32, extra_px: i32) -> (u32, u32) {
+        let font = current_font(&self.font);
+        let width = font.layout_styled(text, scale, extra_px, self.layout_rules, |_, _| ());
+        (width as u32, scaled_font_height(font, scale))
+    }
+
+    /// Return true if `point`, in the coordinates `mouse_position` reports, is over a pixel of
+    /// `image` whose alpha is at least `alpha_threshold`, when the image is drawn unscaled at
+    /// `image_pos` with `draw_image`. `mouse_position` has already divided by the scale and
+    /// removed the letterbox bars, and the viewport and draw offset are taken into account here,
+    /// so pass the same position the image is drawn at. A round button should only be pressed by
+    /// clicks on the button, not on the transparent corners of its image.
+    ///
+    /// This reads the copy of the pixels the Image keeps in main memory, so it is cheap enough to
+    /// do for every click.
+    pub fn image_hit_test(
+        &self,
+        image: &Image,
+        image_pos: (i32, i32),
+        point: (i32, i32),
+        alpha_threshold: u8,
+    ) -> bool {
+        let origin = self.viewport.map_or((0, 0), |v| (v.x(), v.y()));
+        let x =
+            (point.0 as i64) - (origin.0 as i64 + image_pos.0 as i64 + self.draw_offset.0 as i64);
+        let y =
+            (point.1 as i64) - (origin.1 as i64 + image_pos.1 as i64 + self.draw_offset.1 as i64);
+        match (i32::try_from(x), i32::try_from(y)) {
+            (Ok(x), Ok(y)) => image.alpha_at(x, y).is_some_and(|a| a >= alpha_threshold),
+            _ => false,
+        }
+    }
+
+    /// Return the x coordinate of the caret placed before the character at `char_index` of `text`,
+    /// printed with `print` at `origin_x`. An index past the end puts the caret after the last
+    /// character. This uses the same advances as `print`, missing glyphs included, so a caret or a
+    /// selection drawn with `fill_rect` lines up with the text exactly.
+    pub fn caret_x(&self, text: &str, char_index: usize, origin_x: i32) -> i32 {
+        let offsets = current_font(&self.font).caret_offsets(text, self.layout_rules);
+        origin_x.saturating_add(offsets[char_index.min(offsets.len() - 1)])
+    }
+
+    /// Return the caret position, as a character index, nearest to `query_x` in `text` printed at
+    /// `origin_x`. Clicking the right half of a character puts the caret after it. This is the
+    /// inverse of `caret_x`.
+    pub fn char_index_at(&self, text: &str, origin_x: i32, query_x: i32) -> usize {
+        let offsets = current_font(&self.font).caret_offsets(text, self.layout_rules);
+        font::nearest_caret(&offsets, query_x.saturating_sub(origin_x))
+    }
+
+    /// Break `text` into the lines `print_rect` would draw in a box `max_width` pixels wide, using
+    /// the current font. Lines break between words where possible, and inside a word that is too
+    /// long for a whole line. Newlines always start a new line.
+    pub fn layout_text(&self, text: &str, max_width: u32) -> Vec<String> {
+        font::wrap_text(text, max_width, |line| self.measure_text(line).0)
+    }
+
+    /// Write the text inside `rect`, wrapped to its width as by `layout_text`, one line below the
+    /// other from the top. Lines that would run past the bottom of `rect` are not drawn. Return
+    /// how many lines the text wrapped to, including those, so callers can add a scroll bar or
+    /// another page.
+    pub fn print_rect(&mut self, text: &str, rect: shape::Rect) -> usize {
+        self.print_rect_with(text, rect, &font::TextOptions::default())
+    }
+
+    /// Like `print_rect`, but aligned and spaced as `options` says. The wrapped block is measured
+    /// first, so vertical alignment places the whole block, not just its first line.
+    pub fn print_rect_with(
+        &mut self,
+        text: &str,
+        rect: shape::Rect,
+        options: &font::TextOptions,
+    ) -> usize {
+        self.print_rect_spans(text, rect, options, &[])
+    }
+
+    /// Print `template` like `print`, with every `{name}` in it replaced by the value of the
+    /// substitution called `name`; see `font::Substitution`. A value is drawn in its color, if it
+    /// has one, and the rest of the text in the Window's color, so a translated sentence like
+    /// "Press {key:JUMP} to jump" stays in one piece and still shows the key in a color of its
+    /// own. `{{` and `}}` print as braces.
+    ///
+    /// A token without a substitution is printed as it is, braces and all, rather than failing,
+    /// and logged to the debug console the first time it is seen.
+    pub fn print_template(
+        &mut self,
+        template: &str,
+        substitutions: &[font::Substitution],
+        x: i32,
+        y: i32,
+    ) -> shape::Rect {
+        let expanded = self.expand_template(template, substitutions);
+        let style = PrintStyle {
+            spans: &expanded.spans,
+            ..PrintStyle::default()
+        };
+        self.print_styled(&expanded.text, x, y, style)
+    }
+
+    /// Like `print_rect`, for a template as `print_template` takes it. The text is wrapped after
+    /// the tokens have been replaced, so a long value wraps like any other words, and keeps its
+    /// color across the break.
+    pub fn print_template_rect(
+        &mut self,
+        template: &str,
+        substitutions: &[font::Substitution],
+        rect: shape::Rect,
+    ) -> usize {
+        let expanded = self.expand_template(template, substitutions);
+        let options = font::TextOptions::default();
+        self.print_rect_spans(&expanded.text, rect, &options, &expanded.spans)
+    }
+
+    /// Replace the tokens in `template`, logging those without a substitution that haven't been
+    /// logged before.
+    fn expand_template(
+        &mut self,
+        template: &str,
+        substitutions: &[font::Substitution],
+    ) -> font::ExpandedTemplate {
+        let expanded = font::expand_template(template, substitutions);
+        for name in &expanded.unknown {
+            if self.unknown_template_tokens.insert(name.clone()) {
+                crate::log!("print_template: no substitution for {{{}}}", name);
+            }
+        }
+        expanded
+    }
+
+    /// `print_rect_with`, drawing the characters of `text` that `spans` covers in their colors;
+    /// see `PrintStyle`.
+    fn print_rect_spans(
+        &mut self,
+        text: &str,
+        rect: shape::Rect,
+        options: &font::TextOptions,
+        spans: &[(usize, usize, pixels::Color)],
+    ) -> usize {
+        let lines = self.layout_text(text, rect.width());
+        // where the line being drawn starts in text, in bytes and in characters
+        let (mut line_byte, mut line_char) = (0, 0);
+        let line_height = self.measure_text("").1;
+        let step = (line_height as f32 * options.line_spacing.max(0.0)).round() as u32;
+        let block_height = step
+            .saturating_mul((lines.len() as u32).saturating_sub(1))
+            .saturating_add(line_height);
+        let top = rect.y() + font::align_offset(block_height, rect.height(), options.valign);
+        for (i, line) in lines.iter().enumerate() {
+            let y = top.saturating_add((i as u32).saturating_mul(step).min(i32::MAX as u32) as i32);
+            if options.clip && y.saturating_add(line_height as i32) > rect.bottom() {
+                break;
+            }
+            let width = self.measure_text(line).0;
+            let spare = rect.width().saturating_sub(width) as i32;
+            let x = rect.x()
+                + match options.align {
+                    font::Align::Left => 0,
+                    font::Align::Center => spare / 2,
+                    font::Align::Right => spare,
+                };
+            // every line is a piece of text, in order, with the spaces it broke at left out
+            let start = line_byte + text[line_byte..].find(line.as_str()).unwrap_or(0);
+            line_char += text[line_byte..start].chars().count();
+            let line_spans: Vec<_> = spans
+                .iter()
+                .filter(|&&(_, end, _)| end > line_char)
+                .map(|&(from, end, color)| (from.saturating_sub(line_char), end - line_char, color))
+                .collect();
+            let style = PrintStyle {
+                spans: &line_spans,
+                ..PrintStyle::default()
+            };
+            self.print_styled(line, x, y, style);
+            line_byte = start + line.len();
+            line_char += line.chars().count();
+        }
+        lines.len()
+    }
+
+    /// Like `print`, but the text is centered on `center`. See `measure_text`.
+    pub fn print_centered(&mut self, text: &str, center: shape::Point) -> shape::Rect {
+        let (width, height) = self.measure_text(text);
+        let (x, y) = centered(center, width, height);
+        self.print(text, x, y)
+    }
+
+    /// Like `print`, but the whole string is rotated clockwise by `angle_degrees` around (x, y),
+    /// the top-left corner of the unrotated text. Each glyph is rotated around that same point, so
+    /// the string turns as one block. Multiples of 90 degrees stay pixel-crisp; other angles are
+    /// smoothed by the renderer.
+    ///
+    /// Return the axis-aligned bounding box of the rotated text.
+    pub fn print_rotated(&mut self, text: &str, x: i32, y: i32, angle_degrees: f64) -> shape::Rect {
+        let (dx, dy) = self.draw_offset;
+        let (color, viewport) = (self.foreground_color, self.viewport);
+        let rules = self.layout_rules;
+        let font = current_font(&self.font);
+        let (canvas, layers) = (&mut self.canvas, &mut self.layers);
+        let texture = layer::TextureId::of(&font.texture);
+
+        let origin = (x.saturating_add(dx), y.saturating_add(dy));
+        let width = font.layout_styled(text, 1.0, 0, rules, |src, mut dst| {
+            let offset = dst.x();
+            dst.offset(origin.0, origin.1);
+            let draw = match src {
+                Some(src) => layer::Draw::CopyRotated {
+                    texture,
+                    src: *src,
+                    dst,
+                    angle: angle_degrees,
+                    center: shape::Point::new(offset.saturating_neg(), 0),
+                    flip: (false, false),
+                    blend: Some(BlendMode::Blend),
+                    glyph: true,
+                },
+                // the box standing in for a missing glyph turns with the text
+                None => layer::Draw::Points(util::rotated_outline(
+                    dst,
+                    shape::Point::new(origin.0, origin.1),
+                    angle_degrees,
+                )),
+            };
+            layers.submit(canvas, draw, color, viewport);
+        });
+
+        util::rotated_bounds(
+            shape::Rect::new(x, y, width as u32, font.get_height()),
+            shape::Point::new(x, y),
+            angle_degrees,
+        )
+    }
+
+    /// Read back what has been drawn so far as tightly packed RGBA bytes, four per pixel with red
+    /// first whatever the platform, and return them with their width and height. `region` is
+    /// measured like any other drawing coordinates, from the top-left corner of the viewport;
+    /// `None` reads the whole viewport, which is the whole Window unless one is set.
+    ///
+    /// Calls recorded on layers (see `layer`) are only drawn during `next_frame`, so they are not
+    /// included yet. This is slow, because it waits for the GPU; it's meant for screenshots and
+    /// tests rather than for every frame.
+    ///
+    /// Draws still waiting in the batch (see `set_batching`) are flushed first, so this takes
+    /// `&mut self`.
+    pub fn read_pixels(
+        &mut self,
+        region: Option<shape::Rect>,
+    ) -> Result<(Vec<u8>, u32, u32), SimpleError> {
+        self.flush_batch();
+        let (width, height) = match region {
+            Some(rect) => (rect.width(), rect.height()),
+            None => {
+                let viewport = self.canvas.viewport();
+                (viewport.width(), viewport.height())
+            }
+        };
+        let pixels = self
+            .canvas
+            .read_pixels(region, pixels::PixelFormatEnum::RGBA32)?;
+        Ok((pixels, width, height))
+    }
+
+    /// Clear the screen to black. Does not affect the current rendering color.
+    pub fn clear(&mut self) {
+        self.fill_background(pixels::Color::RGB(0, 0, 0));
+    }
+
+    /// Clear the screen to the color you specify.
+    pub fn clear_to_color(&mut self, r: u8, g: u8, b: u8) {
+        self.fill_background(pixels::Color::RGB(r, g, b));
+    }
+
+    /// Set the color that the screen is cleared to at the start of every frame once
+    /// `set_auto_clear` is on. It is black until this is called.
+    pub fn set_clear_color(&mut self, r: u8, g: u8, b: u8) {
+        self.clear_color = pixels::Color::RGB(r, g, b);
+    }
+
+    /// When `enabled`, `next_frame` clears the screen to the clear color (see `set_clear_color`)
+    /// once it has shown the frame, so every frame starts blank without a call to `clear`. During
+    /// partial redraw, each region is cleared as it is passed to `mark_dirty` instead. This is off
+    /// by default.
+    pub fn set_auto_clear(&mut self, enabled: bool) {
+        self.auto_clear = enabled;
+    }
+
+    /// Clear the whole screen to `color`, or only the dirty rectangles during partial redraw.
+    fn fill_background(&mut self, color: pixels::Color) {
+        self.flush_batch();
+        if self.retained_frame.is_none() || self.image_target.is_some() {
+            self.layers.set_draw_color(&mut self.canvas, color);
+            self.canvas.clear();
+            self.layers.stats.primitives += 1;
+            return;
+        }
+        let rects = self.dirty_rects.clone();
+        self.fill_window_rects(color, &rects);
+    }
+
+    /// Fill `rects`, given in Window coordinates, with `color`, ignoring the viewport, clip rect,
+    /// scale and blend mode.
+    fn fill_window_rects(&mut self, color: pixels::Color, rects: &[shape::Rect]) {
+        self.flush_batch();
+        self.layers.set_draw_color(&mut self.canvas, color);
+        let (clip, scale, blend) = (
+            self.canvas.clip_rect(),
+            self.canvas.scale(),
+            self.canvas.blend_mode(),
+        );
+        self.canvas.set_viewport(None);
+        self.canvas.set_clip_rect(None);
+        let _ = self.canvas.set_scale(1.0, 1.0);
+        self.layers
+            .set_blend_mode(&mut self.canvas, BlendMode::None);
+        self.canvas.fill_rects(rects).unwrap();
+        self.layers.set_blend_mode(&mut self.canvas, blend);
+        self.layers.stats.primitives += 1;
+        self.target_retained_frame(clip, scale);
+    }
+
+    /// Draw into a frame that is kept from one frame to the next, instead of starting from an
+    /// undefined screen every time, so that a mostly static program only has to redraw what
+    /// changed. Mark the regions that are about to change with `mark_dirty`; `clear` then only
+    /// clears those, and `is_dirty` tells whether something needs to be drawn at all. Each
+    /// `next_frame` copies the kept frame to the screen and forgets the dirty regions.
+    ///
+    /// The whole Window is dirty on the first frame and whenever its size changes. Frame hooks
+    /// draw into the kept frame like everything else; the debug overlay is drawn on top of it.
+    /// Returns an error if the renderer can't draw to textures.
+    pub fn set_partial_redraw(&mut self, on: bool) -> Result<(), SimpleError> {
+        if on == self.retained_frame.is_some() {
+            return Ok(());
+        }
+        self.flush_batch();
+        let (clip, scale) = (self.canvas.clip_rect(), self.canvas.scale());
+        if on {
+            if !self.canvas.render_target_supported() {
+                return Err(SimpleError::Unsupported(
+                    "partial redraw needs a renderer that can draw to textures".to_string(),
+                ));
+            }
+            self.retain_frame(clip, scale).map_err(SimpleError::Sdl)
+        } else {
+            self.drop_retained_frame(clip, scale);
+            Ok(())
+        }
+    }
+
+    /// Record that `rect`, in Window coordinates (ignoring the viewport and draw offset), is going
+    /// to be redrawn this frame. Only has an effect during partial redraw; see
+    /// `set_partial_redraw`. With `set_auto_clear` on, the region is cleared to the clear color
+    /// straight away.
+    pub fn mark_dirty(&mut self, rect: shape::Rect) {
+        if self.retained_frame.is_some() {
+            self.dirty_rects.push(rect);
+            if self.auto_clear {
+                self.fill_window_rects(self.clear_color, &[rect]);
+            }
+        }
+    }
+
+    /// Return true if `rect`, in Window coordinates, overlaps a region marked with `mark_dirty`
+    /// this frame. Without partial redraw everything is redrawn every frame, so this is always
+    /// true.
+    pub fn is_dirty(&self, rect: shape::Rect) -> bool {
+        self.retained_frame.is_none() || self.dirty_rects.iter().any(|d| d.has_intersection(rect))
+    }
+
+    /// Make a new kept frame the size of the screen, draw to it from now on, and mark all of it
+    /// dirty. The screen must be the render target.
+    fn retain_frame(&mut self, clip: Option<shape::Rect>, scale: (f32, f32)) -> Result<(), String> {
+        let (width, height) = self.drawable_size();
+        let frame = self
+            .canvas
+            .create_texture_target(None, width, height)
+            .map_err(|e| e.to_string())?;
+        if let Some(old) = self.retained_frame.replace(frame) {
+            // SAFETY: the kept frame is never recorded in a layer, so nothing refers to it
+            unsafe { old.destroy() };
+        }
+        self.target_retained_frame(clip, scale);
+        self.dirty_rects = vec![shape::Rect::new(0, 0, width, height)];
+        Ok(())
+    }
+
+    /// Stop partial redraw, putting what has been drawn so far on the screen.
+    fn drop_retained_frame(&mut self, clip: Option<shape::Rect>, scale: (f32, f32)) {
+        set_render_target(&mut self.canvas, None);
+        if let Some(frame) = self.retained_frame.take() {
+            self.canvas.set_viewport(None);
+            self.canvas.set_clip_rect(None);
+            let _ = self.canvas.set_scale(1.0, 1.0);
+            self.canvas.copy(&frame, None, None).unwrap();
+            self.layers.stats.texture_copies += 1;
+            // SAFETY: the kept frame is never recorded in a layer, so nothing refers to it
+            unsafe { frame.destroy() };
+        }
+        self.dirty_rects.clear();
+        self.canvas.set_viewport(self.viewport);
+        self.canvas.set_clip_rect(clip);
+        let _ = self.canvas.set_scale(scale.0, scale.1);
+    }
+
+    /// Draw to the Image that `draw_to_image` is drawing to, if it is running, or else to the kept
+    /// frame, if there is one, with the Window's viewport and the given clip rect and scale. SDL
+    /// resets all three whenever the render target changes.
+    fn target_retained_frame(&mut self, clip: Option<shape::Rect>, scale: (f32, f32)) {
+        if let Some(target) = self.image_target.as_ref().or(self.retained_frame.as_ref()) {
+            set_render_target(&mut self.canvas, Some(target));
+        }
+        self.canvas.set_viewport(self.viewport);
+        self.canvas.set_clip_rect(clip);
+        let _ = self.canvas.set_scale(scale.0, scale.1);
+    }
+}
+
+/// Look up the function called `name` in the SDL the program is running with, for the functions
+/// newer than the sdl2 crate's bindings. Return `None` if that SDL doesn't have it.
+fn newer_sdl_function(name: &str) -> Option<*mut std::ffi::c_void> {
+    // SDL is usually a library of its own, but may be linked into the program, which a null name
+    // stands for
+    const LIBRARIES: [&str; 4] = ["", "libSDL2-2.0.so.0", "libSDL2-2.0.0.dylib", "SDL2.dll"];
+    let name = std::ffi::CString::new(name).ok()?;
+    LIBRARIES.iter().find_map(|library| {
+        let library = std::ffi::CString::new(*library).unwrap();
+        let library = match library.as_bytes() {
+            [] => std::ptr::null(),
+            _ => library.as_ptr(),
+        };
+        // SAFETY: both names are NUL-terminated strings that outlive the calls. A library SDL
+        // opens is never closed, so what is found in it stays valid.
+        unsafe {
+            let object = sdl2::sys::SDL_LoadObject(library);
+            if object.is_null() {
+                return None;
+            }
+            let function = sdl2::sys::SDL_LoadFunction(object, name.as_ptr());
+            (!function.is_null()).then_some(function)
+        }
+    })
+}
+
+/// Make `texture`, which must be a render target created by `canvas`, or the screen if `None`,
+/// the target of everything `canvas` draws.
+fn set_render_target(
+    canvas: &mut render::Canvas<sdl2::video::Window>,
+    texture: Option<&render::Texture>,
+) {
+    let raw = texture.map_or(std::ptr::null_mut(), |t| t.raw());
+    // SAFETY: both pointers come from live SDL objects, and the texture belongs to this renderer
+    unsafe {
+        sdl2::sys::SDL_SetRenderTarget(canvas.raw(), raw);
+    }
+}
+
+/// Return the color of the light (`false`) or dark (`true`) squares of the placeholder
+/// checkerboard, which stands in for images that failed to load or whose contents were lost.
+fn placeholder_color(odd: bool) -> pixels::Color {
+    if odd {
+        pixels::Color::RGB(0, 0, 0)
+    } else {
+        pixels::Color::RGB(255, 0, 255)
+    }
+}
+
+/**
+ * Image represents a texture that can be drawn on the screen.
+ *
+ * Images are immutable, in the sense that they have no methods to modify their contents. Methods
+ * that transform an Image, like `crop`, return a new one instead.
+ *
+ * An Image keeps an RGBA copy of its pixels in main memory alongside the texture, so that it can
+ * be processed without reading back from the GPU. This doubles the memory an Image uses.
+ *
+ * An Image can be kept in any struct and dropped at any time, even after the Window that made
+ * it; its texture is freed at the end of the frame, once nothing can still be drawing it. Only
+ * that Window can draw it. To carry it over to a new Window, pass it to `Window::adopt_image`.
+ */
+pub struct Image {
+    texture: texture::Texture,
+    surface: surface::Surface<'static>,
+    width: u32,
+    height: u32,
+    blend_mode: Option<BlendMode>,
+    // for an Image made with create_render_target, whether its texture still holds what was drawn
+    target: Option<texture::Contents>,
+}
+
+impl Image {
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return the blend mode set with `set_blend_mode`, or `None` if the Image follows the
+    /// Window's.
+    pub fn blend_mode(&self) -> Option<BlendMode> {
+        self.blend_mode
+    }
+
+    /// Draw this Image with `mode`, whatever `Window::set_blend_mode` says, so that a lighting
+    /// image can multiply with `BlendMode::Mod` while the sprites around it blend normally,
+    /// without switching the Window's mode between draws. `None`, the default, draws it with
+    /// the Window's blend mode at the time the draw is carried out: when the layers are drawn, if
+    /// a layer is selected. Copies made with `crop` and the like start out with `None` again.
+    pub fn set_blend_mode(&mut self, mode: Option<BlendMode>) {
+        self.blend_mode = mode;
+    }
+
+    /// Return true if this Image was made with `Window::create_render_target`, so that it can be
+    /// drawn on with `Window::draw_to_image`.
+    pub fn is_render_target(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Treat what was drawn on this render target as lost, as if `Event::RenderTargetsLost` had
+    /// come, so that it draws as a checkerboard until `Window::draw_to_image` draws it again. Use
+    /// it to notice content that was never re-baked. Does nothing to an Image that isn't a render
+    /// target.
+    pub fn invalidate(&mut self) {
+        if let Some(contents) = self.target.as_mut() {
+            *contents = texture::Contents::Lost;
+        }
+    }
+
+    /// Create a new Image containing only the `src` portion of this one. Returns an error if `src`
+    /// doesn't lie entirely within the Image.
+    pub fn crop(&self, window: &Window, src: shape::Rect) -> Result<Image, SimpleError> {
+        if src.x() < 0
+            || src.y() < 0
+            || src.x() as u32 + src.width() > self.width
+            || src.y() as u32 + src.height() > self.height
+        {
+            return Err(SimpleError::InvalidArgument(format!(
+                "crop rectangle {:?} is outside of the {}x{} image",
+                src, self.width, self.height
+            )));
+        }
+
+        let mut cropped = util::new_rgba_surface(src.width(), src.height())?;
+        self.surface.blit(src, &mut cropped, None)?;
+        Ok(window.image_from_surface(cropped)?)
+    }
+
+    /// Create a desaturated copy of this Image, for example to show that a button is disabled.
+    /// Each pixel becomes the gray of its luminance; alpha is kept as it is.
+    pub fn to_grayscale(&self, window: &Window) -> Result<Image, SimpleError> {
+        let mut pixels = util::rgba_surface_bytes(&self.surface);
+        util::grayscale_rgba(&mut pixels);
+        let surf = util::rgba_surface_from_bytes(&pixels, self.width, self.height)?;
+        Ok(window.image_from_surface(surf)?)
+    }
+
+    /// Create a copy of this Image scaled to `new_width` by `new_height`, for thumbnails or for
+    /// sprites that are always drawn smaller, which then take less memory and are quicker to
+    /// draw than scaling the original every time. With `ScaleQuality::Nearest` every pixel of the
+    /// copy is a pixel of the original, which suits pixel art; the other qualities average the
+    /// pixels the new ones cover. Returns an error if either size is zero.
+    pub fn resize(
+        &self,
+        window: &Window,
+        new_width: u32,
+        new_height: u32,
+        quality: ScaleQuality,
+    ) -> Result<Image, SimpleError> {
+        if new_width == 0 || new_height == 0 {
+            return Err(SimpleError::InvalidArgument(format!(
+                "can't resize an image to {}x{}",
+                new_width, new_height
+            )));
+        }
+        let pixels = util::rgba_surface_bytes(&self.surface);
+        let resized = util::resize_rgba(
+            &pixels,
+            (self.width, self.height),
+            (new_width, new_height),
+            quality != ScaleQuality::Nearest,
+        );
+        let surf = util::rgba_surface_from_bytes(&resized, new_width, new_height)?;
+        Ok(window.image_from_surface(surf)?)
+    }
+
+    /// Make a mask of the pixels of this Image whose alpha is at least `alpha_threshold`, for
+    /// pixel-perfect collision tests with `CollisionMask::overlaps`. It is made from the copy of
+    /// the pixels the Image keeps in main memory, so no Window is needed and it can't fail, but
+    /// it does take a pass over every pixel: make it once, not every frame.
+    pub fn collision_mask(&self, alpha_threshold: u8) -> CollisionMask {
+        let pixels = util::rgba_surface_bytes(&self.surface);
+        CollisionMask::from_rgba(&pixels, self.width, self.height, alpha_threshold)
+    }
+
+    /// Return the alpha of the pixel at (x, y), or `None` if that is outside the Image.
+    fn alpha_at(&self, x: i32, y: i32) -> Option<u8> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return None;
+        }
+        let pitch = self.surface.pitch() as usize;
+        let offset = y as usize * pitch + x as usize * 4 + 3;
+        Some(self.surface.with_lock(|pixels| pixels[offset]))
+    }
+}
+
+/// A seed that is different every time: the number of nanoseconds since the Unix epoch.
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// The UI scale for a display with `dpi` horizontal DPI. See `Window::suggested_ui_scale`.
+fn ui_scale_for_dpi(dpi: Option<f32>) -> f32 {
+    match dpi {
+        Some(dpi) if dpi.is_finite() && dpi > 0.0 => ((dpi / 96.0) * 4.0).round() / 4.0,
+        _ => 1.0,
+    }
+    .clamp(1.0, 4.0)
+}
+
+/// Return the height of a line of `font` printed at `scale`.
+fn scaled_font_height(font: &Font, scale: f32) -> u32 {
+    (font.get_height() as f32 * scale).round().max(0.0) as u32
+}
+
+/// Return the top-left corner of a `width` by `height` box whose center is `center`.
+fn centered(center: shape::Point, width: u32, height: u32) -> (i32, i32) {
+    (
+        center.x().saturating_sub((width / 2) as i32),
+        center.y().saturating_sub((height / 2) as i32),
+    )
+}
+
+/// Return the keycode that `key` produces with the current keyboard layout.
+fn keycode_of(key: event::Key) -> i32 {
+    // SAFETY: SDL's video subsystem, which owns the keyboard layout, lives as long as the Window
+    // that callers borrow. Every Key is a valid SDL_Scancode.
+    unsafe {
+        sdl2::sys::SDL_GetKeyFromScancode(std::mem::transmute::<u32, sdl2::sys::SDL_Scancode>(
+            key as u32,
+        ))
+    }
+}
+
+/// How `print_styled` lays out and draws a string.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct PrintStyle<'a> {
+    /// How much bigger than the font's own size each glyph is drawn.
+    scale: f32,
+    /// Extra space between neighbouring characters, or less space if negative.
+    extra_px: i32,
+    /// Mirror the layout so that it ends at `x` instead of starting there.
+    right_to_left: bool,
+    /// Draw the glyphs' outlines only.
+    hollow: bool,
+    /// Tint the glyphs from the first color at the top of the line to the second at the bottom,
+    /// instead of with the Window's color.
+    gradient: Option<(pixels::Color, pixels::Color)>,
+    /// Draw the characters from the first index up to the second in the color, instead of the
+    /// Window's, counting characters from the start of the text.
+    spans: &'a [(usize, usize, pixels::Color)],
+}
+
+impl Default for PrintStyle<'_> {
+    fn default() -> Self {
+        PrintStyle {
+            scale: 1.0,
+            extra_px: 0,
+            right_to_left: false,
+            hollow: false,
+            gradient: None,
+            spans: &[],
+        }
+    }
+}
+
+/// Return true if a multiple of `period_ms` lies in `(from_ms, to_ms]`, the time covered by a
+/// frame. Every time does when `period_ms` is 0.
+fn crossed_period(from_ms: u32, to_ms: u32, period_ms: u32) -> bool {
+    period_ms == 0 || from_ms / period_ms != to_ms / period_ms
+}
+
+/// Push an event of the type `type_`, which carries nothing, onto SDL's event queue so that a
+/// `wait_event` blocked on it wakes up. Unlike the rest of SDL, this may be called from any thread.
+fn push_wake_event(type_: u32) {
+    let mut e: sdl2::sys::SDL_Event = unsafe { std::mem::zeroed() };
+    e.type_ = type_;
+    unsafe { sdl2::sys::SDL_PushEvent(&mut e) };
+}
+
+/// Return the color `t` of the way from `from` to `to`, where `t` is clamped to 0..=1.
+fn mix_colors(from: pixels::Color, to: pixels::Color, t: f32) -> pixels::Color {
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    pixels::Color::RGBA(
+        mix(from.r, to.r),
+        mix(from.g, to.g),
+        mix(from.b, to.b),
+        mix(from.a, to.a),
+    )
+}
+
+/// Return the screen row where row `row` of a glyph `rows` rows tall starts when the glyph is
+/// drawn stretched to `dst`.
+fn glyph_row_edge(dst: shape::Rect, rows: u32, row: u32) -> i32 {
+    dst.y() + (row as i64 * dst.height() as i64 / rows.max(1) as i64) as i32
+}
+
+/// Return how many times larger the glyphs of crisp text should be drawn from: the nearest whole
+/// number to the larger of the canvas scales times the print scale. Past 8 the gain isn't worth
+/// the memory.
+fn crisp_factor(canvas_scale: (f32, f32), print_scale: f32) -> u32 {
+    let scale = canvas_scale.0.max(canvas_scale.1) * print_scale;
+    if scale.is_finite() {
+        scale.round().clamp(1.0, 8.0) as u32
+    } else {
+        1
+    }
+}
+
+/// Return the font that is set on a Window. This takes the Window's field on its own so the canvas
+/// can still be borrowed for drawing.
+fn current_font(font: &Option<Font>) -> &Font {
+    match *font {
+        Some(ref r) => r,
+
+        // FIXME: shouldn't be possible to have no font, and the `font` field on Window should
+        // be updated to reflect this.
+        None => panic!("no font set on window"),
+    }
+}
+
+/// The default cap on the event queue. See `Window::set_event_queue_limit`.
+const DEFAULT_EVENT_QUEUE_LIMIT: usize = 10_000;
+
+/// Resource Loading Methods
+/// ========================
+impl Window {
+    /// Load the image at the path you specify. If it fails, the error is a `SimpleError::File`
+    /// that names the path, around an `Io` error if the file couldn't be opened.
+    pub fn load_image_from_file(&self, filename: impl AsRef<Path>) -> Result<Image, SimpleError> {
+        let filename = filename.as_ref();
+        let image = error::check_readable(filename).and_then(|()| {
+            let surf = LoadSurface::from_file(filename)
+                .and_then(|mut surf: surface::Surface| util::to_rgba_surface(&mut surf))
+                .map_err(SimpleError::ImageLoad)?;
+            Ok(self.image_from_surface(surf)?)
+        });
+        self.or_placeholder(image, Some(filename))
+    }
+
+    /// Load every image in `paths`, in order, with `load_image_from_file`. Stop at the first one
+    /// that fails and return its index in `paths` along with the error, which names its path.
+    pub fn load_images(
+        &self,
+        paths: &[impl AsRef<Path>],
+    ) -> Result<Vec<Image>, (usize, SimpleError)> {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| self.load_image_from_file(path).map_err(|e| (i, e)))
+            .collect()
+    }
+
+    /// When `enabled`, `load_image` and `load_image_from_file` no longer fail: instead of an
+    /// error they return a 64x64 magenta and black checkerboard, so that one broken file doesn't
+    /// stop a development build from running. The error can still be found with
+    /// `last_load_error`. This is off by default.
+    pub fn set_placeholder_on_load_failure(&mut self, enabled: bool) {
+        self.placeholder_on_load_failure = enabled;
+    }
+
+    /// Return the error from the most recent `load_image` or `load_image_from_file` call that
+    /// failed, even if a placeholder was returned in its place.
+    pub fn last_load_error(&self) -> Option<String> {
+        self.last_load_error.borrow().clone()
+    }
+
+    /// Record a failed load, and replace it with a placeholder if that's enabled. `path` is the
+    /// file the image came from, if it came from one.
+    fn or_placeholder(
+        &self,
+        image: Result<Image, SimpleError>,
+        path: Option<&Path>,
+    ) -> Result<Image, SimpleError> {
+        let error = match image {
+            Ok(image) => return Ok(image),
+            Err(error) => error,
+        };
+        let error = match path {
+            Some(path) => error.in_file(path),
+            None => error,
+        };
+        *self.last_load_error.borrow_mut() = Some(error.to_string());
+        if !self.placeholder_on_load_failure {
+            return Err(error);
+        }
+
+        let mut surf = util::new_rgba_surface(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE)?;
+        let bounds = shape::Rect::new(0, 0, PLACEHOLDER_SIZE, PLACEHOLDER_SIZE);
+        for (square, odd) in util::checker_cells(bounds, PLACEHOLDER_SIZE / 8) {
+            surf.fill_rect(square, placeholder_color(odd))?;
+        }
+        Ok(self.image_from_surface(surf)?)
+    }
+
+    /// Start loading the image at `path` in the background. The file is decoded on a worker
+    /// thread, and the texture is created on the main thread during a later `next_frame`,
+    /// `begin_frame` or `wait_event`, which the worker wakes when it is done.
+    ///
+    /// Every request produces exactly one event: `Event::ImageLoaded { key }` once the Image is
+    /// ready to be collected with `take_image(key)`, or `Event::ImageLoadFailed { key, error }`.
+    /// Requests may complete in any order. If the Window quits first, pending results are
+    /// silently dropped.
+    pub fn load_image_async(&mut self, key: String, path: impl AsRef<Path>) {
+        let sender = self.image_load_sender.clone();
+        let wake = self.image_load_event;
+        let path = path.as_ref().to_path_buf();
+        thread::spawn(move || {
+            let decoded = error::check_readable(&path)
+                .and_then(|()| decode::decode_image_file(&path).map_err(SimpleError::ImageLoad))
+                .map_err(|e| e.in_file(&path).to_string());
+            // the Window may already be gone, in which case nobody wants the result
+            if sender.send((key, decoded)).is_ok() {
+                push_wake_event(wake);
+            }
+        });
+    }
+
+    /// Remove and return an Image loaded by `load_image_async`, once its `ImageLoaded` event has
+    /// been queued.
+    pub fn take_image(&mut self, key: &str) -> Option<Image> {
+        self.loaded_images.remove(key)
+    }
+
+    /// Upload every image that worker threads have finished decoding, and queue completion events.
+    fn finish_image_loads(&mut self) {
+        while let Ok((key, decoded)) = self.image_load_receiver.try_recv() {
+            let image = decoded.and_then(|decoded| {
+                let surf =
+                    util::rgba_surface_from_bytes(&decoded.pixels, decoded.width, decoded.height)?;
+                self.image_from_surface(surf)
+            });
+            match image {
+                Ok(image) => {
+                    self.loaded_images.insert(key.clone(), image);
+                    self.queue_event(Event::ImageLoaded { key });
+                }
+                Err(error) => self.queue_event(Event::ImageLoadFailed { key, error }),
+            }
+        }
+    }
+
+    /// Upload the Surface to the GPU as a texture ready for alpha-blended drawing.
+    pub(crate) fn texture_from_surface(
+        &self,
+        surf: &surface::SurfaceRef,
+    ) -> Result<texture::Texture, String> {
+        let mut texture = match self
+            .canvas
+            .texture_creator()
+            .create_texture_from_surface(surf)
+        {
+            Ok(t) => t,
+            Err(e) => return Err(e.to_string()),
+        };
+        texture.set_blend_mode(render::BlendMode::Blend);
+        Ok(texture::Texture::new(texture, &self.textures))
+    }
+
+    /// Make `image`, which may have been made by another Window, drawable by this one, uploading
+    /// its pixels again from the copy it keeps in main memory. Images can only be drawn by the
+    /// Window that made them; this is how they are carried over to a new one, for instance
+    /// after closing a Window to open it again in another mode. Drawing an Image that hasn't been
+    /// adopted draws nothing. Images this Window made are left alone. A render target stays one,
+    /// holding what was last drawn on it with `draw_to_image`.
+    pub fn adopt_image(&self, image: &mut Image) -> Result<(), SimpleError> {
+        if image.texture.belongs_to(&self.textures) {
+            return Ok(());
+        }
+        if image.target.is_some() {
+            image.texture = self.target_from_surface(&image.surface)?;
+            image.target = Some(self.textures.drawn_now());
+        } else {
+            image.texture = self.texture_from_surface(&image.surface)?;
+        }
+        Ok(())
+    }
+
+    /// Like `adopt_image`, for a Font: make its texture again from its pixels, read back from the
+    /// Window that made it, or just before that Window closed if the Font outlived it. The
+    /// enlarged copies that `set_crisp_text` made are dropped, to be made again when needed.
+    pub fn adopt_font(&self, font: &mut Font) -> Result<(), SimpleError> {
+        if !font.texture.belongs_to(&self.textures) {
+            let texture = {
+                let pixels = font.pixels().map_err(SimpleError::Sdl)?;
+                let (ref pixels, width, height) = *pixels;
+                self.texture_from_rgba(pixels, width, height)?
+            };
+            texture.read_on_close(&font.pixels);
+            font.texture = texture;
+            font.hollow_texture = None;
+            font.upscaled.clear();
+        }
+        Ok(())
+    }
+
+    /// Load an image from a slice of bytes. This function is particularly powerful when
+    /// used in conjunction with the `include_bytes` macro that embeds data in the compiled
+    /// executable. In this way, you can pack all of your game data into your executable.
+    pub fn load_image(&self, data: &[u8]) -> Result<Image, SimpleError> {
+        let image = rwops::RWops::from_bytes(data)
+            .and_then(|rwops| {
+                let mut surf: surface::Surface = rwops.load()?;
+                util::to_rgba_surface(&mut surf)
+            })
+            .map_err(SimpleError::ImageLoad)
+            .and_then(|surf| Ok(self.image_from_surface(surf)?));
+        self.or_placeholder(image, None)
+    }
+
+    /// Load an image from `len` bytes of `bundle` starting at `offset`, without copying them. This
+    /// is for games that pack their assets into one file, perhaps embedded with `include_bytes`.
+    pub fn load_image_at(
+        &self,
+        bundle: &[u8],
+        offset: usize,
+        len: usize,
+    ) -> Result<Image, SimpleError> {
+        let data = bundle_slice(bundle, offset, len)?;
+        self.load_image(data).map_err(decode_failed)
+    }
+
+    /// Load an image from everything that is left in `reader`. The bytes are read into one
+    /// buffer, then decoded as with `load_image`.
+    pub fn load_image_from_reader<R: Read>(&self, reader: R) -> Result<Image, SimpleError> {
+        let data = read_all(reader)?;
+        self.load_image(&data).map_err(decode_failed)
+    }
+
+    /// Return a new Image showing `overlay` drawn on top of `base` with its top-left corner at
+    /// (x, y), blended by the overlay's alpha. The result is the size of `base`; any part of
+    /// `overlay` that hangs over the edge is cut off. Neither Image is changed.
+    ///
+    /// This works on the CPU copies the Images keep of their pixels, so the result gets one too and
+    /// can be cropped or composited again.
+    pub fn composite(
+        &self,
+        base: &Image,
+        overlay: &Image,
+        x: i32,
+        y: i32,
+    ) -> Result<Image, SimpleError> {
+        let mut result = util::rgba_surface_from_bytes(
+            &util::rgba_surface_bytes(&base.surface),
+            base.width,
+            base.height,
+        )?;
+        let mut top = util::rgba_surface_from_bytes(
+            &util::rgba_surface_bytes(&overlay.surface),
+            overlay.width,
+            overlay.height,
+        )?;
+        top.set_blend_mode(render::BlendMode::Blend)?;
+        top.blit(
+            None,
+            &mut result,
+            shape::Rect::new(x, y, overlay.width, overlay.height),
+        )?;
+        Ok(self.image_from_surface(result)?)
+    }
+
+    /// Upload an image decoded with `decode_image`, possibly on another thread, to the GPU. This
+    /// only copies the pixels, so it is quick; it must be called on the Window's thread.
+    pub fn image_from_decoded(&self, decoded: &decode::DecodedImage) -> Result<Image, SimpleError> {
+        let surf = util::rgba_surface_from_bytes(&decoded.pixels, decoded.width, decoded.height)?;
+        Ok(self.image_from_surface(surf)?)
+    }
+
+    /// Upload a font decoded with `decode_font`, possibly on another thread, to the GPU, like
+    /// `image_from_decoded`.
+    pub fn font_from_decoded(&self, decoded: &decode::DecodedFont) -> Result<Font, SimpleError> {
+        let image = &decoded.image;
+        let font = self.font_from_pixels(
+            &image.pixels,
+            image.width,
+            image.height,
+            decoded.glyphs.clone(),
+        )?;
+        Ok(font)
+    }
+
+    /// Make a Font from its image as RGBA pixels and the glyph rectangles found in it.
+    fn font_from_pixels(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        chars: HashMap<char, shape::Rect>,
+    ) -> Result<Font, String> {
+        let texture = self.texture_from_rgba(pixels, width, height)?;
+        let kept = Rc::new(RefCell::new(None));
+        texture.read_on_close(&kept);
+        Ok(Font {
+            texture,
+            hollow_texture: None,
+            chars,
+            height,
+            kerning: HashMap::new(),
+            pixels: kept,
+            upscaled: HashMap::new(),
+        })
+    }
+
+    /// Make a texture from RGBA pixels.
+    fn texture_from_rgba(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<texture::Texture, String> {
+        let surf = util::rgba_surface_from_bytes(pixels, width, height)?;
+        self.texture_from_surface(&surf)
+    }
+
+    /// Make the texture of the current font enlarged `factor` times, and the outlines of its
+    /// glyphs at that size if `hollow`, unless they were made before. If that fails, text is drawn
+    /// from the ordinary texture instead.
+    fn prepare_font(&mut self, factor: u32, hollow: bool) {
+        let Some(mut font) = self.font.take() else {
+            return;
+        };
+        if font.pixels().is_err() {
+            self.font = Some(font);
+            return;
+        }
+        let kept = Rc::clone(&font.pixels);
+        let kept = kept.borrow();
+        let (ref pixels, width, height) = *kept.as_ref().unwrap();
+        let enlarged = |pixels: &[u8]| {
+            let big = util::upscale_rgba(pixels, width, factor);
+            self.texture_from_rgba(&big, width * factor, height * factor)
+        };
+        if factor > 1
+            && !font.upscaled.contains_key(&factor)
+            && let Ok(texture) = enlarged(pixels)
+        {
+            font.upscaled.insert(factor, (texture, None));
+        }
+        let outlines = match factor {
+            1 => Some(&mut font.hollow_texture),
+            _ => font.upscaled.get_mut(&factor).map(|(_, outlines)| outlines),
+        };
+        if hollow && let Some(outlines @ None) = outlines {
+            let edges = font::hollow_pixels(pixels, width, height, font.chars.values());
+            *outlines = enlarged(&edges).ok();
+        }
+        self.font = Some(font);
+    }
+
+    /// Turn an RGBA Surface into an Image, keeping the Surface as the Image's CPU-side copy.
+    pub(crate) fn image_from_surface(
+        &self,
+        surf: surface::Surface<'static>,
+    ) -> Result<Image, String> {
+        let texture = self.texture_from_surface(&surf)?;
+        Ok(Image {
+            width: surf.width(),
+            height: surf.height(),
+            texture,
+            surface: surf,
+            blend_mode: None,
+            target: None,
+        })
+    }
+
+    /// Make a transparent Image, `width` by `height`, that can be drawn on with `draw_to_image`
+    /// and then drawn every frame like any other Image: to bake a background, or anything else
+    /// that is slow to draw and rarely changes.
+    ///
+    /// The renderer can lose what was drawn on it, for instance when the Window goes fullscreen.
+    /// `Event::RenderTargetsLost` then says to draw it again; until then it draws as a
+    /// checkerboard rather than as whatever the texture holds. The checkerboard is upright and
+    /// fills the box the Image would cover unrotated, whatever rotation or flip it is drawn with.
+    /// Returns an error if the renderer can't draw to textures, or if either size is zero.
+    pub fn create_render_target(&self, width: u32, height: u32) -> Result<Image, SimpleError> {
+        if !self.canvas.render_target_supported() {
+            return Err(SimpleError::Unsupported(
+                "render targets need a renderer that can draw to textures".to_string(),
+            ));
+        }
+        if width == 0 || height == 0 {
+            return Err(SimpleError::InvalidArgument(format!(
+                "can't make a {}x{} render target",
+                width, height
+            )));
+        }
+        let surf = util::new_rgba_surface(width, height)?;
+        Ok(Image {
+            texture: self.target_from_surface(&surf)?,
+            surface: surf,
+            width,
+            height,
+            blend_mode: None,
+            target: Some(self.textures.drawn_now()),
+        })
+    }
+
+    /// Call `f` to draw on `image`, a render target made by `create_render_target`, instead of on
+    /// the screen. Inside `f` there is no viewport or draw offset and no layer is selected; they,
+    /// the clip rect and the scale are put back afterwards. What was drawn is kept in the Image's
+    /// copy of its pixels too, so `crop`, `collision_mask` and the like see it. `f` should only
+    /// draw: ending the frame or turning on partial redraw from inside it draws in the wrong
+    /// place.
+    ///
+    /// Returns an error, without calling `f`, if `image` isn't a render target this Window made
+    /// or if `draw_to_image` is already running.
+    pub fn draw_to_image(
+        &mut self,
+        image: &mut Image,
+        f: impl FnOnce(&mut Window),
+    ) -> Result<(), SimpleError> {
+        if image.target.is_none() || !image.texture.belongs_to(&self.textures) {
+            return Err(SimpleError::InvalidArgument(
+                "can only draw to a render target this Window made".to_string(),
+            ));
+        }
+        if self.image_target.is_some() {
+            return Err(SimpleError::InvalidArgument(
+                "already drawing to an image".to_string(),
+            ));
+        }
+        self.flush_batch();
+        let (clip, scale) = (self.canvas.clip_rect(), self.canvas.scale());
+        let (viewport, offset) = (self.viewport.take(), std::mem::take(&mut self.draw_offset));
+        let layer = self.layers.pause();
+        self.image_target = Some(layer::texture_of(
+            &self.canvas,
+            layer::TextureId::of(&image.texture),
+        ));
+        self.target_retained_frame(None, (1.0, 1.0));
+
+        f(self);
+
+        // the whole target is read with nothing `f` left set in the way
+        self.flush_batch();
+        self.viewport = None;
+        self.target_retained_frame(None, (1.0, 1.0));
+        let pixels = self
+            .canvas
+            .read_pixels(None, pixels::PixelFormatEnum::RGBA32);
+        self.image_target = None;
+        self.layers.resume(layer);
+        self.viewport = viewport;
+        self.draw_offset = offset;
+        set_render_target(&mut self.canvas, None);
+        self.target_retained_frame(clip, scale);
+        image.surface = util::rgba_surface_from_bytes(&pixels?, image.width, image.height)?;
+        image.target = Some(self.textures.drawn_now());
+        Ok(())
+    }
+
+    /// Make a render target texture holding the pixels of `surf`, ready for alpha-blended drawing.
+    fn target_from_surface(&self, surf: &surface::SurfaceRef) -> Result<texture::Texture, String> {
+        let texture = self
+            .canvas
+            .texture_creator()
+            .create_texture_target(None, surf.width(), surf.height())
+            .map_err(|e| e.to_string())?;
+        let mut texture = texture::Texture::new(texture, &self.textures);
+        // an Image's copy of its pixels doesn't blend, so blitting it copies them exactly
+        let (width, height) = (surf.width(), surf.height());
+        let mut converted = surface::Surface::new(width, height, texture.query().format)?;
+        surf.blit(None, &mut converted, None)?;
+        let pitch = converted.pitch() as usize;
+        converted
+            .with_lock(|pixels| texture.update(None, pixels, pitch))
+            .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(render::BlendMode::Blend);
+        Ok(texture)
+    }
+
+    /// Parse a font from the Surface, using the string as a guideline.
+    fn parse_image_font(
+        &self,
+        surf: surface::Surface,
+        string: String,
+    ) -> Result<Font, SimpleError> {
+        let mut surf = surf;
+        let surf = util::to_rgba_surface(&mut surf).map_err(SimpleError::FontParse)?;
+        let chars = font::parse_glyphs(&surf, &string).map_err(SimpleError::FontParse)?;
+        let pixels = util::rgba_surface_bytes(&surf);
+        Ok(self.font_from_pixels(&pixels, surf.width(), surf.height(), chars)?)
+    }
+
+    /// Load a Font from the hard drive. See the documentation on `Font` for details. If it fails,
+    /// the error is a `SimpleError::File` that names the path, around an `Io` error if the file
+    /// couldn't be opened.
+    pub fn load_font_from_file(
+        &self,
+        filename: impl AsRef<Path>,
+        string: String,
+    ) -> Result<Font, SimpleError> {
+        let filename = filename.as_ref();
+        error::check_readable(filename)
+            .and_then(|()| LoadSurface::from_file(filename).map_err(SimpleError::FontParse))
+            .and_then(|surf| self.parse_image_font(surf, string))
+            .map_err(|e| e.in_file(filename))
+    }
+
+    /// Load a Font from a slice of bytes. See the documentation on `Font` for details. This
+    /// function is particularly powerful when used in conjunction with the `include_bytes` macro
+    /// that embeds data in the compiled executable.
+    pub fn load_font(&self, data: &[u8], string: String) -> Result<Font, SimpleError> {
+        let rwops = rwops::RWops::from_bytes(data).map_err(SimpleError::FontParse)?;
+        let surf: surface::Surface = rwops.load().map_err(SimpleError::FontParse)?;
+        self.parse_image_font(surf, string)
+    }
+
+    /// Load a Font from `len` bytes of `bundle` starting at `offset`, like `load_image_at`.
+    pub fn load_font_at(
+        &self,
+        bundle: &[u8],
+        offset: usize,
+        len: usize,
+        string: String,
+    ) -> Result<Font, SimpleError> {
+        let data = bundle_slice(bundle, offset, len)?;
+        self.load_font(data, string).map_err(decode_failed)
+    }
+
+    /// Load a Font from everything that is left in `reader`, like `load_image_from_reader`.
+    pub fn load_font_from_reader<R: Read>(
+        &self,
+        reader: R,
+        string: String,
+    ) -> Result<Font, SimpleError> {
+        let data = read_all(reader)?;
+        self.load_font(&data, string).map_err(decode_failed)
+    }
+}
+
+#[test]
+fn test_display_available_with_window() {
+    if !is_display_available() {
+        return;
+    }
+    let window = Window::new("Test", 32, 32);
+    assert!(is_display_available());
+    // probing again must not have stopped the Window's video subsystem
+    assert!(window.drawable_size() == (32, 32));
+}
+
+#[test]
+fn test_get_drawable() {
+    let width: u32 = 234;
+    let height: u32 = 321;
+    // TODO: Rationalise the use of `u16` V. `u32`
+    let window = Window::new("Test", width as u16, height as u16);
+    let dble = window.drawable_size();
+    assert!(width == dble.0);
+    assert!(height == dble.1);
+}
+
+#[test]
+fn test_atlas_matches_separate_images() {
+    let mut window = Window::new("Test", 1024, 64);
+
+    let mut builder = atlas::AtlasBuilder::new();
+    builder.set_max_texture_size(1024);
+    builder.add_image("first", DEFAULT_FONT_BYTES);
+    builder.add_image("second", DEFAULT_FONT_BYTES);
+    let atlas = builder.build(&window).unwrap();
+    assert!(atlas.page_count() == 1);
+
+    let read = |window: &Window| {
+        window
+            .canvas
+            .read_pixels(None, pixels::PixelFormatEnum::RGBA32)
+            .unwrap()
+    };
+
+    window.clear();
+    let first = atlas.region("first").unwrap();
+    let second = atlas.region("second").unwrap();
+    window.draw_region(&atlas, first, 0, 0);
+    window.draw_region(&atlas, second, 0, 32);
+    let packed = read(&window);
+
+    window.clear();
+    let image = window.load_image(DEFAULT_FONT_BYTES).unwrap();
+    window.draw_image(&image, 0, 0);
+    window.draw_image(&image, 0, 32);
+    let separate = read(&window);
+
+    assert!(packed == separate);
+}
 
 #[test]
-fn test_get_drawable() {
-    let width: u32 = 234;
-    let height: u32 = 321;
-    // TODO: Rationalise the use of `u16` V. `u32`
-    let window = Window::new("Test", width as u16, height as u16);
-    let dble = window.drawable_size();
-    assert!(width == dble.0);
-    assert!(height == dble.1);
+fn test_image_crop() {
+    let window = Window::new("Test", 64, 64);
+    let image = window.load_image(DEFAULT_FONT_BYTES).unwrap();
+
+    let cropped = image.crop(&window, shape::Rect::new(10, 2, 20, 5)).unwrap();
+    assert!(cropped.get_width() == 20);
+    assert!(cropped.get_height() == 5);
+
+    let too_far = shape::Rect::new(image.get_width() as i32 - 5, 0, 10, 1);
+    assert!(image.crop(&window, too_far).is_err());
+    assert!(image.crop(&window, shape::Rect::new(-1, 0, 2, 2)).is_err());
+}
+
+#[test]
+fn test_viewport_then_clip() {
+    let mut window = Window::new("Test", 64, 64);
+    window.clear_to_color(0, 0, 0);
+    window.set_color(255, 255, 255, 255);
+    window.with_viewport(shape::Rect::new(32, 16, 32, 32), |w| {
+        w.set_clip_rect(Some(shape::Rect::new(4, 4, 8, 8)));
+        w.fill_rect(shape::Rect::new(0, 0, 64, 64));
+    });
+    assert!(window.viewport().is_none());
+    assert!(window.canvas.clip_rect().is_none());
+
+    let pixels = window
+        .canvas
+        .read_pixels(None, pixels::PixelFormatEnum::RGBA32)
+        .unwrap();
+    let is_white = |x: usize, y: usize| pixels[(y * 64 + x) * 4] == 255;
+    assert!(is_white(36, 20) && is_white(43, 27));
+    assert!(!is_white(35, 20) && !is_white(44, 27) && !is_white(36, 19) && !is_white(36, 28));
+    assert!(!is_white(4, 4));
+}
+
+#[test]
+fn test_save_restore_state() {
+    let mut window = Window::new("Test", 64, 64);
+    window.restore_state(); // nothing saved: no-op
+
+    window.set_color(1, 2, 3, 4);
+    window.save_state();
+    window.set_color(9, 9, 9, 9);
+    window.set_viewport(Some(shape::Rect::new(8, 8, 16, 16)));
+    window.set_clip_rect(Some(shape::Rect::new(0, 0, 4, 4)));
+    window.set_blend_mode(render::BlendMode::Add);
+    window.set_scale(2.0, 2.0).unwrap();
+
+    window.save_state();
+    window.set_color(5, 5, 5, 5);
+    window.restore_state();
+    assert!(window.foreground_color == pixels::Color::RGBA(9, 9, 9, 9));
+
+    window.restore_state();
+    assert!(window.foreground_color == pixels::Color::RGBA(1, 2, 3, 4));
+    assert!(window.viewport().is_none());
+    assert!(window.canvas.clip_rect().is_none());
+    assert!(window.canvas.blend_mode() == render::BlendMode::Blend);
+    assert!(window.canvas.scale() == (1.0, 1.0));
+}
+
+#[test]
+fn test_placeholder_on_load_failure() {
+    let mut window = Window::new("Test", 64, 64);
+    assert!(window.load_image(b"not an image").is_err());
+    assert!(window.last_load_error().is_some());
+
+    window.set_placeholder_on_load_failure(true);
+    let image = window.load_image(b"not an image").unwrap();
+    assert!(image.get_width() == 64 && image.get_height() == 64);
+    assert!(window.load_image(DEFAULT_FONT_BYTES).unwrap().get_height() == 17);
+}
+
+#[test]
+fn test_load_images_names_the_failing_path() {
+    let dir = std::env::temp_dir().join("simple-test-load-images");
+    std::fs::create_dir_all(&dir).unwrap();
+    let good = dir.join("good.png");
+    std::fs::write(&good, DEFAULT_FONT_BYTES).unwrap();
+    let missing = dir.join("missing.png");
+
+    let window = Window::new("Test", 64, 64);
+    assert!(window.load_images(&[&good, &good]).unwrap().len() == 2);
+    let (index, error) = match window.load_images(&[&good, &missing, &good]) {
+        Err(failure) => failure,
+        Ok(_) => panic!("loading a missing file succeeded"),
+    };
+    assert!(index == 1);
+    assert!(
+        error
+            .to_string()
+            .starts_with(&missing.display().to_string())
+    );
+    assert!(window.last_load_error() == Some(error.to_string()));
+
+    let error = window.load_font_from_file(&missing, "a".to_string()).err();
+    assert!(error.unwrap().to_string().contains("missing.png"));
+
+    // a file that isn't there is an I/O error, one that doesn't decode is an image error
+    let in_file = |e: Option<SimpleError>| match e {
+        Some(SimpleError::File { error, .. }) => *error,
+        e => panic!("expected a File error, got {:?}", e),
+    };
+    let error = in_file(window.load_image_from_file(&missing).err());
+    assert!(matches!(error, SimpleError::Io(_)));
+    let error = in_file(window.load_font_from_file(&missing, "a".to_string()).err());
+    assert!(matches!(error, SimpleError::Io(_)));
+    let garbage = dir.join("garbage.png");
+    std::fs::write(&garbage, b"not an image").unwrap();
+    let error = in_file(window.load_image_from_file(&garbage).err());
+    assert!(matches!(error, SimpleError::ImageLoad(_)));
+    let error = window
+        .load_image_from_reader(&b"not an image"[..])
+        .err()
+        .unwrap();
+    assert!(matches!(error, SimpleError::ImageLoad(_)));
+    assert!(error.to_string().starts_with("decode failed: "));
+}
+
+#[test]
+fn test_centered() {
+    assert!(centered(shape::Point::new(10, 10), 4, 6) == (8, 7));
+    // odd sizes put the extra pixel right of and below the center
+    assert!(centered(shape::Point::new(10, 10), 5, 1) == (8, 10));
+    assert!(centered(shape::Point::new(0, 0), 0, 0) == (0, 0));
+    // huge sizes clamp instead of overflowing
+    assert!(centered(shape::Point::new(i32::MIN, 0), u32::MAX, 2).0 == i32::MIN);
+}
+
+#[test]
+fn test_scale_quality_hint_values() {
+    assert!(ScaleQuality::Nearest.hint_value() == "0");
+    assert!(ScaleQuality::Linear.hint_value() == "1");
+    assert!(ScaleQuality::Best.hint_value() == "2");
+}
+
+#[test]
+fn test_layers_draw_in_index_order() {
+    let mut window = Window::new("Test", 16, 16);
+    window.clear_to_color(0, 0, 0);
+
+    window.layer(1);
+    window.set_color(255, 0, 0, 255);
+    window.fill_rect(shape::Rect::new(0, 0, 16, 16));
+    window.layer(0);
+    window.set_color(0, 0, 255, 255);
+    window.fill_rect(shape::Rect::new(0, 0, 16, 16));
+
+    let read = |window: &Window| {
+        window
+            .canvas
+            .read_pixels(None, pixels::PixelFormatEnum::RGBA32)
+            .unwrap()
+    };
+    // nothing has been drawn yet
+    assert!(read(&window)[..3] == [0, 0, 0]);
+    window.flush_layers();
+    assert!(read(&window)[..3] == [255, 0, 0]);
+}
+
+#[test]
+fn test_image_alpha_follows_draw_color() {
+    let mut window = Window::new("Test", 8, 8);
+    let white = util::rgba_surface_from_bytes(&[255; 8 * 8 * 4], 8, 8).unwrap();
+    let image = window.image_from_surface(white).unwrap();
+
+    window.clear_to_color(0, 0, 0);
+    window.set_color(255, 255, 255, 128);
+    window.draw_image(&image, 0, 0);
+    let pixels = window
+        .canvas
+        .read_pixels(None, pixels::PixelFormatEnum::RGBA32)
+        .unwrap();
+    assert!(pixels[..3].iter().all(|&c| (126..=130).contains(&c)));
+
+    // the alpha doesn't stick to the image once the color is opaque again
+    window.clear_to_color(0, 0, 0);
+    window.set_color(255, 255, 255, 255);
+    window.draw_image(&image, 0, 0);
+    let pixels = window
+        .canvas
+        .read_pixels(None, pixels::PixelFormatEnum::RGBA32)
+        .unwrap();
+    assert!(pixels[..3] == [255, 255, 255]);
+}
+
+#[test]
+fn test_read_pixels() {
+    let mut window = Window::new("Test", 16, 8);
+    window.clear_to_color(0, 0, 0);
+    window.set_color(10, 20, 30, 255);
+    window.fill_rect(shape::Rect::new(4, 2, 2, 2));
+
+    let (pixels, width, height) = window.read_pixels(None).unwrap();
+    assert!((width, height) == (16, 8));
+    assert!(pixels.len() == 16 * 8 * 4);
+    assert!(pixels[(2 * 16 + 4) * 4..][..4] == [10, 20, 30, 255]);
+
+    let (pixels, width, height) = window
+        .read_pixels(Some(shape::Rect::new(4, 2, 3, 1)))
+        .unwrap();
+    assert!((width, height) == (3, 1));
+    assert!(pixels == [10, 20, 30, 255, 10, 20, 30, 255, 0, 0, 0, 255]);
+}
+
+#[test]
+fn test_partial_redraw_keeps_clean_regions() {
+    let mut window = Window::new("Test", 16, 8);
+    window.set_partial_redraw(true).unwrap();
+    assert!(window.is_dirty(shape::Rect::new(10, 5, 1, 1)));
+    window.clear_to_color(200, 0, 0);
+    window.next_frame();
+
+    // only the marked region is cleared; the rest of the last frame stays
+    window.mark_dirty(shape::Rect::new(0, 0, 4, 4));
+    assert!(!window.is_dirty(shape::Rect::new(10, 5, 1, 1)));
+    window.clear();
+    let (pixels, _, _) = window.read_pixels(None).unwrap();
+    assert!(pixels[..4] == [0, 0, 0, 255]);
+    assert!(pixels[(5 * 16 + 10) * 4..][..4] == [200, 0, 0, 255]);
+
+    window.set_partial_redraw(false).unwrap();
+    assert!(window.is_dirty(shape::Rect::new(10, 5, 1, 1)));
+}
+
+#[test]
+fn test_point_mapping() {
+    let identity = PointMapping {
+        bars: (0, 0),
+        scale: (1.0, 1.0),
+    };
+    assert!(identity.to_drawing((17, -3)) == (17, -3));
+
+    // a 100x60 design scaled 3 times in a 320x200 Window leaves bars of 10 pixels on each side
+    let pixel_perfect = PointMapping {
+        bars: (10, 10),
+        scale: (3.0, 3.0),
+    };
+    assert!(pixel_perfect.to_drawing((10, 10)) == (0, 0));
+    assert!(pixel_perfect.to_drawing((12, 12)) == (0, 0));
+    assert!(pixel_perfect.to_drawing((13, 40)) == (1, 10));
+    assert!(pixel_perfect.to_drawing((309, 189)) == (99, 59));
+    // the bars are outside the design
+    assert!(pixel_perfect.to_drawing((0, 9)) == (-4, -1));
+    // warping to a design pixel puts the pointer in the middle of it
+    assert!(pixel_perfect.to_window((0, 0)) == (11, 11));
+    assert!(pixel_perfect.to_window((99, -4)) == (308, -1));
+    for point in [(0, 0), (5, 7), (99, 59), (-4, -1)] {
+        assert!(pixel_perfect.to_drawing(pixel_perfect.to_window(point)) == point);
+        assert!(identity.to_window(point) == point);
+    }
+
+    let scaled = PointMapping {
+        bars: (0, 0),
+        scale: (2.0, 0.5),
+    };
+    assert!(scaled.to_drawing((9, 9)) == (4, 18));
+    assert!(scaled.to_window((4, 18)) == (9, 9));
+    assert!(scaled.to_drawing((i32::MIN, i32::MAX)).0 == i32::MIN / 2);
+}
+
+#[test]
+fn test_pixel_perfect_point_mapping() {
+    let mut window = Window::new("Test", 320, 200);
+    window.set_scale(2.0, 4.0).unwrap();
+    assert!(window.point_mapping().to_drawing((9, 9)) == (4, 2));
+    window.enable_pixel_perfect(100, 60).unwrap();
+    let mapping = window.point_mapping();
+    assert!(mapping.bars == (10, 10) && mapping.scale == (3.0, 3.0));
+}
+
+#[test]
+fn test_background_rects() {
+    let stretch = background_rects(10, 10, 40, 20, BackgroundFit::Stretch);
+    assert!(stretch == [shape::Rect::new(0, 0, 40, 20)]);
+
+    // a square image covering a wide area is cut off at the top and bottom
+    let cover = background_rects(10, 10, 40, 20, BackgroundFit::Cover);
+    assert!(cover == [shape::Rect::new(0, -10, 40, 40)]);
+
+    let tile = background_rects(16, 16, 40, 20, BackgroundFit::Tile);
+    assert!(tile.len() == 6);
+    assert!(tile[5] == shape::Rect::new(32, 16, 16, 16));
+
+    assert!(background_rects(0, 10, 40, 20, BackgroundFit::Tile).is_empty());
+}
+
+#[test]
+fn test_caret_matches_print() {
+    let mut window = Window::new("Test", 8, 8);
+    let text = "ab\u{1}c";
+    let width = window.measure_text(text).0 as i32;
+    assert!(window.caret_x(text, 0, 10) == 10);
+    assert!(window.caret_x(text, 4, 10) == 10 + width);
+    assert!(window.caret_x(text, 99, 10) == 10 + width);
+    assert!(window.caret_x(text, 2, 0) == window.measure_text("ab").0 as i32);
+
+    window.set_missing_glyph_mode(font::MissingGlyphMode::Box);
+    for i in 0..=4 {
+        let x = window.caret_x(text, i, 10);
+        assert!(window.char_index_at(text, 10, x) == i);
+    }
+}
+
+#[test]
+fn test_auto_clear() {
+    let mut window = Window::new("Test", 8, 8);
+    window.set_color(200, 200, 200, 255);
+    window.fill_rect(shape::Rect::new(0, 0, 8, 8));
+    window.set_clear_color(10, 20, 30);
+    window.set_auto_clear(true);
+    window.next_frame();
+    let (pixels, _, _) = window.read_pixels(None).unwrap();
+    assert!(pixels[..4] == [10, 20, 30, 255]);
+}
+
+#[test]
+fn test_draw_stats() {
+    let mut window = Window::new("Test", 64, 64);
+    window.next_frame();
+    window.fill_rect(shape::Rect::new(0, 0, 8, 8));
+    // the same color again isn't a state change
+    window.draw_point(shape::Point::new(9, 9));
+    window.layer(1);
+    window.print("ab", 0, 0);
+    window.set_blend_mode(BlendMode::Add);
+    // culled, so never drawn
+    window.print("c", 1000, 1000);
+    window.next_frame();
+    let stats = window.draw_stats();
+    assert!(stats.primitives == 2);
+    assert!(stats.texture_copies == 2);
+    assert!(stats.glyphs == 2);
+    assert!(stats.state_changes == 4);
+
+    window.next_frame();
+    assert!(window.draw_stats() == DrawStats::default());
+}
+
+#[test]
+fn test_print_fmt_matches_print() {
+    let mut window = Window::new("Test", 64, 16);
+    let score = 1234;
+    let expected = window.print(&format!("Score: {}", score), 0, 0);
+    let (expected_pixels, _, _) = window.read_pixels(None).unwrap();
+
+    window.clear();
+    // a longer string first, so the buffer has something left over to leak
+    window.print_fmt(
+        format_args!("{}", "a much longer string than the score"),
+        0,
+        20,
+    );
+    window.clear();
+    assert!(window.print_fmt(format_args!("Score: {}", score), 0, 0) == expected);
+    assert!(window.read_pixels(None).unwrap().0 == expected_pixels);
+    assert!(window.print_display(&score, 0, 0) == window.print("1234", 0, 0));
+}
+
+#[test]
+fn test_print_rtl_mirrors_print() {
+    let mut window = Window::new("Test", 64, 16);
+    let bounds = window.print("ab", 2, 0);
+    let (ltr, _, _) = window.read_pixels(None).unwrap();
+    window.clear();
+    assert!(window.print_rtl("ba", bounds.right(), 0) == bounds);
+    // "b" then "a" from the right looks like "ab" from the left, glyph for glyph
+    assert!(window.read_pixels(None).unwrap().0 == ltr);
+}
+
+#[test]
+fn test_tab_stops() {
+    let mut window = Window::new("Test", 64, 16);
+    let four_spaces = window.measure_text("    ").0;
+    assert!(window.measure_text("\t").0 == four_spaces);
+
+    window.set_tab_width(40);
+    assert!(window.measure_text("\t").0 == 40);
+    assert!(window.measure_text("ab\t").0 == 40);
+    assert!(window.measure_text("\t\tx").0 == 80 + window.measure_text("x").0);
+    assert!(window.print("a\tb", 0, 0).width() == 40 + window.measure_text("b").0);
+    assert!(window.caret_x("a\tb", 2, 5) == 45);
+    assert!(window.measure_text_ex("\t", 2.0, 0).0 == 80);
+
+    window.set_tab_width(0);
+    assert!(window.measure_text("\t").0 == four_spaces);
+}
+
+#[test]
+fn test_print_hollow_covers_the_same_area() {
+    let mut window = Window::new("Test", 64, 16);
+    assert!(current_font(&window.font).hollow_texture.is_none());
+    let hollow = window.print_hollow("Ab", 3, 4);
+    assert!(current_font(&window.font).hollow_texture.is_some());
+    window.clear();
+    assert!(hollow == window.print("Ab", 3, 4));
+
+    // a font whose only glyph is a solid white 4x4 square, after a separator column
+    let (width, height) = (5, 4);
+    let pixels = vec![255; (width * height * 4) as usize];
+    let chars = HashMap::from([('a', shape::Rect::new(1, 0, 4, 4))]);
+    let font = window
+        .font_from_pixels(&pixels, width, height, chars)
+        .unwrap();
+    window.set_font(font);
+    window.clear();
+    window.print_hollow("a", 0, 0);
+    let (pixels, width, _) = window.read_pixels(None).unwrap();
+    let lit = |x: usize, y: usize| pixels[(y * width as usize + x) * 4] > 0;
+    // only the edges light up, not the middle of the square
+    for y in 0..4 {
+        for x in 0..4 {
+            assert!(lit(x, y) == (x == 0 || x == 3 || y == 0 || y == 3));
+        }
+    }
+}
+
+#[test]
+fn test_performance_counter() {
+    let window = Window::new("Test", 8, 8);
+    let start = window.performance_counter();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let elapsed = window.performance_counter() - start;
+    // at least a millisecond passed, in whatever units the counter uses
+    assert!(elapsed * 1000 >= window.performance_frequency());
+}
+
+#[test]
+fn test_batching_draws_the_same_in_fewer_calls() {
+    let mut window = Window::new("Test", 16, 16);
+    let draw = |window: &mut Window| {
+        window.clear();
+        window.set_color(255, 0, 0, 255);
+        for x in 0..4 {
+            window.fill_rect(shape::Rect::new(x * 4, 0, 4, 4));
+        }
+        window.draw_point(shape::Point::new(0, 8));
+        window.draw_point(shape::Point::new(1, 8));
+        window.set_color(0, 255, 0, 255);
+        window.fill_rect(shape::Rect::new(2, 2, 4, 4));
+        window.set_clip_rect(Some(shape::Rect::new(0, 0, 3, 16)));
+        window.fill_rect(shape::Rect::new(0, 12, 16, 4));
+        window.set_clip_rect(None);
+        window.read_pixels(None).unwrap().0
+    };
+
+    let batched = draw(&mut window);
+    // a clear, then the red rects, the points, the green rect and the clipped one
+    assert!(window.current_draw_stats().primitives == 5);
+    window.next_frame();
+    window.set_batching(false);
+    let unbatched = draw(&mut window);
+    assert!(window.current_draw_stats().primitives == 9);
+    assert!(batched == unbatched);
+    // the green rect was drawn after the red ones, and the clip rect applied to the last one
+    assert!(batched[(2 * 16 + 2) * 4..][..3] == [0, 255, 0]);
+    assert!(batched[(12 * 16 + 3) * 4..][..3] == [0, 0, 0]);
+}
+
+#[test]
+fn test_frame_hooks_see_the_whole_frame() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut window = Window::new("Test", 64, 64);
+    let seen = Rc::new(Cell::new(DrawStats::default()));
+    let seen_by_hook = seen.clone();
+    window.add_frame_hook(move |window| {
+        seen_by_hook.set(window.current_draw_stats());
+        window.fill_rect(shape::Rect::new(0, 0, 1, 1));
+    });
+    window.next_frame();
+
+    window.fill_rect(shape::Rect::new(0, 0, 8, 8));
+    window.layer(3);
+    window.fill_rect(shape::Rect::new(8, 8, 8, 8));
+    window.set_debug_overlay(true);
+    window.next_frame();
+    // the hook ran after the layer was drawn, and before the stats moved to draw_stats
+    assert!(seen.get().primitives == 2);
+    assert!(window.draw_stats().primitives == 3);
+    assert!(window.current_draw_stats() == DrawStats::default());
+}
+
+#[test]
+fn test_kerning() {
+    let mut window = Window::new("Test", 64, 16);
+    let (plain, _) = window.measure_text("AVA");
+    let (v_width, _) = window.measure_text("V");
+    let mut font = window
+        .load_font(DEFAULT_FONT_BYTES, DEFAULT_FONT_STR.to_string())
+        .unwrap();
+    font.set_kerning('A', 'V', -2);
+    assert!(font.kerning('A', 'V') == -2 && font.kerning('V', 'A') == 0);
+    window.set_font(font);
+
+    assert!(window.measure_text("AVA").0 == plain - 2);
+    assert!(window.print("AVA", 0, 0).width() == plain - 2);
+    assert!(window.measure_text_ex("AVA", 2.0, 0).0 == 2 * plain - 4);
+    // the caret after the V moves with it
+    assert!(window.caret_x("AVA", 2, 0) == window.caret_x("A", 1, 0) - 2 + v_width as i32);
+}
+
+#[test]
+fn test_bundle_slice() {
+    let bundle = [1, 2, 3, 4, 5];
+    assert!(bundle_slice(&bundle, 1, 3).unwrap() == [2, 3, 4]);
+    assert!(bundle_slice(&bundle, 5, 0).unwrap().is_empty());
+    assert!(
+        bundle_slice(&bundle, 3, 3)
+            == Err(SimpleError::OutOfRange {
+                offset: 3,
+                len: 3,
+                bundle_len: 5
+            })
+    );
+    assert!(bundle_slice(&bundle, usize::MAX, 2).is_err());
+}
+
+#[test]
+fn test_fill_rect_color_keeps_window_color() {
+    let mut window = Window::new("Test", 8, 8);
+    window.set_color(1, 2, 3, 255);
+    window.fill_rect_color(shape::Rect::new(0, 0, 8, 8), (200, 100, 50, 255));
+    assert!(window.foreground_color == pixels::Color::RGBA(1, 2, 3, 255));
+    let (pixels, _, _) = window.read_pixels(None).unwrap();
+    assert!(pixels[..4] == [200, 100, 50, 255]);
+}
+
+#[test]
+fn test_measure_text_ex_matches_print() {
+    let mut window = Window::new("Test", 64, 64);
+    for &(scale, extra_px) in &[(1.0, 0), (2.0, 0), (1.5, 3), (1.0, -2)] {
+        let text = "Ab, é!"; // é is not in the default font
+        let drawn = if extra_px == 0 {
+            window.print_scaled(text, 3, 4, scale)
+        } else {
+            let style = PrintStyle {
+                scale,
+                extra_px,
+                ..PrintStyle::default()
+            };
+            window.print_styled(text, 3, 4, style)
+        };
+        let measured = window.measure_text_ex(text, scale, extra_px);
+        assert!((drawn.width(), drawn.height()) == measured);
+    }
+    assert!(window.measure_text("Ab") == window.measure_text_ex("Ab", 1.0, 0));
+}
+
+#[test]
+fn test_missing_glyph_modes() {
+    let mut window = Window::new("Test", 64, 64);
+    let gap = window.measure_text("a\u{e9}b").0;
+    assert!(gap == window.measure_text("ab").0 + 5);
+
+    window.set_missing_glyph_mode(font::MissingGlyphMode::ReplacementChar('?'));
+    assert!(window.measure_text("a\u{e9}b") == window.measure_text("a?b"));
+
+    window.set_missing_glyph_mode(font::MissingGlyphMode::Box);
+    let boxed = window.measure_text("a\u{e9}b").0;
+    let average = window.font.as_ref().unwrap().average_width();
+    assert!(boxed == window.measure_text("ab").0 + average);
+    assert!(window.print("a\u{e9}b", 0, 0).width() == boxed);
+}
+
+#[test]
+fn test_composite() {
+    let window = Window::new("Test", 8, 8);
+    let solid = |rgba: [u8; 4], w: u32, h: u32| {
+        let bytes: Vec<u8> = (0..w * h).flat_map(|_| rgba).collect();
+        let surf = util::rgba_surface_from_bytes(&bytes, w, h).unwrap();
+        window.image_from_surface(surf).unwrap()
+    };
+    let base = solid([0, 0, 255, 255], 4, 4);
+    let overlay = solid([255, 0, 0, 128], 2, 2);
+
+    let result = window.composite(&base, &overlay, 3, 3).unwrap();
+    assert!(result.get_width() == 4 && result.get_height() == 4);
+    let pixels = util::rgba_surface_bytes(&result.surface);
+    assert!(pixels[..4] == [0, 0, 255, 255]);
+    let corner = &pixels[(3 * 4 + 3) * 4..][..4];
+    assert!((126..=130).contains(&corner[0]) && (125..=129).contains(&corner[2]));
+    assert!(corner[3] == 255);
+}
+
+#[test]
+fn test_crisp_factor() {
+    assert!(crisp_factor((1.0, 1.0), 1.0) == 1);
+    assert!(crisp_factor((1.25, 1.25), 1.0) == 1);
+    assert!(crisp_factor((1.0, 1.5), 1.0) == 2);
+    assert!(crisp_factor((1.25, 1.25), 2.0) == 3);
+    assert!(crisp_factor((4.0, 4.0), 4.0) == 8);
+    assert!(crisp_factor((1.0, 1.0), 0.5) == 1);
+    assert!(crisp_factor((1.0, 1.0), f32::NAN) == 1);
+}
+
+#[test]
+fn test_crisp_text_keeps_layout() {
+    let mut window = Window::new("test", 64, 64);
+    let before = window.print_scaled("Hi", 0, 0, 2.0);
+    window.set_crisp_text(true);
+    window.set_scale(1.5, 1.5).unwrap();
+    assert!(window.print_scaled("Hi", 0, 0, 2.0) == before);
+    assert!(window.measure_text_ex("Hi", 2.0, 0) == (before.width(), before.height()));
+    let font = window.font.as_ref().unwrap();
+    assert!(font.upscaled.keys().collect::<Vec<_>>() == [&3]);
+    // unscaled text needs no enlarged copy
+    window.set_scale(1.0, 1.0).unwrap();
+    window.print("Hi", 0, 0);
+    assert!(window.font.as_ref().unwrap().upscaled.len() == 1);
+}
+
+#[test]
+fn test_ui_scale_for_dpi() {
+    assert!(ui_scale_for_dpi(None) == 1.0);
+    assert!(ui_scale_for_dpi(Some(96.0)) == 1.0);
+    assert!(ui_scale_for_dpi(Some(192.0)) == 2.0);
+    assert!(ui_scale_for_dpi(Some(140.0)) == 1.5);
+    assert!(ui_scale_for_dpi(Some(72.0)) == 1.0);
+    assert!(ui_scale_for_dpi(Some(1000.0)) == 4.0);
+    assert!(ui_scale_for_dpi(Some(f32::NAN)) == 1.0);
+}
+
+#[test]
+fn test_event_logger_sees_consumed_events() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut window = Window::new("Test", 8, 8);
+    let logged = Rc::new(RefCell::new(vec![]));
+    let log = logged.clone();
+    window.set_event_logger(move |e| log.borrow_mut().push(e.clone()));
+    window.add_event_hook(|e| match e {
+        Event::ControllerAdded { .. } => HookResult::Consume,
+        _ => HookResult::Pass,
+    });
+
+    window.queue_event(Event::ControllerAdded { id: 1 });
+    window.queue_event(Event::ControllerRemoved { id: 1 });
+    assert!(
+        *logged.borrow()
+            == [
+                Event::ControllerAdded { id: 1 },
+                Event::ControllerRemoved { id: 1 }
+            ]
+    );
+    assert!(window.next_event() == Event::ControllerRemoved { id: 1 });
+
+    window.clear_event_logger();
+    window.queue_event(Event::ControllerRemoved { id: 2 });
+    assert!(logged.borrow().len() == 2);
+}
+
+#[test]
+fn test_extreme_coordinates_do_not_overflow() {
+    let mut window = Window::new("Test", 16, 16);
+    let image = window
+        .image_from_decoded(&decode::DecodedImage {
+            width: 4,
+            height: 4,
+            pixels: vec![255; 4 * 4 * 4],
+        })
+        .unwrap();
+
+    for &(x, y) in &[
+        (i32::MAX, i32::MAX),
+        (i32::MIN, i32::MIN),
+        (i32::MAX, i32::MIN),
+    ] {
+        for &offset in &[(0, 0), (i32::MAX, i32::MAX), (i32::MIN, i32::MIN)] {
+            window.set_draw_offset(offset.0, offset.1);
+            window.print("far away\tand\ttabbed", x, y);
+            window.print_rtl("far away", x, y);
+            window.print_rotated("far away", x, y, 30.0);
+            window.print_centered("far away", shape::Point::new(x, y));
+            window.draw_image(&image, x, y);
+            window.draw_image_centered(&image, shape::Point::new(x, y));
+            window.fill_rect(shape::Rect::new(x, y, u32::MAX, u32::MAX));
+            window.draw_point(shape::Point::new(x, y));
+            window.caret_x("far away", 3, x);
+            window.char_index_at("far away", x, y);
+        }
+    }
+    window.set_draw_offset(0, 0);
+
+    // enormous scales and spacing saturate the width instead of overflowing it
+    let long = "W".repeat(1000);
+    assert!(window.measure_text_ex(&long, f32::MAX, i32::MAX).0 == i32::MAX as u32);
+    window.print_scaled(&long, 0, 0, 1e30);
+    window.print_spaced(&long, 0, 0, i32::MIN);
+    window.set_tab_width(u32::MAX);
+    window.print("\t\t\t", i32::MAX, 0);
+
+    let options = font::TextOptions {
+        line_spacing: f32::MAX,
+        clip: false,
+        ..font::TextOptions::default()
+    };
+    let rect = shape::Rect::new(i32::MAX, i32::MAX, 40, 40);
+    assert!(window.print_rect_with("many words wrap onto lines", rect, &options) > 1);
+    window.next_frame();
+}
+
+#[test]
+fn test_frame_guard_draws() {
+    let mut window = Window::new("Test", 16, 16);
+    let image = window
+        .image_from_decoded(&decode::DecodedImage {
+            width: 2,
+            height: 2,
+            pixels: vec![255; 2 * 2 * 4],
+        })
+        .unwrap();
+    window.set_auto_clear(false);
+    window.clear_to_color(0, 0, 0);
+
+    let mut g = window.begin_frame();
+    g.set_color(255, 255, 255, 255);
+    g.image(&image).at(4, 4).scale(3.0).draw().unwrap();
+    g.image(&image).at(0, 0).rotation(90.0).draw().unwrap();
+    assert!(g.image(&image).scale(0.0).draw().is_err());
+    assert!(g.image(&image).rotation(f64::NAN).draw().is_err());
+    g.finish();
+
+    let (pixels, _, _) = window
+        .read_pixels(Some(shape::Rect::new(4, 4, 7, 1)))
+        .unwrap();
+    let lit: Vec<bool> = pixels.chunks(4).map(|p| p[0] == 255).collect();
+    assert!(lit == [true, true, true, true, true, true, false]);
+    assert!(window.end_frame());
+}
+
+#[test]
+fn test_key_press_times() {
+    let mut window = Window::new("Test", 8, 8);
+    let press = |repeat| sdl2::event::Event::KeyDown {
+        timestamp: 0,
+        window_id: window.canvas.window().id(),
+        keycode: None,
+        scancode: Some(event::Key::Space),
+        keymod: sdl2::keyboard::Mod::empty(),
+        repeat,
+    };
+    let (first, repeated) = (press(false), press(true));
+    let events = window.sdl_context.event().unwrap();
+    events.push_event(first.clone()).unwrap();
+    events.push_event(repeated).unwrap();
+    events.push_event(first).unwrap();
+    window.begin_frame();
+
+    let times = window.key_press_times(event::Key::Space);
+    assert!(times.len() == 2);
+    assert!(times[0] <= times[1] && times[1] <= window.elapsed_seconds());
+    assert!(window.key_press_times(event::Key::A).is_empty());
+    window.begin_frame();
+    assert!(window.key_press_times(event::Key::Space).is_empty());
+}
+
+#[test]
+fn test_input_recording_round_trip() {
+    let path = std::env::temp_dir().join(format!("simple-recording-{}", std::process::id()));
+    let mut window = Window::new("Test", 8, 8);
+    let window_id = window.canvas.window().id();
+    let key = |down, scancode| {
+        let timestamp = 0;
+        let (keycode, keymod) = (None, sdl2::keyboard::Mod::empty());
+        let (scancode, repeat) = (Some(scancode), false);
+        match down {
+            true => sdl2::event::Event::KeyDown {
+                timestamp,
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+            },
+            false => sdl2::event::Event::KeyUp {
+                timestamp,
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+            },
+        }
+    };
+    let motion = |x, y| sdl2::event::Event::MouseMotion {
+        timestamp: 0,
+        window_id,
+        which: 0,
+        mousestate: sdl2::mouse::MouseState::from_sdl_state(0),
+        x,
+        y,
+        xrel: 1,
+        yrel: 0,
+    };
+    let frames = [
+        vec![key(true, event::Key::Space)],
+        vec![motion(1, 4), motion(3, 4)],
+        vec![key(false, event::Key::Space), key(true, event::Key::A)],
+    ];
+
+    // what each frame saw: its events, its key presses, its frame time and the mouse's velocity
+    type Observed = (Vec<Event>, usize, u32, (f32, f32));
+    fn observe(window: &mut Window) -> Observed {
+        let mut events = vec![];
+        while window.has_event() {
+            events.push(window.next_event());
+        }
+        let presses = window.key_presses.len();
+        (
+            events,
+            presses,
+            window.frame_time(),
+            window.mouse_velocity(),
+        )
+    }
+
+    window.start_input_recording(&path).unwrap();
+    let sdl_events = window.sdl_context.event().unwrap();
+    let mut recorded = vec![];
+    for frame in &frames {
+        for e in frame {
+            sdl_events.push_event(e.clone()).unwrap();
+        }
+        window.next_frame();
+        recorded.push(observe(&mut window));
+    }
+    window.stop_input_recording().unwrap();
+    assert!(recorded[2].0.len() == 2 && recorded[2].1 == 1);
+    assert!(recorded[1].3.0 > 0.0);
+
+    window.play_input_recording(&path).unwrap();
+    // real input is ignored while the recording plays
+    sdl_events.push_event(key(true, event::Key::B)).unwrap();
+    let mut replayed = vec![];
+    for _ in &frames {
+        window.next_frame();
+        replayed.push(observe(&mut window));
+    }
+    assert!(replayed == recorded);
+    assert!(window.is_playing_input_recording());
+    window.next_frame();
+    assert!(!window.is_playing_input_recording());
+
+    std::fs::write(&path, "simple input recording 99\n").unwrap();
+    let e = window.play_input_recording(&path).err().unwrap();
+    assert!(e.to_string().starts_with(&path.display().to_string()));
+    assert!(e.to_string().contains("version 99"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_image_async_wakes_wait_event() {
+    let path = std::env::temp_dir().join(format!("simple-async-{}.png", std::process::id()));
+    std::fs::write(&path, DEFAULT_FONT_BYTES).unwrap();
+    let mut window = Window::new("Test", 8, 8);
+    window.load_image_async("font".to_string(), &path);
+    window.load_image_async("missing".to_string(), path.with_extension("none"));
+
+    // nothing but the workers finishing wakes these up
+    let mut keys = vec![];
+    for _ in 0..2 {
+        match window.wait_event() {
+            Event::ImageLoaded { key } | Event::ImageLoadFailed { key, .. } => keys.push(key),
+            _ => panic!("woke up without a finished load"),
+        }
+    }
+    keys.sort();
+    assert!(keys == ["font", "missing"]);
+    assert!(window.take_image("font").is_some());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_image_resize() {
+    let window = Window::new("Test", 8, 8);
+    let image = window.load_image(DEFAULT_FONT_BYTES).unwrap();
+    let half = image
+        .resize(&window, image.get_width() / 2, 8, ScaleQuality::Linear)
+        .unwrap();
+    assert!(half.get_width() == image.get_width() / 2 && half.get_height() == 8);
+    let big = image.resize(&window, 3, 40, ScaleQuality::Nearest).unwrap();
+    assert!((big.get_width(), big.get_height()) == (3, 40));
+    assert!(image.resize(&window, 0, 8, ScaleQuality::Best).is_err());
+}
+
+#[test]
+fn test_image_hit_test() {
+    let mut window = Window::new("Test", 8, 8);
+    // a 2x2 image whose right column is only faintly visible
+    let pixels = [255, 255, 255, 255, 255, 255, 255, 20].repeat(2);
+    let image = window
+        .image_from_surface(util::rgba_surface_from_bytes(&pixels, 2, 2).unwrap())
+        .unwrap();
+    assert!(window.image_hit_test(&image, (3, 4), (3, 5), 128));
+    assert!(!window.image_hit_test(&image, (3, 4), (4, 5), 128));
+    assert!(window.image_hit_test(&image, (3, 4), (4, 5), 20));
+    assert!(!window.image_hit_test(&image, (3, 4), (2, 4), 0));
+    assert!(!window.image_hit_test(&image, (3, 4), (3, 6), 0));
+    assert!(!window.image_hit_test(&image, (i32::MAX, 0), (i32::MIN, 0), 0));
+
+    window.set_viewport(Some(shape::Rect::new(2, 0, 4, 4)));
+    window.set_draw_offset(1, 0);
+    assert!(window.image_hit_test(&image, (0, 0), (3, 0), 128));
+    assert!(!window.image_hit_test(&image, (0, 0), (0, 0), 0));
+
+    // at scale 2 the image covers Window pixels 6..10, mapped as mouse_position maps them
+    window.set_viewport(None);
+    window.set_draw_offset(0, 0);
+    window.set_scale(2.0, 2.0).unwrap();
+    let at = |window: &Window, p: (i32, i32)| window.point_mapping().to_drawing(p);
+    assert!(window.image_hit_test(&image, (3, 4), at(&window, (7, 9)), 128));
+    assert!(!window.image_hit_test(&image, (3, 4), at(&window, (8, 9)), 128));
+    assert!(!window.image_hit_test(&image, (3, 4), at(&window, (5, 9)), 0));
+
+    // a 4x2 design in the 8x8 Window is letterboxed by 2 pixels at the top
+    window.enable_pixel_perfect(4, 2).unwrap();
+    assert!(window.image_hit_test(&image, (1, 0), at(&window, (3, 3)), 128));
+    assert!(!window.image_hit_test(&image, (1, 0), at(&window, (3, 1)), 0));
+}
+
+#[test]
+fn test_image_collision_mask() {
+    let window = Window::new("Test", 8, 8);
+    // a 2x2 image whose right column is transparent
+    let pixels = [255, 255, 255, 255, 0, 0, 0, 0].repeat(2);
+    let image = window
+        .image_from_decoded(&decode::DecodedImage {
+            width: 2,
+            height: 2,
+            pixels,
+        })
+        .unwrap();
+    let mask = image.collision_mask(128);
+    assert!(mask.is_solid(0, 1) && !mask.is_solid(1, 1));
+    assert!(mask.overlaps(&mask, 0, 1));
+    assert!(!mask.overlaps(&mask, 1, 0));
+    assert!(image.collision_mask(0).overlaps(&mask, 1, 0));
+}
+
+#[test]
+fn test_mix_colors() {
+    let (black, white) = (
+        pixels::Color::RGBA(0, 0, 0, 0),
+        pixels::Color::RGBA(255, 255, 255, 255),
+    );
+    assert!(mix_colors(black, white, 0.0) == black);
+    assert!(mix_colors(black, white, 1.5) == white);
+    assert!(mix_colors(black, white, 0.5) == pixels::Color::RGBA(128, 128, 128, 128));
+    assert!(mix_colors(white, black, f32::NAN) == white);
+    // rows of a glyph stretched over more screen rows than it has cover every one of them
+    let dst = shape::Rect::new(0, 10, 4, 5);
+    let edges: Vec<i32> = (0..=3).map(|row| glyph_row_edge(dst, 3, row)).collect();
+    assert!(edges == [10, 11, 13, 15]);
+}
+
+#[test]
+fn test_gradient_and_textured_text() {
+    let mut window = Window::new("Test", 64, 32);
+    let plain = window.print("Hi", 3, 4);
+    window.clear();
+    let gradient = window.print_gradient("Hi", 3, 4, (255, 0, 0, 255), (0, 0, 255, 255));
+    assert!(gradient == plain);
+    let (pixels, width, _) = window.read_pixels(None).unwrap();
+    let lit: Vec<(usize, &[u8])> = pixels
+        .chunks(4)
+        .enumerate()
+        .filter(|(_, p)| p[0] > 0 || p[2] > 0)
+        .map(|(i, p)| (i / width as usize, p))
+        .collect();
+    let (first, last) = (lit[0], lit[lit.len() - 1]);
+    assert!(first.0 < last.0);
+    assert!(first.1[0] > first.1[2] && last.1[2] > last.1[0]);
+
+    // a green pattern, whatever the Window's color
+    let mut pattern = window
+        .image_from_surface(
+            util::rgba_surface_from_bytes(&[0, 255, 0, 255].repeat(4), 2, 2).unwrap(),
+        )
+        .unwrap();
+    window.clear();
+    window.set_color(255, 0, 0, 255);
+    pattern.texture.set_blend_mode(BlendMode::Add);
+    assert!(window.print_textured("Hi", 3, 4, &mut pattern) == plain);
+    let (pixels, _, _) = window.read_pixels(None).unwrap();
+    assert!(pixels.chunks(4).any(|p| p[1] > 0));
+    assert!(pixels.chunks(4).all(|p| p[0] == 0 && p[2] == 0));
+    // the pattern's own blend mode is given back
+    assert!(pattern.texture.blend_mode() == BlendMode::Add);
+    assert!(window.print_textured("", 3, 4, &mut pattern) == window.print("", 3, 4));
+}
+
+#[test]
+fn test_slow_frame_callback() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut window = Window::new("Test", 8, 8);
+    let slow = Rc::new(RefCell::new(vec![]));
+    let log = slow.clone();
+    window.on_slow_frame(100, move |ms| log.borrow_mut().push(ms));
+    // the first frame counts from when SDL started, so it is never reported
+    thread::sleep(std::time::Duration::from_millis(150));
+    window.next_frame();
+    assert!(slow.borrow().is_empty());
+
+    window.next_frame();
+    assert!(slow.borrow().is_empty());
+    thread::sleep(std::time::Duration::from_millis(150));
+    window.next_frame();
+    assert!(slow.borrow().len() == 1 && slow.borrow()[0] >= 150);
+    assert!(slow.borrow()[0] == window.frame_time());
+
+    window.clear_slow_frame_callback();
+    thread::sleep(std::time::Duration::from_millis(150));
+    window.next_frame();
+    assert!(slow.borrow().len() == 1);
+}
+
+#[test]
+fn test_crossed_period() {
+    // 16ms frames against a 100ms period: true once per period, in the frame that reaches it
+    let frames: Vec<u32> = (0..20).map(|i| i * 16).collect();
+    let due: Vec<u32> = frames
+        .windows(2)
+        .filter(|w| crossed_period(w[0], w[1], 100))
+        .map(|w| w[1])
+        .collect();
+    assert!(due == [112, 208, 304]);
+    // a frame that ends exactly on a multiple counts, and the next one doesn't
+    assert!(crossed_period(84, 100, 100) && !crossed_period(100, 116, 100));
+    // a long frame spanning several periods counts once
+    assert!(crossed_period(50, 450, 100));
+    assert!(!crossed_period(0, 0, 100));
+    assert!(crossed_period(7, 7, 0));
+}
+
+#[test]
+fn test_frame_count() {
+    let mut window = Window::new("Test", 8, 8);
+    assert!(window.frame_count() == 0);
+    assert!(window.every_n_frames(3) && window.every_n_frames(0));
+    assert!(!window.every_millis(0));
+    let mut every_third = vec![];
+    for _ in 0..7 {
+        assert!(window.next_frame());
+        every_third.push(window.every_n_frames(3));
+    }
+    assert!(window.frame_count() == 7);
+    assert!(every_third == [false, false, true, false, false, true, false]);
+    assert!(window.every_millis(0));
+    // the periods are counted on the frame times, not on the real clock
+    (window.frame_clock, window.ticks_in_previous_frame) = (250, 100);
+    assert!(window.every_millis(200) && !window.every_millis(300));
+
+    window.quit();
+    assert!(!window.next_frame());
+    assert!(!window.end_frame());
+    assert!(window.frame_count() == 7);
+}
+
+#[test]
+fn test_images_outlive_their_window() {
+    let mut window = Window::new("Test", 8, 8);
+    let mut image = window.load_image(DEFAULT_FONT_BYTES).unwrap();
+    let font = window
+        .load_font(DEFAULT_FONT_BYTES, DEFAULT_FONT_STR.to_string())
+        .unwrap();
+    // a layer may still draw a dropped texture, so it waits for the frame to be shown
+    let dropped = window.load_image(DEFAULT_FONT_BYTES).unwrap();
+    window.layer(1);
+    window.draw_image(&dropped, 0, 0);
+    drop(dropped);
+    assert!(window.textures.dropped_count() == 1);
+    window.next_frame();
+    assert!(window.textures.dropped_count() == 0);
+    assert!(window.draw_stats().texture_copies == 1);
+    // the font's pixels are only kept once its Window closes
+    assert!(font.pixels.borrow().is_none());
+    drop(window);
+    assert!(font.pixels.borrow().is_some());
+
+    let mut window = Window::new("Test", 8, 8);
+    window.draw_image(&image, 0, 0);
+    assert!(window.current_draw_stats().texture_copies == 0);
+    window.adopt_image(&mut image).unwrap();
+    window.draw_image(&image, 0, 0);
+    assert!(window.current_draw_stats().texture_copies == 1);
+    window.set_font(font);
+    window.print("ab", 0, 0);
+    assert!(window.current_draw_stats().glyphs == 2);
+    drop(window);
+    drop(image);
+}
+
+#[test]
+fn test_image_blend_modes() {
+    let mut window = Window::new("Test", 4, 4);
+    let solid = |rgb: [u8; 3], mode| {
+        let pixels = [rgb[0], rgb[1], rgb[2], 255].repeat(4);
+        let surf = util::rgba_surface_from_bytes(&pixels, 2, 2).unwrap();
+        let mut image = window.image_from_surface(surf).unwrap();
+        image.set_blend_mode(mode);
+        image
+    };
+    let sprite = solid([100, 100, 100], None);
+    let shadow = solid([128, 128, 128], Some(BlendMode::Mod));
+    let glow = solid([20, 0, 0], Some(BlendMode::Add));
+    assert!(sprite.blend_mode().is_none() && glow.blend_mode() == Some(BlendMode::Add));
+
+    window.clear();
+    window.draw_image(&sprite, 0, 0);
+    window.draw_image(&shadow, 0, 0);
+    window.draw_image(&glow, 0, 0);
+    assert!(window.canvas.blend_mode() == BlendMode::Blend);
+    let (pixels, _, _) = window.read_pixels(None).unwrap();
+    let close = |got: u8, want: u8| got.abs_diff(want) <= 1;
+    assert!(close(pixels[0], 70) && close(pixels[1], 50) && close(pixels[2], 50));
+
+    // without a mode of its own, an Image follows the Window's
+    window.clear();
+    window.set_blend_mode(BlendMode::Add);
+    window.draw_image(&sprite, 0, 0);
+    window.draw_image(&sprite, 0, 0);
+    let (pixels, _, _) = window.read_pixels(None).unwrap();
+    assert!(close(pixels[0], 200));
+}
+
+#[test]
+fn test_print_template() {
+    let mut window = Window::new("Test", 128, 32);
+    let subs = [("key:JUMP", "XX", Some((255, 0, 0, 255)))];
+    let plain = window.print("Press XX {odd}", 0, 0);
+    window.clear();
+    window.set_color(255, 255, 255, 255);
+    assert!(window.print_template("Press {key:JUMP} {odd}", &subs, 0, 0) == plain);
+    assert!(window.unknown_template_tokens.contains("odd"));
+    let (pixels, width, _) = window.read_pixels(None).unwrap();
+    let at = |i: usize| (i % width as usize) as i32;
+    let red: Vec<i32> = (0..pixels.len() / 4)
+        .filter(|&i| pixels[i * 4..i * 4 + 3] == [255, 0, 0])
+        .map(at)
+        .collect();
+    let start = window.measure_text("Press ").0 as i32;
+    let end = window.measure_text("Press XX").0 as i32;
+    assert!(!red.is_empty() && red.iter().all(|&x| (start..end).contains(&x)));
+    assert!((0..pixels.len() / 4).any(|i| pixels[i * 4..i * 4 + 3] == [255, 255, 255]));
+
+    // wrapped, the value keeps its color on the line it moves to
+    window.clear();
+    let rect = shape::Rect::new(0, 0, start as u32, 32);
+    assert!(window.print_template_rect("Press {key:JUMP}", &subs, rect) == 2);
+    let (pixels, width, _) = window.read_pixels(None).unwrap();
+    let line_height = window.measure_text("").1 as usize;
+    let red_rows: Vec<usize> = (0..pixels.len() / 4)
+        .filter(|&i| pixels[i * 4..i * 4 + 3] == [255, 0, 0])
+        .map(|i| i / width as usize)
+        .collect();
+    assert!(!red_rows.is_empty() && red_rows.iter().all(|&row| row >= line_height));
+}
+
+#[test]
+fn test_render_targets() {
+    let mut window = Window::new("Test", 16, 16);
+    let mut baked = window.create_render_target(8, 8).unwrap();
+    assert!(baked.is_render_target());
+    assert!(window.create_render_target(0, 8).is_err());
+    let bake = |window: &mut Window, baked: &mut Image| {
+        window
+            .draw_to_image(baked, |w| {
+                w.fill_rect_color(shape::Rect::new(0, 0, 2, 8), (255, 0, 0, 255));
+                assert!(
+                    w.draw_to_image(&mut w.create_render_target(1, 1).unwrap(), |_| ())
+                        .is_err()
+                );
+            })
+            .unwrap()
+    };
+    // the offset and viewport don't apply inside, and are put back afterwards
+    window.set_draw_offset(4, 4);
+    bake(&mut window, &mut baked);
+    assert!(window.draw_offset == (4, 4));
+    window.set_draw_offset(0, 0);
+    assert!(util::rgba_surface_bytes(&baked.surface)[..8] == [255, 0, 0, 255, 0, 0, 0, 0]);
+
+    let first_pixel = |window: &mut Window, image: &Image| {
+        window.clear();
+        window.draw_image(image, 0, 0);
+        window.read_pixels(None).unwrap().0[..3].to_vec()
+    };
+    assert!(first_pixel(&mut window, &baked) == [255, 0, 0]);
+    baked.invalidate();
+    assert!(first_pixel(&mut window, &baked) == [255, 0, 255]);
+    bake(&mut window, &mut baked);
+    assert!(first_pixel(&mut window, &baked) == [255, 0, 0]);
+    window.lose_render_targets();
+    assert!(first_pixel(&mut window, &baked) == [255, 0, 255]);
+    bake(&mut window, &mut baked);
+    assert!(first_pixel(&mut window, &baked) == [255, 0, 0]);
+
+    // a reset that wait_event takes loses the targets as well
+    let mut reset: sdl2::sys::SDL_Event = unsafe { std::mem::zeroed() };
+    reset.type_ = sdl2::sys::SDL_EventType::SDL_RENDER_TARGETS_RESET as u32;
+    assert!(unsafe { sdl2::sys::SDL_PushEvent(&mut reset) } == 1);
+    assert!(window.wait_event() == Event::RenderTargetsLost);
+    assert!(first_pixel(&mut window, &baked) == [255, 0, 255]);
+    bake(&mut window, &mut baked);
+
+    // a viewport, clip rect or scale set inside doesn't limit what is read back
+    window
+        .draw_to_image(&mut baked, |w| {
+            w.fill_rect_color(shape::Rect::new(0, 0, 8, 8), (0, 0, 255, 255));
+            w.set_viewport(Some(shape::Rect::new(2, 2, 2, 2)));
+            w.set_clip_rect(Some(shape::Rect::new(0, 0, 1, 1)));
+            w.set_scale(2.0, 2.0).unwrap();
+        })
+        .unwrap();
+    let bytes = util::rgba_surface_bytes(&baked.surface);
+    assert!(bytes.len() == 8 * 8 * 4);
+    assert!(bytes.chunks(4).all(|p| p == [0, 0, 255, 255]));
+    assert!(window.viewport.is_none() && window.canvas.scale() == (1.0, 1.0));
+
+    // only render targets can be drawn to, and invalidating anything else does nothing
+    let surf = util::new_rgba_surface(2, 2).unwrap();
+    let mut plain = window.image_from_surface(surf).unwrap();
+    plain.invalidate();
+    assert!(!plain.is_render_target());
+    assert!(window.draw_to_image(&mut plain, |_| ()).is_err());
 }